@@ -6,19 +6,26 @@ use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::ffi::c_void;
+use std::future::Future;
 use std::io::{self, Read, Write};
 use std::mem::forget;
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::os::raw::c_char;
 use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::pin::Pin;
 use std::rc::Rc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 
 use core::ptr::null_mut;
 
 use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::fiber::{unpack_callback, Cond};
+use crate::fiber::r#async::oneshot;
+use crate::fiber::{unpack_callback, Cond, UnitJoinHandle};
 
 const TIMEOUT_INFINITY: f64 = 365.0 * 86400.0 * 100.0;
 
@@ -64,6 +71,15 @@ impl CoIOStream {
         })
     }
 
+    /// Connect to a unix domain socket at `path`.
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> Result<CoIOStream, io::Error> {
+        let inner_stream = UnixStream::connect(path)?;
+        inner_stream.set_nonblocking(true)?;
+        Ok(CoIOStream {
+            fd: inner_stream.into_raw_fd(),
+        })
+    }
+
     /// Pull some bytes from this source into the specified buffer. Returns how many bytes were read or 0 on timeout.
     pub fn read_with_timeout(
         &mut self,
@@ -166,8 +182,15 @@ impl TryFrom<TcpListener> for CoIOListener {
 /// - `fd` - non-blocking socket file description
 /// - `events` - requested events to wait. Combination of [CoIOFlags::READ | CoIOFlags::WRITE](struct.CoIOFlags.html) bit flags.
 /// - `timeoout` - timeout in seconds.
+///
+/// If the fiber is cancelled returns an error for which
+/// [`Cancelled::is_cause_of`] returns `true`.
+///
+/// [`Cancelled::is_cause_of`]: crate::fiber::Cancelled::is_cause_of
 pub fn coio_wait(fd: RawFd, flags: ffi::CoIOFlags, timeout: f64) -> Result<(), io::Error> {
+    crate::fiber::Cancelled::check()?;
     match unsafe { ffi::coio_wait(fd, flags.bits(), timeout) } {
+        0 if crate::fiber::is_cancelled() => Err(crate::fiber::Cancelled.into()),
         0 => Err(io::ErrorKind::TimedOut.into()),
         _ => Ok(()),
     }
@@ -202,6 +225,146 @@ where
     unsafe { ffi::coio_call(trampoline, callback_ptr, Box::into_raw(Box::<T>::new(arg))) }
 }
 
+/// Runs `f` in the coio thread pool returning its result. The current fiber
+/// **yields** until `f` returns, while other fibers keep running on the TX
+/// thread. Panics in `f` are propagated to the caller.
+///
+/// Returns an error if the coio task couldn't be created, in which case `f`
+/// isn't called.
+///
+/// Use this to offload CPU heavy computations or blocking system calls, e.g.
+/// hashing, compression or file IO. `f` runs in another thread, so it must
+/// not call any tarantool API (fibers, box, lua, etc.).
+///
+/// The waiting fiber can't be interrupted, e.g. by cancelling it, until `f`
+/// returns.
+///
+/// ```no_run
+/// use tarantool::coio::run_blocking;
+///
+/// let data = vec![0u8; 1 << 20];
+/// let sum: u64 = run_blocking(|| data.iter().map(|&b| b as u64).sum()).unwrap();
+/// ```
+///
+/// # Panics
+/// Panics if `f` panics.
+pub fn run_blocking<F, T>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    match call_blocking(f)? {
+        Ok(v) => Ok(v),
+        Err(e) => panic::resume_unwind(e),
+    }
+}
+
+/// Runs `f` in the coio thread pool, returning a future for its result, which
+/// resolves to the value returned by `f` or resumes the panic if `f` panicked.
+/// The future resolves to an error if the coio task couldn't be created.
+///
+/// The task starts immediately in a helper fiber, so the future doesn't need
+/// to be polled for the task to make progress. Dropping the future doesn't
+/// stop the task. See [`run_blocking`] for details.
+///
+/// ```no_run
+/// use tarantool::{coio, fiber};
+///
+/// let contents = fiber::block_on(async {
+///     coio::spawn_blocking(|| std::fs::read("/etc/hostname")).await
+/// });
+/// ```
+///
+/// # Panics
+/// Panics if the helper fiber couldn't be created.
+pub fn spawn_blocking<F, T>(f: F) -> BlockingHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let fiber = crate::fiber::Builder::new()
+        .name("spawn_blocking")
+        .proc(move || {
+            let _ = tx.send(call_blocking(f));
+        })
+        .start()
+        .expect("failed to start a fiber");
+    BlockingHandle {
+        rx,
+        fiber: Some(fiber),
+    }
+}
+
+/// Runs `f` in the coio thread pool. Returns an error if the task couldn't be
+/// created, otherwise the result of `f` or the payload of its panic.
+fn call_blocking<F, T>(f: F) -> io::Result<std::thread::Result<T>>
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    let mut f = Some(f);
+    let mut result = None;
+    let mut callback = |_: Box<()>| {
+        let f = f.take().expect("callback is only called once");
+        result = Some(panic::catch_unwind(AssertUnwindSafe(f)));
+        0
+    };
+    let rc = coio_call(&mut callback, ());
+    match result {
+        Some(res) => Ok(res),
+        // The callback isn't called only if the task couldn't be created
+        None if rc == -1 => Err(io::Error::last_os_error()),
+        None => unreachable!("coio_call returns after the callback"),
+    }
+}
+
+/// Future returned by [`spawn_blocking`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BlockingHandle<T> {
+    rx: oneshot::Receiver<io::Result<std::thread::Result<T>>>,
+    fiber: Option<UnitJoinHandle<'static>>,
+}
+
+impl<T> Future for BlockingHandle<T> {
+    type Output = io::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        let res = match Pin::new(&mut self.rx).poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        // The helper fiber has finished, so this doesn't yield
+        if let Some(fiber) = self.fiber.take() {
+            fiber.join();
+        }
+        match res.expect("the helper fiber always sends the result") {
+            Ok(Ok(v)) => Poll::Ready(Ok(v)),
+            Ok(Err(e)) => panic::resume_unwind(e),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl<T> Drop for BlockingHandle<T> {
+    fn drop(&mut self) {
+        if let Some(fiber) = self.fiber.take() {
+            if self.rx.is_closed() {
+                // The result has been sent, the fiber is finished
+                fiber.join()
+            } else {
+                fiber.detach()
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for BlockingHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockingHandle").finish_non_exhaustive()
+    }
+}
+
 /// Fiber-friendly version of `getaddrinfo(3)`.
 ///
 /// - `host` - host name, i.e. "tarantool.org"
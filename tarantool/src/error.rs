@@ -27,6 +27,7 @@ use rmp::decode::{MarkerReadError, NumValueReadError, ValueReadError};
 use rmp::encode::ValueWriteError;
 
 use crate::ffi::tarantool as ffi;
+pub use crate::network::protocol::UnsupportedFeature;
 use crate::tlua::LuaError;
 
 /// A specialized [`Result`] type for the crate
@@ -77,9 +78,15 @@ pub enum Error {
     #[error("Lua error: {0}")]
     LuaError(LuaError),
 
-    #[cfg(feature = "schema")]
+    #[error("Unsupported: {0}")]
+    Unsupported(UnsupportedFeature),
+
     #[error("Space metadata not found")]
     MetaNotFound,
+
+    #[cfg(feature = "tls")]
+    #[error("TLS error: {0}")]
+    Tls(crate::tls::Error),
 }
 
 impl From<io::Error> for Error {
@@ -157,6 +164,19 @@ impl From<LuaError> for Error {
     }
 }
 
+impl From<UnsupportedFeature> for Error {
+    fn from(error: UnsupportedFeature) -> Self {
+        Error::Unsupported(error)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl From<crate::tls::Error> for Error {
+    fn from(error: crate::tls::Error) -> Self {
+        Error::Tls(error)
+    }
+}
+
 /// Transaction-related error cases
 #[derive(Debug, thiserror::Error)]
 pub enum TransactionError {
@@ -0,0 +1,42 @@
+//! Box: events
+//!
+//! Tarantool instances can broadcast values associated with string keys.
+//! Local and remote watchers subscribed to a key are notified every time a new
+//! value is broadcast for it.
+//!
+//! See also:
+//! - [Lua reference: Event watchers](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_events/)
+use std::os::raw::c_char;
+
+use serde::Serialize;
+
+use crate::error::{Error, TarantoolError};
+use crate::ffi::tarantool as ffi;
+
+/// Update the value of `key` and notify all the watchers of the key,
+/// including the remote ones subscribed via `IPROTO_WATCH`.
+///
+/// Keys starting with `box.` are reserved for system events and must not be
+/// used.
+///
+/// This is the equivalent of `box.broadcast(key, value)`. Requires tarantool
+/// 2.10 or later, see [`has_broadcast`](crate::ffi::has_broadcast).
+pub fn broadcast<T>(key: &str, value: &T) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    let data = rmp_serde::to_vec_named(value)?;
+    let data_ptr = data.as_ptr() as *const c_char;
+    let rc = unsafe {
+        ffi::box_broadcast(
+            key.as_ptr() as *const c_char,
+            key.len(),
+            data_ptr,
+            data_ptr.add(data.len()),
+        )
+    };
+    if rc < 0 {
+        return Err(TarantoolError::last().into());
+    }
+    Ok(())
+}
@@ -32,6 +32,15 @@ pub fn has_fiber_channel() -> bool {
     }
 }
 
+/// Check whether the current tarantool executable supports broadcasting events.
+/// If this function returns `false` calling [`tarantool::event::broadcast`]
+/// will result in a **panic**.
+///
+/// [`tarantool::event::broadcast`]: crate::event::broadcast
+pub fn has_broadcast() -> bool {
+    unsafe { helper::has_dyn_symbol(crate::c_str!("box_broadcast")) }
+}
+
 /// Check whether the current tarantool executable supports getting tuple fields
 /// by json pattern.
 /// If this function returns `false` then
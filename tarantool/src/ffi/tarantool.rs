@@ -302,6 +302,39 @@ extern "C" {
     pub fn fiber_cond_wait(cond: *mut FiberCond) -> c_int;
 }
 
+crate::define_dlsym_reloc! {
+    /// Return the current fiber.
+    pub fn fiber_self() -> *mut Fiber;
+
+    /// Return the id of the fiber `f` or of the current fiber if `f` is null.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn fiber_id(f: *const Fiber) -> u64;
+
+    /// Return the fiber with the given id or null if there's no such fiber.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn fiber_find(fid: u64) -> *mut Fiber;
+
+    /// Return the name of the fiber `f` or of the current fiber if `f` is
+    /// null. The string is valid until the fiber is renamed or destroyed.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn fiber_name(f: *const Fiber) -> *const c_char;
+
+    /// Set the name of the fiber `f` or of the current fiber if `f` is null.
+    /// Names longer than the limit are truncated.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn fiber_set_name_n(f: *mut Fiber, name: *const c_char, len: u32);
+
+    /// Return the number of context switches of the fiber `f` or of the
+    /// current fiber if `f` is null.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn fiber_csw(f: *const Fiber) -> u64;
+}
+
 /// list entry and head structure
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -637,6 +670,38 @@ extern "C" {
     pub fn box_session_push(data: *const c_char, data_end: *const c_char) -> c_int;
 }
 
+crate::define_dlsym_reloc! {
+    /// Return the id of the current session.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn box_session_id() -> u64;
+
+    /// Get the id of the user of the current session and store it in `uid`.
+    /// Returns `-1` on error.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn box_session_user_id(uid: *mut u32) -> c_int;
+
+    /// Return the id of the effective user of the current fiber.
+    ///
+    /// Available since tarantool 2.11.
+    pub fn box_effective_user_id() -> u32;
+}
+
+// Events.
+crate::define_dlsym_reloc! {
+    /// Update the value of a particular key and notify all key watchers of
+    /// the update. `data` must be a single valid msgpack value.
+    ///
+    /// Available since tarantool 2.10.
+    pub fn box_broadcast(
+        key: *const c_char,
+        key_len: usize,
+        data: *const c_char,
+        data_end: *const c_char,
+    ) -> c_int;
+}
+
 // Sequence.
 extern "C" {
     pub fn box_sequence_next(seq_id: u32, result: *mut i64) -> c_int;
@@ -647,10 +712,20 @@ extern "C" {
 // Transaction.
 extern "C" {
     pub fn box_txn() -> bool;
+    /// Return the id of the current transaction or `-1` if there's no active
+    /// transaction.
+    pub fn box_txn_id() -> i64;
     pub fn box_txn_begin() -> c_int;
     pub fn box_txn_commit() -> c_int;
     pub fn box_txn_rollback() -> c_int;
     pub fn box_txn_alloc(size: usize) -> *mut c_void;
+    pub fn box_txn_savepoint() -> *mut BoxTxnSavepoint;
+    pub fn box_txn_rollback_to_savepoint(savepoint: *mut BoxTxnSavepoint) -> c_int;
+}
+
+#[repr(C)]
+pub struct BoxTxnSavepoint {
+    _unused: [u8; 0],
 }
 
 // Indexes, spaces and tuples.
@@ -40,8 +40,23 @@ pub use channel::{
 pub mod mutex;
 use crate::ffi::tarantool::fiber_sleep;
 pub use mutex::Mutex;
+
+pub mod cancel;
+pub use cancel::{CancellationToken, Cancelled, IntoCancellable};
+
+pub mod local;
+pub use local::FiberLocal;
+
+pub mod pool;
+pub use pool::Pool;
+
+mod introspection;
+pub use introspection::{id, info, name, set_name, FiberId, FiberInfo, FiberStatus};
 pub use r#async::block_on;
 
+mod scope;
+pub use scope::{scope, Scope, ScopedJoinHandle};
+
 mod csw;
 pub use csw::check_yield;
 pub use csw::csw;
@@ -469,6 +484,7 @@ where
         let a = C::parse_args(args);
         I::before_callee();
         C::invoke(a);
+        local::on_fiber_exit();
         0
     }
 }
@@ -561,6 +577,7 @@ where
 
         // call f and drop it afterwards
         let res = f();
+        local::on_fiber_exit();
 
         // return results to lua
         C::save_result(l, res)
@@ -1123,6 +1140,33 @@ impl<'f> UnitJoinHandle<'f> {
         let inner_raw = self.inner.take().unwrap().as_ptr();
         let _code = unsafe { ffi::fiber_join(inner_raw) };
     }
+
+    /// Interrupt the fiber's sleep or blocking operation.
+    ///
+    /// **NOTE** the fiber must not have finished yet.
+    pub(crate) fn wakeup(&self) {
+        if let Some(inner) = self.inner {
+            unsafe { ffi::fiber_wakeup(inner.as_ptr()) }
+        }
+    }
+
+    /// Cancel the fiber without waiting for it to finish, see
+    /// [`Fiber::cancel`].
+    pub(crate) fn cancel(&self) {
+        if let Some(inner) = self.inner {
+            unsafe { ffi::fiber_cancel(inner.as_ptr()) }
+        }
+    }
+
+    /// Make the fiber non-joinable, so that it's recycled as soon as it
+    /// finishes.
+    ///
+    /// **NOTE** the fiber must not have finished yet.
+    pub(crate) fn detach(mut self) {
+        if let Some(inner) = self.inner.take() {
+            unsafe { ffi::fiber_set_joinable(inner.as_ptr(), false) }
+        }
+    }
 }
 
 impl<'f> Drop for UnitJoinHandle<'f> {
@@ -1316,6 +1360,15 @@ pub fn sleep(time: Duration) {
     unsafe { ffi::fiber_sleep(time.as_secs_f64()) }
 }
 
+/// Same as [`sleep`], but returns `Err(Cancelled)` as soon as the fiber is
+/// cancelled (e.g. via a [`CancellationToken`]) instead of sleeping through
+/// the cancellation.
+pub fn sleep_cancellable(time: Duration) -> std::result::Result<(), Cancelled> {
+    Cancelled::check()?;
+    sleep(time);
+    Cancelled::check()
+}
+
 /// Report loop begin time as double (cheap).
 pub fn time() -> f64 {
     unsafe { ffi::fiber_time() }
@@ -1500,6 +1553,27 @@ impl Cond {
     pub fn wait(&self) -> bool {
         unsafe { ffi::fiber_cond_wait(self.inner) >= 0 }
     }
+
+    /// Same as [`Cond::wait_timeout`], but returns `Err(Cancelled)` if the
+    /// fiber was cancelled (e.g. via a [`CancellationToken`]) and `Ok(false)`
+    /// on timeout.
+    pub fn wait_timeout_cancellable(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<bool, Cancelled> {
+        Cancelled::check()?;
+        let res = self.wait_timeout(timeout);
+        Cancelled::check()?;
+        Ok(res)
+    }
+
+    /// Same as [`Cond::wait`], but returns `Err(Cancelled)` if the fiber was
+    /// cancelled (e.g. via a [`CancellationToken`]).
+    pub fn wait_cancellable(&self) -> std::result::Result<(), Cancelled> {
+        Cancelled::check()?;
+        self.wait();
+        Cancelled::check()
+    }
 }
 
 impl Default for Cond {
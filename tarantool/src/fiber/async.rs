@@ -1,17 +1,35 @@
 use std::{
     future::Future,
     pin::Pin,
-    rc::Rc,
     task::Poll,
     time::{Duration, Instant},
 };
 
 use futures::pin_mut;
 
+pub mod broadcast;
+pub mod mpsc;
 pub mod oneshot;
+mod reactor;
+pub mod sync;
+pub mod task;
 pub mod timeout;
+pub mod timer;
 pub mod watch;
 
+pub use task::{spawn, JoinError, JoinHandle, JoinSet};
+
+/// Polls multiple futures simultaneously, returning a tuple of all results
+/// once complete. Re-exported from the [`futures`] crate.
+pub use futures::join;
+
+/// Polls multiple futures simultaneously, resolving to the first one to
+/// complete. Re-exported from the [`futures`] crate.
+///
+/// The futures must implement [`FusedFuture`](futures::future::FusedFuture),
+/// which can be achieved with [`FutureExt::fuse`](futures::FutureExt::fuse).
+pub use futures::select;
+
 /// Error that happens on the receiver side of the channel.
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 #[error("sender dropped")]
@@ -19,14 +37,27 @@ pub struct RecvError;
 
 mod waker {
     use crate::fiber;
+    use std::cell::Cell;
+    use std::marker::PhantomData;
+    use std::os::unix::io::RawFd;
     use std::rc::Rc;
     use std::task::RawWaker;
     use std::task::RawWakerVTable;
     use std::task::Waker;
 
+    /// A reference counted object which can be turned into a [`Waker`] with
+    /// [`with_rcw`].
+    pub trait RcWake {
+        fn wake(&self);
+    }
+
     #[derive(Default)]
     pub struct FiberWaker {
         cond: fiber::Cond,
+        is_woken: Cell<bool>,
+        /// Write end of the pipe interrupting the fiber's `coio_wait`, set
+        /// while the fiber is blocked in it.
+        io_wake_fd: Cell<Option<RawFd>>,
     }
 
     impl FiberWaker {
@@ -34,55 +65,79 @@ mod waker {
             &self.cond
         }
 
-        pub fn wake(&self) {
-            self.cond.broadcast()
+        /// Make the waker interrupt the fiber's `coio_wait` by writing to
+        /// `fd`. Pass `None` once the wait is over.
+        pub fn set_io_wake_fd(&self, fd: Option<RawFd>) {
+            self.io_wake_fd.set(fd);
+        }
+
+        /// Returns `true` if the waker was woken since the last call to this
+        /// function.
+        pub fn take_woken(&self) -> bool {
+            self.is_woken.replace(false)
+        }
+    }
+
+    impl RcWake for FiberWaker {
+        fn wake(&self) {
+            self.is_woken.set(true);
+            self.cond.broadcast();
+            if let Some(fd) = self.io_wake_fd.take() {
+                // A single byte is enough to interrupt the wait
+                unsafe { libc::write(fd, [1_u8].as_ptr().cast(), 1) };
+            }
         }
     }
 
     unsafe impl Send for FiberWaker {}
     unsafe impl Sync for FiberWaker {}
 
-    pub fn with_rcw(rcw: Rc<FiberWaker>) -> Waker {
+    pub fn with_rcw<W: RcWake + 'static>(rcw: Rc<W>) -> Waker {
         let raw_waker = raw_waker(rcw);
         unsafe { Waker::from_raw(raw_waker) }
     }
 
-    fn raw_waker(rcw: Rc<FiberWaker>) -> RawWaker {
+    struct VTable<W>(PhantomData<W>);
+
+    impl<W: RcWake + 'static> VTable<W> {
         const RC_WAKER_VT: RawWakerVTable = RawWakerVTable::new(
-            rc_waker_clone,
-            rc_waker_wake,
-            rc_waker_wake_by_ref,
-            rc_waker_drop,
+            rc_waker_clone::<W>,
+            rc_waker_wake::<W>,
+            rc_waker_wake_by_ref::<W>,
+            rc_waker_drop::<W>,
         );
+    }
+
+    fn raw_waker<W: RcWake + 'static>(rcw: Rc<W>) -> RawWaker {
         let ptr: *const () = Rc::into_raw(rcw).cast();
-        RawWaker::new(ptr, &RC_WAKER_VT)
+        RawWaker::new(ptr, &VTable::<W>::RC_WAKER_VT)
     }
 
-    unsafe fn rc_waker_clone(data: *const ()) -> RawWaker {
-        let rcw: Rc<FiberWaker> = {
+    unsafe fn rc_waker_clone<W: RcWake + 'static>(data: *const ()) -> RawWaker {
+        let rcw: Rc<W> = {
             // Clone it manually
-            Rc::increment_strong_count(data);
+            Rc::increment_strong_count(data.cast::<W>());
             Rc::from_raw(data.cast())
         };
         raw_waker(rcw)
     }
 
     /// Represents `fn wake(self)`, must consume the data
-    unsafe fn rc_waker_wake(data: *const ()) {
-        let rcw: Rc<FiberWaker> = Rc::from_raw(data.cast());
+    unsafe fn rc_waker_wake<W: RcWake>(data: *const ()) {
+        let rcw: Rc<W> = Rc::from_raw(data.cast());
         rcw.wake();
         drop(rcw);
     }
 
     /// Represents `fn wake_by_ref(&self)`, must NOT consume the data
-    unsafe fn rc_waker_wake_by_ref(data: *const ()) {
-        let rcw: Rc<FiberWaker> = Rc::from_raw(data.cast());
+    unsafe fn rc_waker_wake_by_ref<W: RcWake>(data: *const ()) {
+        let rcw: Rc<W> = Rc::from_raw(data.cast());
         rcw.wake();
         std::mem::forget(rcw);
     }
 
-    unsafe fn rc_waker_drop(data: *const ()) {
-        let rcw: Rc<FiberWaker> = Rc::from_raw(data.cast());
+    unsafe fn rc_waker_drop<W: RcWake>(data: *const ()) {
+        let rcw: Rc<W> = Rc::from_raw(data.cast());
         drop(rcw)
     }
 }
@@ -90,7 +145,11 @@ mod waker {
 pub(crate) mod coio {
     use std::cell::Cell;
     use std::ffi::CString;
+    use std::os::unix::io::RawFd;
     use std::rc::Rc;
+    use std::time::Duration;
+
+    use crate::ffi::tarantool as ffi;
 
     /// Request for address resolution. After being set in [`context::ContextExt`] it will be executed by `block_on`.
     /// See [coio_getaddrinfo](tarantool::ffi::tarantool::coio_getaddrinfo) for low level details.
@@ -101,10 +160,44 @@ pub(crate) mod coio {
         pub res: Rc<Cell<*mut libc::addrinfo>>,
         pub err: Rc<Cell<bool>>,
     }
+
+    /// A blocking coio operation requested by a pending future via
+    /// [`context::ContextExt`](super::context::ContextExt).
+    #[derive(Clone, Debug)]
+    pub enum IoRequest {
+        Wait(RawFd, ffi::CoIOFlags),
+        GetAddrInfo(GetAddrInfo),
+    }
+
+    impl IoRequest {
+        /// Block the current fiber until the request is completed or the
+        /// `timeout` expires.
+        pub fn perform(&self, timeout: Duration) {
+            match self {
+                Self::GetAddrInfo(getaddrinfo) => {
+                    let mut res = std::ptr::null_mut();
+                    let out = unsafe {
+                        ffi::coio_getaddrinfo(
+                            getaddrinfo.host.as_ptr(),
+                            std::ptr::null(),
+                            &getaddrinfo.hints as *const _,
+                            &mut res as *mut _,
+                            timeout.as_secs_f64(),
+                        )
+                    };
+                    getaddrinfo.err.set(out != 0);
+                    getaddrinfo.res.set(res);
+                }
+                Self::Wait(fd, event) => unsafe {
+                    ffi::coio_wait(*fd, event.bits(), timeout.as_secs_f64());
+                },
+            }
+        }
+    }
 }
 
 pub(crate) mod context {
-    use super::coio::GetAddrInfo;
+    use super::coio::{GetAddrInfo, IoRequest};
     use std::os::unix::io::RawFd;
     use std::task::Context;
     use std::task::Waker;
@@ -160,6 +253,17 @@ pub(crate) mod context {
             cx
         }
 
+        /// Take the blocking coio operation requested by the future if any.
+        pub(super) fn take_io_request(&mut self) -> Option<IoRequest> {
+            if let Some(getaddrinfo) = self.coio_getaddrinfo.take() {
+                Some(IoRequest::GetAddrInfo(getaddrinfo))
+            } else {
+                self.coio_wait
+                    .take()
+                    .map(|(fd, event)| IoRequest::Wait(fd, event))
+            }
+        }
+
         /// SAFETY: `cx` must really be the `ContextExt`
         pub unsafe fn set_deadline(cx: &mut Context<'_>, new: Instant) {
             let cx = Self::as_context_ext(cx);
@@ -237,49 +341,51 @@ impl<T> IntoOnDrop for T where T: Future + Sized {}
 /// Runs a future to completion on the fiber-based runtime. This is the async runtime’s entry point.
 ///
 /// This runs the given future on the current fiber, blocking until it is complete, and yielding its resolved result.
+///
+/// Other futures can be [`spawn`]ed from within `f`. They are executed
+/// concurrently with `f` on the same fiber, and are cancelled when `f`
+/// completes.
 pub fn block_on<F: Future>(f: F) -> F::Output {
-    let rcw: Rc<waker::FiberWaker> = Default::default();
+    let executor = task::Executor::new();
+    let rcw = executor.fiber_waker();
     let waker = waker::with_rcw(rcw.clone());
 
     pin_mut!(f);
     loop {
-        let mut cx = context::ContextExt::from_waker(&waker);
+        let guard = executor.enter();
+        rcw.take_woken();
 
+        let mut cx = context::ContextExt::from_waker(&waker);
         if let Poll::Ready(t) = f.as_mut().poll(cx.cx()) {
+            drop(guard);
+            executor.shutdown();
             return t;
         }
+        executor.poll_tasks();
+        drop(guard);
+
+        if rcw.take_woken() {
+            // Somebody was woken up while being polled, no need to wait.
+            continue;
+        }
 
-        let timeout = match cx.deadline {
+        let deadline = match (cx.deadline, executor.next_deadline()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let timeout = match deadline {
             Some(deadline) => deadline.saturating_duration_since(Instant::now()),
             None => Duration::MAX,
         };
 
-        if let Some(getaddrinfo) = cx.coio_getaddrinfo {
-            let mut res = std::ptr::null_mut();
-            let out = unsafe {
-                crate::ffi::tarantool::coio_getaddrinfo(
-                    getaddrinfo.host.as_ptr(),
-                    std::ptr::null(),
-                    &getaddrinfo.hints as *const _,
-                    &mut res as *mut _,
-                    timeout.as_secs_f64(),
-                )
-            };
-            getaddrinfo.err.set(out != 0);
-            getaddrinfo.res.set(res);
-        } else if let Some((fd, event)) = cx.coio_wait {
-            unsafe {
-                crate::ffi::tarantool::coio_wait(fd, event.bits(), timeout.as_secs_f64());
-            }
-        } else {
-            rcw.cond().wait_timeout(timeout);
-        }
+        executor.wait(cx.take_io_request(), &waker, timeout);
     }
 }
 
 #[cfg(feature = "internal_test")]
 mod tests {
     use std::cell::Cell;
+    use std::rc::Rc;
 
     use super::timeout::IntoTimeout as _;
     use super::*;
@@ -0,0 +1,356 @@
+//! A multi-producer, multi-consumer broadcast queue. Each sent value is seen
+//! by all the receivers.
+//!
+//! [`channel`] creates a [`Sender`] / [`Receiver`] pair. More receivers can be
+//! created with [`Sender::subscribe`], a new receiver only gets the values
+//! sent after it's been created.
+//!
+//! The channel keeps at most `capacity` values. If a receiver falls behind
+//! and the oldest values it hasn't seen are overwritten, the next call to
+//! [`Receiver::recv`] returns [`RecvError::Lagged`] with the number of
+//! skipped values, after which the receiver continues from the oldest value
+//! still stored in the channel.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber;
+//! use tarantool::fiber::r#async::broadcast;
+//!
+//! let (tx, mut rx1) = broadcast::channel(16);
+//! let mut rx2 = tx.subscribe();
+//! tx.send(10).unwrap();
+//! fiber::block_on(async {
+//!     assert_eq!(rx1.recv().await, Ok(10));
+//!     assert_eq!(rx2.recv().await, Ok(10));
+//! });
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// Error returned by [`Sender::send`] if there are no receivers. Contains the
+/// value which couldn't be sent.
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq)]
+#[error("channel closed")]
+pub struct SendError<T>(pub T);
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+/// Error returned by [`Receiver::recv`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// All the senders have been dropped and there are no more values.
+    #[error("channel closed")]
+    Closed,
+    /// The receiver lagged behind and the given number of values were
+    /// skipped.
+    #[error("channel lagged by {0}")]
+    Lagged(u64),
+}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// There are no new values at the moment.
+    #[error("channel empty")]
+    Empty,
+    /// All the senders have been dropped and there are no more values.
+    #[error("channel closed")]
+    Closed,
+    /// The receiver lagged behind and the given number of values were
+    /// skipped.
+    #[error("channel lagged by {0}")]
+    Lagged(u64),
+}
+
+#[derive(Debug)]
+struct State<T> {
+    /// The stored values, the oldest first.
+    buffer: RefCell<VecDeque<T>>,
+    /// Position of the oldest value in the buffer.
+    head: Cell<u64>,
+    capacity: usize,
+    wakers: RefCell<Vec<Waker>>,
+    sender_count: Cell<usize>,
+    receiver_count: Cell<usize>,
+}
+
+impl<T> State<T> {
+    #[inline]
+    fn tail(&self) -> u64 {
+        self.head.get() + self.buffer.borrow().len() as u64
+    }
+
+    fn add_waker(&self, waker: &Waker) {
+        let mut wakers = self.wakers.borrow_mut();
+        if !wakers.iter().any(|w| waker.will_wake(w)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&self) {
+        let wakers = std::mem::take(&mut *self.wakers.borrow_mut());
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Creates a broadcast channel which stores at most `capacity` values.
+///
+/// # Panics
+/// Panics if `capacity` is `0`.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel requires capacity > 0");
+    let state = Rc::new(State {
+        buffer: RefCell::new(VecDeque::with_capacity(capacity)),
+        head: Cell::new(0),
+        capacity,
+        wakers: Default::default(),
+        sender_count: Cell::new(1),
+        receiver_count: Cell::new(0),
+    });
+    let tx = Sender { state };
+    let rx = tx.subscribe();
+    (tx, rx)
+}
+
+/// Sends values to all the associated [`Receiver`]s. Created by [`channel`].
+///
+/// Can be cloned to get multiple senders for the same channel.
+pub struct Sender<T> {
+    state: Rc<State<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to all the active receivers. Returns the number of
+    /// receivers which will see the value.
+    ///
+    /// Sending never waits: if the channel is full, the oldest value is
+    /// dropped. Returns an error containing the value if there are no
+    /// receivers.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let receivers = self.state.receiver_count.get();
+        if receivers == 0 {
+            return Err(SendError(value));
+        }
+        {
+            let mut buffer = self.state.buffer.borrow_mut();
+            if buffer.len() == self.state.capacity {
+                buffer.pop_front();
+                self.state.head.set(self.state.head.get() + 1);
+            }
+            buffer.push_back(value);
+        }
+        self.state.wake_all();
+        Ok(receivers)
+    }
+
+    /// Creates a new [`Receiver`] which receives the values sent after this
+    /// call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let count = self.state.receiver_count.get();
+        self.state.receiver_count.set(count + 1);
+        Receiver {
+            state: self.state.clone(),
+            next: self.state.tail(),
+        }
+    }
+
+    /// Returns the number of active receivers.
+    #[inline]
+    pub fn receiver_count(&self) -> usize {
+        self.state.receiver_count.get()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let count = self.state.sender_count.get();
+        self.state.sender_count.set(count + 1);
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let count = self.state.sender_count.get() - 1;
+        self.state.sender_count.set(count);
+        if count == 0 {
+            self.state.wake_all();
+        }
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// Receives values from the associated [`Sender`]s. Created by [`channel`] or
+/// [`Sender::subscribe`].
+pub struct Receiver<T> {
+    state: Rc<State<T>>,
+    /// Position of the next value to receive.
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Receives the next value.
+    ///
+    /// Returns [`RecvError::Lagged`] if some values were skipped because the
+    /// receiver fell behind, and [`RecvError::Closed`] if all the senders have
+    /// been dropped and all the values have been received.
+    ///
+    /// This method is cancel safe.
+    #[inline]
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { rx: self }
+    }
+
+    /// Attempts to receive the next value without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let head = self.state.head.get();
+        if self.next < head {
+            let lagged = head - self.next;
+            self.next = head;
+            return Err(TryRecvError::Lagged(lagged));
+        }
+        let value = self
+            .state
+            .buffer
+            .borrow()
+            .get((self.next - head) as usize)
+            .cloned();
+        match value {
+            Some(value) => {
+                self.next += 1;
+                Ok(value)
+            }
+            None if self.state.sender_count.get() == 0 => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Creates a new `Receiver` which receives the values sent after this
+    /// call.
+    pub fn resubscribe(&self) -> Self {
+        let count = self.state.receiver_count.get();
+        self.state.receiver_count.set(count + 1);
+        Self {
+            state: self.state.clone(),
+            next: self.state.tail(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let count = self.state.receiver_count.get();
+        self.state.receiver_count.set(count - 1);
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// Future returned by [`Receiver::recv`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, T> {
+    rx: &'a mut Receiver<T>,
+}
+
+impl<T: Clone> Future for Recv<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.rx.try_recv() {
+            Ok(value) => Poll::Ready(Ok(value)),
+            Err(TryRecvError::Lagged(n)) => Poll::Ready(Err(RecvError::Lagged(n))),
+            Err(TryRecvError::Closed) => Poll::Ready(Err(RecvError::Closed)),
+            Err(TryRecvError::Empty) => {
+                self.rx.state.add_waker(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Debug for Recv<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recv").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timer};
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn all_receivers_see_values() {
+        let (tx, rx1) = channel(4);
+        let rx2 = tx.subscribe();
+        let receive = |mut rx: Receiver<i32>| {
+            spawn(async move {
+                let mut values = vec![];
+                while let Ok(v) = rx.recv().await {
+                    values.push(v);
+                }
+                values
+            })
+        };
+        fiber::block_on(async {
+            let t1 = receive(rx1);
+            let t2 = receive(rx2);
+            for i in 0..10 {
+                assert_eq!(tx.send(i), Ok(2));
+                timer::sleep(Duration::ZERO).await;
+            }
+            drop(tx);
+            let expected: Vec<_> = (0..10).collect();
+            assert_eq!(t1.await.unwrap(), expected);
+            assert_eq!(t2.await.unwrap(), expected);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn lagged() {
+        let (tx, mut rx) = channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn subscribe_and_no_receivers() {
+        let (tx, rx) = channel(2);
+        tx.send(1).unwrap();
+        let mut rx2 = rx.resubscribe();
+        assert_eq!(tx.receiver_count(), 2);
+        assert_eq!(rx2.try_recv(), Err(TryRecvError::Empty));
+        drop((rx, rx2));
+        assert_eq!(tx.send(2), Err(SendError(2)));
+    }
+}
@@ -0,0 +1,533 @@
+//! A multi-producer, single-consumer queue for sending values between
+//! asynchronous tasks.
+//!
+//! [`channel`] creates a bounded channel: [`Sender::send`] waits if the queue
+//! is full, which provides backpressure. [`unbounded_channel`] creates an
+//! unbounded channel, sending to which never waits, so
+//! [`UnboundedSender::send`] can be used from non-async code.
+//!
+//! The channel is closed when all the senders are dropped or when the
+//! receiver is dropped or [closed](Receiver::close). After the senders are
+//! dropped, the receiver still gets the values remaining in the queue.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber;
+//! use tarantool::fiber::r#async::{mpsc, spawn};
+//!
+//! let (tx, mut rx) = mpsc::channel(8);
+//! fiber::block_on(async move {
+//!     for i in 0..3 {
+//!         let tx = tx.clone();
+//!         drop(spawn(async move { tx.send(i).await.unwrap() }));
+//!     }
+//!     drop(tx);
+//!     let mut sum = 0;
+//!     while let Some(i) = rx.recv().await {
+//!         sum += i;
+//!     }
+//!     assert_eq!(sum, 3);
+//! });
+//! ```
+
+use super::sync::{Semaphore, TryAcquireError};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// Error returned when sending to a closed channel. Contains the value which
+/// couldn't be sent.
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq)]
+#[error("channel closed")]
+pub struct SendError<T>(pub T);
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+/// Error returned by [`Sender::try_send`].
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel's queue is full.
+    #[error("channel full")]
+    Full(T),
+    /// The receiver has been dropped or closed.
+    #[error("channel closed")]
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Returns the value which couldn't be sent.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(v) | Self::Closed(v) => v,
+        }
+    }
+}
+
+impl<T> Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel's queue is currently empty.
+    #[error("channel empty")]
+    Empty,
+    /// The queue is empty and all the senders have been dropped.
+    #[error("channel disconnected")]
+    Disconnected,
+}
+
+struct Chan<T> {
+    queue: RefCell<VecDeque<T>>,
+    /// Limits the number of values in the queue. `None` for unbounded
+    /// channels. Closed when the receiver is closed.
+    capacity: Option<Semaphore>,
+    rx_closed: Cell<bool>,
+    rx_waker: Cell<Option<Waker>>,
+    sender_count: Cell<usize>,
+}
+
+impl<T> Chan<T> {
+    fn new(capacity: Option<usize>) -> Rc<Self> {
+        Rc::new(Self {
+            queue: Default::default(),
+            capacity: capacity.map(Semaphore::new),
+            rx_closed: Cell::new(false),
+            rx_waker: Cell::new(None),
+            sender_count: Cell::new(1),
+        })
+    }
+
+    fn push(&self, value: T) {
+        self.queue.borrow_mut().push_back(value);
+        self.wake_rx();
+    }
+
+    fn wake_rx(&self) {
+        if let Some(waker) = self.rx_waker.take() {
+            waker.wake()
+        }
+    }
+
+    fn add_sender(&self) {
+        self.sender_count.set(self.sender_count.get() + 1);
+    }
+
+    fn remove_sender(&self) {
+        let count = self.sender_count.get() - 1;
+        self.sender_count.set(count);
+        if count == 0 {
+            self.wake_rx();
+        }
+    }
+
+    fn close(&self) {
+        self.rx_closed.set(true);
+        if let Some(capacity) = &self.capacity {
+            capacity.close();
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let value = self.queue.borrow_mut().pop_front();
+        match value {
+            Some(value) => {
+                if let Some(capacity) = &self.capacity {
+                    capacity.add_permits(1);
+                }
+                Ok(value)
+            }
+            None if self.sender_count.get() == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.try_recv() {
+            Ok(value) => Poll::Ready(Some(value)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) if self.rx_closed.get() => Poll::Ready(None),
+            Err(TryRecvError::Empty) => {
+                self.rx_waker.set(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Bounded
+////////////////////////////////////////////////////////////////////////////////
+
+/// Creates a bounded channel with the given capacity.
+///
+/// # Panics
+/// Panics if `capacity` is `0`.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "mpsc bounded channel requires capacity > 0");
+    let chan = Chan::new(Some(capacity));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Sends values to the associated [`Receiver`]. Created by [`channel`].
+///
+/// Can be cloned to get multiple senders for the same channel.
+pub struct Sender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waiting until there's capacity in the queue.
+    ///
+    /// Returns an error containing the value if the receiver has been closed.
+    /// Senders waiting for capacity are served in the order they've called
+    /// `send`.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let capacity = self.capacity_semaphore();
+        match capacity.acquire().await {
+            Ok(permit) => permit.forget(),
+            Err(_) => return Err(SendError(value)),
+        }
+        self.chan.push(value);
+        Ok(())
+    }
+
+    /// Attempts to send a value without waiting.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        match self.capacity_semaphore().try_acquire() {
+            Ok(permit) => permit.forget(),
+            Err(TryAcquireError::NoPermits) => return Err(TrySendError::Full(value)),
+            Err(TryAcquireError::Closed) => return Err(TrySendError::Closed(value)),
+        }
+        self.chan.push(value);
+        Ok(())
+    }
+
+    /// Returns the current capacity of the channel, i.e. the number of values
+    /// which can be sent without waiting.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity_semaphore().available_permits()
+    }
+
+    /// Returns `true` if the receiver has been dropped or closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.chan.rx_closed.get()
+    }
+
+    #[inline]
+    fn capacity_semaphore(&self) -> &Semaphore {
+        self.chan
+            .capacity
+            .as_ref()
+            .expect("bounded channel always has capacity")
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.remove_sender()
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// Receives values from the associated [`Sender`]s. Created by [`channel`].
+pub struct Receiver<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value. Returns `None` if the channel is closed and
+    /// there are no more values in the queue.
+    ///
+    /// This method is cancel safe: if the future is dropped before it
+    /// completes, no values are lost.
+    #[inline]
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { chan: &self.chan }
+    }
+
+    /// Attempts to receive the next value without waiting.
+    #[inline]
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Polls to receive the next value. See [`Receiver::recv`].
+    #[inline]
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(cx)
+    }
+
+    /// Closes the receiving half of the channel without dropping it. All the
+    /// subsequent and pending sends fail, but the values already in the queue
+    /// can still be received.
+    #[inline]
+    pub fn close(&mut self) {
+        self.chan.close()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.close()
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unbounded
+////////////////////////////////////////////////////////////////////////////////
+
+/// Creates an unbounded channel.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let chan = Chan::new(None);
+    (
+        UnboundedSender { chan: chan.clone() },
+        UnboundedReceiver { chan },
+    )
+}
+
+/// Sends values to the associated [`UnboundedReceiver`]. Created by
+/// [`unbounded_channel`].
+///
+/// Can be cloned to get multiple senders for the same channel.
+pub struct UnboundedSender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends a value without waiting.
+    ///
+    /// Returns an error containing the value if the receiver has been closed.
+    /// Since this method is not async, it can be used from non-async code.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.chan.rx_closed.get() {
+            return Err(SendError(value));
+        }
+        self.chan.push(value);
+        Ok(())
+    }
+
+    /// Returns `true` if the receiver has been dropped or closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.chan.rx_closed.get()
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.chan.remove_sender()
+    }
+}
+
+impl<T> Debug for UnboundedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnboundedSender").finish_non_exhaustive()
+    }
+}
+
+/// Receives values from the associated [`UnboundedSender`]s. Created by
+/// [`unbounded_channel`].
+pub struct UnboundedReceiver<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> UnboundedReceiver<T> {
+    /// Receives the next value. Returns `None` if the channel is closed and
+    /// there are no more values in the queue.
+    ///
+    /// This method is cancel safe: if the future is dropped before it
+    /// completes, no values are lost.
+    #[inline]
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { chan: &self.chan }
+    }
+
+    /// Attempts to receive the next value without waiting.
+    #[inline]
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Polls to receive the next value. See [`UnboundedReceiver::recv`].
+    #[inline]
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(cx)
+    }
+
+    /// Closes the receiving half of the channel without dropping it. All the
+    /// subsequent sends fail, but the values already in the queue can still
+    /// be received.
+    #[inline]
+    pub fn close(&mut self) {
+        self.chan.close()
+    }
+}
+
+impl<T> Drop for UnboundedReceiver<T> {
+    fn drop(&mut self) {
+        self.chan.close()
+    }
+}
+
+impl<T> Debug for UnboundedReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnboundedReceiver").finish_non_exhaustive()
+    }
+}
+
+/// Future returned by [`Receiver::recv`] and [`UnboundedReceiver::recv`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, T> {
+    chan: &'a Chan<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.chan.poll_recv(cx)
+    }
+}
+
+impl<T> Debug for Recv<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recv").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timeout::IntoTimeout as _, timer};
+    use std::time::Duration;
+
+    const _1_MS: Duration = Duration::from_millis(1);
+
+    #[crate::test(tarantool = "crate")]
+    fn bounded_backpressure() {
+        let (tx, mut rx) = channel(2);
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.capacity(), 0);
+        assert!(matches!(tx.try_send(3), Err(TrySendError::Full(3))));
+        fiber::block_on(async {
+            assert!(tx.send(3).timeout(_1_MS).await.is_err());
+            let sender = spawn({
+                let tx = tx.clone();
+                async move { tx.send(3).await.unwrap() }
+            });
+            timer::sleep(_1_MS).await;
+            assert!(!sender.is_finished());
+            assert_eq!(rx.recv().await, Some(1));
+            sender.await.unwrap();
+            assert_eq!(rx.recv().await, Some(2));
+            assert_eq!(rx.recv().await, Some(3));
+        });
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn receiver_closed() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(1).unwrap();
+        fiber::block_on(async {
+            let sender = spawn({
+                let tx = tx.clone();
+                async move { tx.send(2).await }
+            });
+            timer::sleep(_1_MS).await;
+            rx.close();
+            assert!(tx.is_closed());
+            assert_eq!(sender.await.unwrap(), Err(SendError(2)));
+            // The values in the queue are still received
+            assert_eq!(rx.recv().await, Some(1));
+            assert_eq!(rx.recv().await, None);
+        });
+        assert!(matches!(tx.try_send(3), Err(TrySendError::Closed(3))));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn unbounded() {
+        let (tx, mut rx) = unbounded_channel();
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        let task = fiber::start_async(async move {
+            let mut received = vec![];
+            while let Some(i) = rx.recv().await {
+                received.push(i);
+            }
+            received
+        });
+        tx.send(100).unwrap();
+        drop(tx);
+        assert_eq!(task.join(), (0..=100).collect::<Vec<_>>());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn unbounded_receiver_dropped() {
+        let (tx, rx) = unbounded_channel();
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn recv_from_another_fiber() {
+        let (tx, mut rx) = unbounded_channel::<i32>();
+        let jh = fiber::start_async(async move { rx.recv().await });
+        tx.send(42).unwrap();
+        assert_eq!(jh.join(), Some(42));
+    }
+}
@@ -0,0 +1,486 @@
+//! Blocking coio operations requested by the tasks of an
+//! [`Executor`](super::task::Executor).
+//!
+//! A fiber can only perform one blocking operation at a time, so instead of
+//! waiting for each file descriptor separately, all of them are registered in
+//! a [`Poller`] (`epoll` or `kqueue`), whose own descriptor is awaited with a
+//! single `coio_wait` from the executor's fiber. Address resolution can't be
+//! multiplexed this way, so the requests are performed one after another by a
+//! single [`Resolver`] fiber.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::os::raw::c_int;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::task::Waker;
+use std::time::Duration;
+
+use super::coio::{GetAddrInfo, IoRequest};
+use super::waker::FiberWaker;
+use crate::ffi::tarantool as ffi;
+use crate::fiber;
+
+/// Performs the operations requested by the pending tasks.
+#[derive(Default)]
+pub(super) struct Reactor {
+    poller: Option<Poller>,
+    resolver: Option<Resolver>,
+}
+
+impl Reactor {
+    /// Block the current fiber until either one of the `requests` is
+    /// completed, the `fiber_waker` is woken or the `timeout` expires. The
+    /// waker paired with the completed request is woken.
+    pub fn wait<'a>(
+        &mut self,
+        requests: impl IntoIterator<Item = (&'a IoRequest, &'a Waker)>,
+        fiber_waker: &FiberWaker,
+        timeout: Duration,
+    ) {
+        let mut fds: HashMap<RawFd, (ffi::CoIOFlags, Vec<Waker>)> = HashMap::new();
+        let mut lookups = Vec::new();
+        for (request, waker) in requests {
+            match request {
+                IoRequest::Wait(fd, flags) => {
+                    let (all_flags, wakers) = fds
+                        .entry(*fd)
+                        .or_insert_with(|| (ffi::CoIOFlags::empty(), vec![]));
+                    *all_flags |= *flags;
+                    wakers.push(waker.clone());
+                }
+                IoRequest::GetAddrInfo(getaddrinfo) => {
+                    lookups.push((getaddrinfo.clone(), waker.clone()));
+                }
+            }
+        }
+
+        if !lookups.is_empty() || self.resolver.is_some() {
+            self.resolver
+                .get_or_insert_with(Resolver::default)
+                .update(lookups);
+        }
+
+        if fds.is_empty() {
+            fiber_waker.cond().wait_timeout(timeout);
+            return;
+        }
+
+        let poller = match &mut self.poller {
+            Some(poller) => poller,
+            None => match Poller::new() {
+                Ok(poller) => self.poller.insert(poller),
+                Err(e) => {
+                    // Let the tasks retry the operations, there's nothing
+                    // else we can do
+                    log::error!("Failed to create poller: {e}");
+                    fds.values()
+                        .flat_map(|(_, w)| w)
+                        .for_each(Waker::wake_by_ref);
+                    return;
+                }
+            },
+        };
+        poller.update(fds.iter().map(|(fd, (flags, _))| (*fd, *flags)));
+
+        // Wakers of the tasks interrupt the wait
+        fiber_waker.set_io_wake_fd(Some(poller.wake_pipe.write));
+        unsafe {
+            ffi::coio_wait(
+                poller.fd,
+                ffi::CoIOFlags::READ.bits(),
+                timeout.as_secs_f64(),
+            )
+        };
+        fiber_waker.set_io_wake_fd(None);
+
+        for fd in poller.ready() {
+            if let Some((_, wakers)) = fds.get(&fd) {
+                wakers.iter().for_each(Waker::wake_by_ref);
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Poller
+////////////////////////////////////////////////////////////////////////////////
+
+/// A set of file descriptors whose readiness is reported via a single file
+/// descriptor. The registrations are level-triggered.
+struct Poller {
+    fd: RawFd,
+    registered: HashMap<RawFd, ffi::CoIOFlags>,
+    /// Descriptors which can't be polled (e.g. regular files), they're always
+    /// considered ready.
+    always_ready: Vec<RawFd>,
+    /// Always registered, used to interrupt the wait.
+    wake_pipe: WakePipe,
+}
+
+impl Poller {
+    fn new() -> io::Result<Self> {
+        let wake_pipe = WakePipe::new()?;
+        let fd = Self::create()?;
+        let mut poller = Self {
+            fd,
+            registered: HashMap::new(),
+            always_ready: Vec::new(),
+            wake_pipe,
+        };
+        poller.update(std::iter::empty());
+        Ok(poller)
+    }
+
+    /// Make the set of the registered descriptors equal to `interest`.
+    fn update(&mut self, interest: impl Iterator<Item = (RawFd, ffi::CoIOFlags)>) {
+        let mut stale = std::mem::take(&mut self.registered);
+        self.always_ready.clear();
+        let wake = (self.wake_pipe.read, ffi::CoIOFlags::READ);
+        for (fd, flags) in interest.chain(Some(wake)) {
+            let res = match stale.remove(&fd) {
+                Some(old) if old == flags => Ok(()),
+                old => self.register(fd, old, flags),
+            };
+            match res {
+                Ok(()) => {
+                    self.registered.insert(fd, flags);
+                }
+                Err(_) => self.always_ready.push(fd),
+            }
+        }
+        for (fd, flags) in stale {
+            // The descriptor may have been closed already, that's ok
+            let _ = self.deregister(fd, flags);
+        }
+    }
+
+    /// Return the descriptors which are ready. Doesn't block.
+    fn ready(&mut self) -> Vec<RawFd> {
+        let mut ready = std::mem::take(&mut self.always_ready);
+        match self.poll(&mut ready) {
+            Ok(()) => {}
+            // Let the tasks retry
+            Err(_) => ready.extend(self.registered.keys()),
+        }
+        if let Some(i) = ready.iter().position(|&fd| fd == self.wake_pipe.read) {
+            ready.swap_remove(i);
+            self.wake_pipe.drain();
+        }
+        ready
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Poller {
+    fn create() -> io::Result<RawFd> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fd)
+    }
+
+    fn register(
+        &self,
+        fd: RawFd,
+        old: Option<ffi::CoIOFlags>,
+        flags: ffi::CoIOFlags,
+    ) -> io::Result<()> {
+        let op = if old.is_some() {
+            libc::EPOLL_CTL_MOD
+        } else {
+            libc::EPOLL_CTL_ADD
+        };
+        // The descriptor number may have been reused after it was closed, in
+        // which case the registration is either lost or left from the old
+        // descriptor
+        match self.ctl(op, fd, flags) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => {
+                self.ctl(libc::EPOLL_CTL_ADD, fd, flags)
+            }
+            Err(e) if e.raw_os_error() == Some(libc::EEXIST) => {
+                self.ctl(libc::EPOLL_CTL_MOD, fd, flags)
+            }
+            res => res,
+        }
+    }
+
+    fn deregister(&self, fd: RawFd, _: ffi::CoIOFlags) -> io::Result<()> {
+        self.ctl(libc::EPOLL_CTL_DEL, fd, ffi::CoIOFlags::empty())
+    }
+
+    fn ctl(&self, op: c_int, fd: RawFd, flags: ffi::CoIOFlags) -> io::Result<()> {
+        let mut events = 0;
+        if flags.contains(ffi::CoIOFlags::READ) {
+            events |= libc::EPOLLIN;
+        }
+        if flags.contains(ffi::CoIOFlags::WRITE) {
+            events |= libc::EPOLLOUT;
+        }
+        let mut event = libc::epoll_event {
+            events: events as _,
+            u64: fd as _,
+        };
+        if unsafe { libc::epoll_ctl(self.fd, op, fd, &mut event) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn poll(&self, ready: &mut Vec<RawFd>) -> io::Result<()> {
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; 64];
+        loop {
+            let n = unsafe { libc::epoll_wait(self.fd, events.as_mut_ptr(), events.len() as _, 0) };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            ready.extend(events[..n as usize].iter().map(|e| e.u64 as RawFd));
+            if (n as usize) < events.len() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Poller {
+    fn create() -> io::Result<RawFd> {
+        let fd = unsafe { libc::kqueue() };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fd)
+    }
+
+    fn register(
+        &self,
+        fd: RawFd,
+        old: Option<ffi::CoIOFlags>,
+        flags: ffi::CoIOFlags,
+    ) -> io::Result<()> {
+        let old = old.unwrap_or_else(ffi::CoIOFlags::empty);
+        for (flag, filter) in Self::FILTERS {
+            if flags.contains(flag) {
+                self.change(fd, filter, libc::EV_ADD)?;
+            } else if old.contains(flag) {
+                let _ = self.change(fd, filter, libc::EV_DELETE);
+            }
+        }
+        Ok(())
+    }
+
+    fn deregister(&self, fd: RawFd, flags: ffi::CoIOFlags) -> io::Result<()> {
+        for (flag, filter) in Self::FILTERS {
+            if flags.contains(flag) {
+                self.change(fd, filter, libc::EV_DELETE)?;
+            }
+        }
+        Ok(())
+    }
+
+    const FILTERS: [(ffi::CoIOFlags, i16); 2] = [
+        (ffi::CoIOFlags::READ, libc::EVFILT_READ),
+        (ffi::CoIOFlags::WRITE, libc::EVFILT_WRITE),
+    ];
+
+    fn change(&self, fd: RawFd, filter: i16, flags: u16) -> io::Result<()> {
+        let mut change: libc::kevent = unsafe { std::mem::zeroed() };
+        change.ident = fd as _;
+        change.filter = filter as _;
+        change.flags = flags as _;
+        let rc = unsafe {
+            libc::kevent(
+                self.fd,
+                &change,
+                1,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn poll(&self, ready: &mut Vec<RawFd>) -> io::Result<()> {
+        let mut events: [libc::kevent; 64] = unsafe { std::mem::zeroed() };
+        let timeout: libc::timespec = unsafe { std::mem::zeroed() };
+        loop {
+            let n = unsafe {
+                libc::kevent(
+                    self.fd,
+                    std::ptr::null(),
+                    0,
+                    events.as_mut_ptr(),
+                    events.len() as _,
+                    &timeout,
+                )
+            };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            ready.extend(events[..n as usize].iter().map(|e| e.ident as RawFd));
+            if (n as usize) < events.len() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// A pipe used to interrupt `coio_wait` on the [`Poller`]'s descriptor.
+struct WakePipe {
+    read: RawFd,
+    write: RawFd,
+}
+
+impl WakePipe {
+    fn new() -> io::Result<Self> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let pipe = Self {
+            read: fds[0],
+            write: fds[1],
+        };
+        for fd in fds {
+            let rc = unsafe {
+                libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC)
+            };
+            if rc < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(pipe)
+    }
+
+    fn drain(&self) {
+        let mut buf = [0_u8; 64];
+        while unsafe { libc::read(self.read, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+    }
+}
+
+impl Drop for WakePipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Resolver
+////////////////////////////////////////////////////////////////////////////////
+
+/// A fiber performing the address resolution requests one after another.
+#[derive(Default)]
+struct Resolver {
+    shared: Rc<ResolverShared>,
+    handle: Option<fiber::UnitJoinHandle<'static>>,
+}
+
+#[derive(Default)]
+struct ResolverShared {
+    queue: RefCell<VecDeque<(GetAddrInfo, Waker)>>,
+    /// The request being performed right now.
+    current: RefCell<Option<GetAddrInfo>>,
+    cond: fiber::Cond,
+    is_stopped: Cell<bool>,
+}
+
+impl Resolver {
+    /// Make the set of the pending requests equal to `requests`. The requests
+    /// which are no longer awaited are cancelled.
+    fn update(&mut self, requests: Vec<(GetAddrInfo, Waker)>) {
+        let shared = &self.shared;
+        let current_is_stale = matches!(
+            &*shared.current.borrow(),
+            Some(current) if !requests.iter().any(|(r, _)| is_same(r, current))
+        );
+        if current_is_stale {
+            if let Some(handle) = &self.handle {
+                // Interrupt the request, it will complete with an error
+                handle.wakeup();
+            }
+        }
+
+        let mut queue = shared.queue.borrow_mut();
+        queue.clear();
+        for (request, waker) in requests {
+            let is_done = !request.res.get().is_null() || request.err.get();
+            let is_current = matches!(&*shared.current.borrow(), Some(c) if is_same(c, &request));
+            if !is_done && !is_current {
+                queue.push_back((request, waker));
+            }
+        }
+        if queue.is_empty() {
+            return;
+        }
+        drop(queue);
+
+        if self.handle.is_none() {
+            match Self::start(shared.clone()) {
+                Ok(handle) => self.handle = Some(handle),
+                Err(e) => {
+                    log::error!("Failed to start a fiber for address resolution: {e}");
+                    for (request, waker) in shared.queue.borrow_mut().drain(..) {
+                        request.err.set(true);
+                        waker.wake();
+                    }
+                    return;
+                }
+            }
+        }
+        shared.cond.signal();
+    }
+
+    fn start(shared: Rc<ResolverShared>) -> crate::Result<fiber::UnitJoinHandle<'static>> {
+        fiber::Builder::new()
+            .name("async_getaddrinfo")
+            .proc(move || loop {
+                let next = shared.queue.borrow_mut().pop_front();
+                match next {
+                    Some((request, waker)) => {
+                        *shared.current.borrow_mut() = Some(request.clone());
+                        IoRequest::GetAddrInfo(request).perform(Duration::MAX);
+                        shared.current.borrow_mut().take();
+                        waker.wake();
+                    }
+                    None if shared.is_stopped.get() => break,
+                    None => {
+                        shared.cond.wait();
+                    }
+                }
+            })
+            .start()
+    }
+}
+
+impl Drop for Resolver {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.shared.queue.borrow_mut().clear();
+            self.shared.is_stopped.set(true);
+            // Interrupt the request being performed or the wait for the next
+            // one
+            handle.wakeup();
+            handle.join();
+        }
+    }
+}
+
+fn is_same(a: &GetAddrInfo, b: &GetAddrInfo) -> bool {
+    Rc::ptr_eq(&a.res, &b.res)
+}
@@ -0,0 +1,26 @@
+//! Synchronization primitives for the tasks running within a
+//! [`block_on`](super::block_on) call.
+//!
+//! The primitives from [`fiber`](crate::fiber) (e.g. [`fiber::Mutex`] or
+//! [`fiber::Cond`]) block the whole fiber, so they can't be used to
+//! synchronize the tasks running on the same fiber: the task waiting for a
+//! lock would block the task holding it. The primitives in this module
+//! suspend only the current task instead.
+//!
+//! These primitives can also be used to synchronize futures running on
+//! different fibers, as long as each one of them is driven by `block_on`.
+//!
+//! [`fiber::Mutex`]: crate::fiber::Mutex
+//! [`fiber::Cond`]: crate::fiber::Cond
+
+mod barrier;
+mod mutex;
+mod notify;
+mod rwlock;
+mod semaphore;
+
+pub use barrier::{Barrier, BarrierWaitResult};
+pub use mutex::{Mutex, MutexGuard};
+pub use notify::{Notified, Notify};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{Acquire, AcquireError, Semaphore, SemaphorePermit, TryAcquireError};
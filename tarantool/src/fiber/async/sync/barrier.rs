@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug)]
+struct State {
+    arrived: usize,
+    generation: u64,
+    wakers: Vec<Waker>,
+}
+
+/// A barrier enables multiple tasks to synchronize the beginning of some
+/// computation.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::{spawn, sync::Barrier};
+/// use std::rc::Rc;
+///
+/// let barrier = Rc::new(Barrier::new(3));
+/// fiber::block_on(async {
+///     let tasks: Vec<_> = (0..3)
+///         .map(|_| {
+///             let barrier = barrier.clone();
+///             spawn(async move { barrier.wait().await.is_leader() })
+///         })
+///         .collect();
+///     let mut leaders = 0;
+///     for task in tasks {
+///         leaders += task.await.unwrap() as usize;
+///     }
+///     assert_eq!(leaders, 1);
+/// });
+/// ```
+#[derive(Debug)]
+pub struct Barrier {
+    n: usize,
+    state: RefCell<State>,
+}
+
+impl Barrier {
+    /// Creates a new barrier which blocks until `n` tasks call
+    /// [`Barrier::wait`].
+    ///
+    /// A barrier with `n == 0` behaves the same as with `n == 1`.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n: n.max(1),
+            state: RefCell::new(State {
+                arrived: 0,
+                generation: 0,
+                wakers: vec![],
+            }),
+        }
+    }
+
+    /// Waits until all `n` tasks have reached the barrier. After that the
+    /// barrier is reset and can be reused.
+    ///
+    /// A single task (the last one to arrive) receives a
+    /// [`BarrierWaitResult`] for which [`BarrierWaitResult::is_leader`]
+    /// returns `true`.
+    ///
+    /// **NOTE** the task is counted as arrived when the future is polled for
+    /// the first time. Dropping the future before it completes doesn't undo
+    /// this.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        let generation = {
+            let mut state = self.state.borrow_mut();
+            state.arrived += 1;
+            if state.arrived == self.n {
+                state.arrived = 0;
+                state.generation = state.generation.wrapping_add(1);
+                let wakers = std::mem::take(&mut state.wakers);
+                drop(state);
+                wakers.into_iter().for_each(Waker::wake);
+                return BarrierWaitResult(true);
+            }
+            state.generation
+        };
+        Wait {
+            barrier: self,
+            generation,
+        }
+        .await;
+        BarrierWaitResult(false)
+    }
+}
+
+struct Wait<'a> {
+    barrier: &'a Barrier,
+    generation: u64,
+}
+
+impl Future for Wait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.barrier.state.borrow_mut();
+        if state.generation != self.generation {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Returned by [`Barrier::wait`] when all the tasks have reached the barrier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task was the last one to reach the barrier.
+    #[inline(always)]
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timer};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn reusable() {
+        let barrier = Rc::new(Barrier::new(3));
+        let passed = Rc::new(Cell::new(0));
+        fiber::block_on(async {
+            let tasks: Vec<_> = (0..3)
+                .map(|i| {
+                    let barrier = barrier.clone();
+                    let passed = passed.clone();
+                    spawn(async move {
+                        let mut leader = 0;
+                        for round in 0..2 {
+                            timer::sleep(Duration::from_millis(i)).await;
+                            leader += barrier.wait().await.is_leader() as usize;
+                            // Nobody goes past the barrier before all arrive
+                            assert!(passed.get() >= round * 3);
+                            passed.set(passed.get() + 1);
+                        }
+                        leader
+                    })
+                })
+                .collect();
+            let mut leaders = 0;
+            for task in tasks {
+                leaders += task.await.unwrap();
+            }
+            assert_eq!(leaders, 2);
+        });
+        assert_eq!(passed.get(), 6);
+    }
+}
@@ -0,0 +1,211 @@
+use super::semaphore::Semaphore;
+use std::cell::UnsafeCell;
+use std::fmt::{self, Debug};
+use std::ops::{Deref, DerefMut};
+
+/// An asynchronous mutual exclusion primitive useful for protecting shared
+/// data.
+///
+/// Unlike [`fiber::Mutex`] locking this mutex doesn't block the fiber, so it
+/// can be used by the tasks spawned within the same [`block_on`] call. The
+/// guard can be held across `.await` points.
+///
+/// The tasks waiting for the lock acquire it in the order they've called
+/// [`Mutex::lock`].
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::{spawn, sync::Mutex};
+/// use std::rc::Rc;
+///
+/// let data = Rc::new(Mutex::new(0));
+/// fiber::block_on(async {
+///     let tasks: Vec<_> = (0..10)
+///         .map(|_| {
+///             let data = data.clone();
+///             spawn(async move { *data.lock().await += 1 })
+///         })
+///         .collect();
+///     for task in tasks {
+///         task.await.unwrap();
+///     }
+///     assert_eq!(*data.lock().await, 10);
+/// });
+/// ```
+///
+/// [`fiber::Mutex`]: crate::fiber::Mutex
+/// [`block_on`]: crate::fiber::block_on
+pub struct Mutex<T: ?Sized> {
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Creates a new unlocked mutex.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self {
+            semaphore: Semaphore::new(1),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Locks the mutex, waiting until it's available.
+    ///
+    /// Dropping the returned future before it completes removes the task from
+    /// the queue of waiters.
+    pub async fn lock(&self) -> MutexGuard<'_, T> {
+        self.semaphore
+            .acquire()
+            .await
+            .expect("mutex semaphore is never closed")
+            .forget();
+        MutexGuard { lock: self }
+    }
+
+    /// Attempts to acquire the lock without waiting. Returns `None` if the
+    /// mutex is locked or other tasks are waiting for it.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let permit = self.semaphore.try_acquire().ok()?;
+        permit.forget();
+        Some(MutexGuard { lock: self })
+    }
+
+    /// Returns `true` if the mutex is currently locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.semaphore.available_permits() == 0
+    }
+
+    /// Returns a mutable reference to the underlying data. No locking is
+    /// needed, because the mutable borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+/// A handle to a locked [`Mutex`]. The mutex is unlocked when the guard is
+/// dropped.
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct MutexGuard<'a, T: ?Sized> {
+    lock: &'a Mutex<T>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: the guard guarantees exclusive access
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard guarantees exclusive access
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.add_permits(1)
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for MutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timeout::IntoTimeout as _, timer};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn try_lock() {
+        let mut m = Mutex::new(1);
+        let g = m.try_lock().unwrap();
+        assert!(m.is_locked());
+        assert!(m.try_lock().is_none());
+        drop(g);
+        assert!(!m.is_locked());
+        *m.get_mut() = 2;
+        assert_eq!(m.into_inner(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn held_across_await() {
+        let m = Rc::new(Mutex::new(vec![]));
+        fiber::block_on(async {
+            let tasks: Vec<_> = (0..3)
+                .map(|i| {
+                    let m = m.clone();
+                    spawn(async move {
+                        let mut g = m.lock().await;
+                        g.push(i);
+                        timer::sleep(Duration::from_millis(1)).await;
+                        g.push(i);
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+        assert_eq!(*m.try_lock().unwrap(), [0, 0, 1, 1, 2, 2]);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn lock_timeout() {
+        let m = Mutex::new(());
+        fiber::block_on(async {
+            let g = m.lock().await;
+            assert!(m.lock().timeout(Duration::from_millis(1)).await.is_err());
+            drop(g);
+            assert!(m.lock().timeout(Duration::from_millis(1)).await.is_ok());
+        });
+    }
+}
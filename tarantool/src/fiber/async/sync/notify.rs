@@ -0,0 +1,250 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+struct Waiter {
+    is_notified: Cell<bool>,
+    waker: Cell<Option<Waker>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Set by `notify_one` if there were no waiters.
+    has_permit: bool,
+    /// Number of `notify_waiters` calls so far.
+    generation: u64,
+    waiters: VecDeque<Rc<Waiter>>,
+}
+
+/// Notifies a single task or all the tasks waiting for an event.
+///
+/// `Notify` doesn't carry any data, it's a basic building block for other
+/// synchronization primitives. It's similar to [`fiber::Cond`], but doesn't
+/// block the fiber.
+///
+/// [`Notify::notify_one`] stores a permit if there are no waiting tasks, so
+/// that the next call to [`Notify::notified`] completes immediately. This way
+/// notifications aren't lost if they happen before the task starts waiting.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::sync::Notify;
+///
+/// let notify = Notify::new();
+/// notify.notify_one();
+/// fiber::block_on(notify.notified());
+/// ```
+///
+/// [`fiber::Cond`]: crate::fiber::Cond
+#[derive(Default)]
+pub struct Notify {
+    state: RefCell<State>,
+}
+
+impl Debug for Notify {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Notify")
+            .field("has_permit", &state.has_permit)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+impl Notify {
+    /// Creates a new `Notify` without a stored permit.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a future which completes once the `Notify` is notified.
+    ///
+    /// The future is considered to be waiting from the moment it's created,
+    /// i.e. it will complete after a [`Notify::notify_waiters`] call which
+    /// happens before it's polled for the first time.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: self.state.borrow().generation,
+            waiter: None,
+        }
+    }
+
+    /// Notifies the first waiting task. If there are no waiting tasks, a
+    /// permit is stored, so that the next [`Notify::notified`] call completes
+    /// immediately. At most one permit is stored.
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            match state.waiters.pop_front() {
+                Some(waiter) => {
+                    waiter.is_notified.set(true);
+                    waiter.waker.take()
+                }
+                None => {
+                    state.has_permit = true;
+                    None
+                }
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake()
+        }
+    }
+
+    /// Notifies all the currently waiting tasks. Doesn't store a permit.
+    pub fn notify_waiters(&self) {
+        let waiters = {
+            let mut state = self.state.borrow_mut();
+            state.generation = state.generation.wrapping_add(1);
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.is_notified.set(true);
+            if let Some(waker) = waiter.waker.take() {
+                waker.wake()
+            }
+        }
+    }
+}
+
+/// Future returned by [`Notify::notified`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    generation: u64,
+    waiter: Option<Rc<Waiter>>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(waiter) = &self.waiter {
+            if waiter.is_notified.get() {
+                self.waiter = None;
+                return Poll::Ready(());
+            }
+            waiter.waker.set(Some(cx.waker().clone()));
+            return Poll::Pending;
+        }
+
+        let mut state = self.notify.state.borrow_mut();
+        if state.generation != self.generation {
+            return Poll::Ready(());
+        }
+        if state.has_permit {
+            state.has_permit = false;
+            return Poll::Ready(());
+        }
+        let waiter = Rc::new(Waiter {
+            is_notified: Cell::new(false),
+            waker: Cell::new(Some(cx.waker().clone())),
+        });
+        state.waiters.push_back(waiter.clone());
+        drop(state);
+        self.waiter = Some(waiter);
+        Poll::Pending
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let waiter = if let Some(waiter) = self.waiter.take() {
+            waiter
+        } else {
+            return;
+        };
+        if waiter.is_notified.get() {
+            // The notification was received but not consumed, pass it on.
+            // This may be a `notify_waiters` notification, but passing it on
+            // only results in a spurious wakeup.
+            self.notify.notify_one();
+        } else {
+            let mut state = self.notify.state.borrow_mut();
+            state.waiters.retain(|w| !Rc::ptr_eq(w, &waiter));
+        }
+    }
+}
+
+impl Debug for Notified<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notified").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::check_yield;
+    use crate::fiber::r#async::{spawn, timeout::IntoTimeout as _, timer};
+    use crate::fiber::YieldResult::DidntYield;
+    use std::time::Duration;
+
+    const _1_MS: Duration = Duration::from_millis(1);
+
+    #[crate::test(tarantool = "crate")]
+    fn permit_is_stored() {
+        let notify = Notify::new();
+        notify.notify_one();
+        notify.notify_one();
+        assert_eq!(
+            check_yield(|| fiber::block_on(notify.notified())),
+            DidntYield(())
+        );
+        // Only one permit is stored
+        let res = fiber::block_on(notify.notified().timeout(_1_MS));
+        assert!(res.is_err());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn notify_one_and_waiters() {
+        let notify = Rc::new(Notify::new());
+        let done = Rc::new(Cell::new(0));
+        fiber::block_on(async {
+            for _ in 0..3 {
+                let notify = notify.clone();
+                let done = done.clone();
+                drop(spawn(async move {
+                    notify.notified().await;
+                    done.set(done.get() + 1);
+                }));
+            }
+            timer::sleep(_1_MS).await;
+            notify.notify_one();
+            timer::sleep(_1_MS).await;
+            assert_eq!(done.get(), 1);
+            notify.notify_waiters();
+            timer::sleep(_1_MS).await;
+            assert_eq!(done.get(), 3);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn notify_waiters_before_poll() {
+        let notify = Notify::new();
+        let notified = notify.notified();
+        notify.notify_waiters();
+        assert_eq!(check_yield(|| fiber::block_on(notified)), DidntYield(()));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn dropped_waiter_passes_notification() {
+        let notify = Notify::new();
+        fiber::block_on(async {
+            let mut first = Box::pin(notify.notified());
+            let second = notify.notified();
+            assert!(futures::poll!(first.as_mut()).is_pending());
+            notify.notify_one();
+            drop(first);
+            second.timeout(_1_MS).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,243 @@
+use super::semaphore::Semaphore;
+use std::cell::UnsafeCell;
+use std::fmt::{self, Debug};
+use std::ops::{Deref, DerefMut};
+
+/// Number of permits a writer acquires, i.e. the maximum number of concurrent
+/// readers.
+const MAX_READS: usize = 1 << 20;
+
+/// An asynchronous reader-writer lock.
+///
+/// Allows any number of readers or at most one writer at a time. The lock is
+/// fair: the tasks acquire it in the order they've requested it, so a waiting
+/// writer blocks the readers which came after it and can't be starved.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::sync::RwLock;
+///
+/// let lock = RwLock::new(5);
+/// fiber::block_on(async {
+///     {
+///         let r1 = lock.read().await;
+///         let r2 = lock.read().await;
+///         assert_eq!(*r1 + *r2, 10);
+///     }
+///     *lock.write().await += 1;
+///     assert_eq!(*lock.read().await, 6);
+/// });
+/// ```
+pub struct RwLock<T: ?Sized> {
+    semaphore: Semaphore,
+    data: UnsafeCell<T>,
+}
+
+impl<T> RwLock<T> {
+    /// Creates a new unlocked `RwLock`.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self {
+            semaphore: Semaphore::new(MAX_READS),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the lock, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Locks the lock with shared read access, waiting until it's available.
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.semaphore
+            .acquire()
+            .await
+            .expect("rwlock semaphore is never closed")
+            .forget();
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Attempts to acquire the lock with shared read access without waiting.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.semaphore.try_acquire().ok()?.forget();
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    /// Locks the lock with exclusive write access, waiting until it's
+    /// available.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.semaphore
+            .acquire_many(MAX_READS)
+            .await
+            .expect("rwlock semaphore is never closed")
+            .forget();
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Attempts to acquire the lock with exclusive write access without
+    /// waiting.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.semaphore.try_acquire_many(MAX_READS).ok()?.forget();
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    /// Returns a mutable reference to the underlying data. No locking is
+    /// needed, because the mutable borrow guarantees exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+/// A handle to a [`RwLock`] locked for reading. The shared access is released
+/// when the guard is dropped.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: there're no writers while the guard exists
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.add_permits(1)
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+/// A handle to a [`RwLock`] locked for writing. The exclusive access is
+/// released when the guard is dropped.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: the guard guarantees exclusive access
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard guarantees exclusive access
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.semaphore.add_permits(MAX_READS)
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timer};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn try_read_write() {
+        let lock = RwLock::new(1);
+        let r1 = lock.try_read().unwrap();
+        let r2 = lock.try_read().unwrap();
+        assert!(lock.try_write().is_none());
+        drop((r1, r2));
+        let mut w = lock.try_write().unwrap();
+        *w = 2;
+        assert!(lock.try_read().is_none());
+        drop(w);
+        assert_eq!(lock.into_inner(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn writer_is_not_starved() {
+        let lock = Rc::new(RwLock::new(0));
+        let log = Rc::new(RefCell::new(vec![]));
+        fiber::block_on(async {
+            let r = lock.read().await;
+            let writer = spawn({
+                let lock = lock.clone();
+                let log = log.clone();
+                async move {
+                    *lock.write().await += 1;
+                    log.borrow_mut().push("write");
+                }
+            });
+            timer::sleep(Duration::from_millis(1)).await;
+            // The writer is waiting, new readers must wait for it
+            assert!(lock.try_read().is_none());
+            let reader = spawn({
+                let lock = lock.clone();
+                let log = log.clone();
+                async move {
+                    assert_eq!(*lock.read().await, 1);
+                    log.borrow_mut().push("read");
+                }
+            });
+            timer::sleep(Duration::from_millis(1)).await;
+            drop(r);
+            reader.await.unwrap();
+            writer.await.unwrap();
+        });
+        assert_eq!(*log.borrow(), ["write", "read"]);
+    }
+}
@@ -0,0 +1,407 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// Error returned from [`Semaphore::acquire`] if the semaphore has been
+/// closed.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("semaphore closed")]
+pub struct AcquireError;
+
+/// Error returned from [`Semaphore::try_acquire`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    /// The semaphore has been closed.
+    #[error("semaphore closed")]
+    Closed,
+    /// The semaphore has not enough available permits (or there are other
+    /// tasks waiting for them).
+    #[error("no permits available")]
+    NoPermits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WaiterState {
+    Waiting,
+    Granted,
+    Closed,
+}
+
+struct Waiter {
+    permits: usize,
+    state: Cell<WaiterState>,
+    waker: Cell<Option<Waker>>,
+}
+
+#[derive(Default)]
+struct State {
+    permits: usize,
+    closed: bool,
+    waiters: VecDeque<Rc<Waiter>>,
+}
+
+impl State {
+    /// Grant permits to the waiters at the front of the queue. Returns the
+    /// wakers of the waiters which must be woken up.
+    #[must_use]
+    fn assign_permits(&mut self) -> Vec<Waker> {
+        let mut wakers = vec![];
+        while let Some(waiter) = self.waiters.front() {
+            if waiter.permits > self.permits {
+                break;
+            }
+            self.permits -= waiter.permits;
+            waiter.state.set(WaiterState::Granted);
+            wakers.extend(waiter.waker.take());
+            self.waiters.pop_front();
+        }
+        wakers
+    }
+}
+
+/// A counting semaphore performing asynchronous permit acquisition.
+///
+/// A semaphore maintains a set of permits. Permits are used to synchronize
+/// access to a shared resource. Tasks waiting for permits are served in the
+/// order they've requested them, i.e. a task requesting many permits blocks
+/// the tasks which came after it.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::sync::Semaphore;
+///
+/// let semaphore = Semaphore::new(3);
+/// fiber::block_on(async {
+///     let _permit = semaphore.acquire().await.unwrap();
+///     assert_eq!(semaphore.available_permits(), 2);
+/// });
+/// ```
+#[derive(Default)]
+pub struct Semaphore {
+    state: RefCell<State>,
+}
+
+impl Debug for Semaphore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Semaphore")
+            .field("permits", &state.permits)
+            .field("closed", &state.closed)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+impl Semaphore {
+    /// The maximum number of permits a semaphore can hold.
+    pub const MAX_PERMITS: usize = usize::MAX >> 3;
+
+    /// Creates a new semaphore with the initial number of permits.
+    ///
+    /// # Panics
+    /// Panics if `permits` exceeds [`Semaphore::MAX_PERMITS`].
+    pub fn new(permits: usize) -> Self {
+        assert!(permits <= Self::MAX_PERMITS, "too many permits");
+        Self {
+            state: RefCell::new(State {
+                permits,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Returns the current number of available permits.
+    #[inline]
+    pub fn available_permits(&self) -> usize {
+        self.state.borrow().permits
+    }
+
+    /// Adds `n` new permits to the semaphore, waking up the waiting tasks if
+    /// possible.
+    ///
+    /// # Panics
+    /// Panics if the total number of permits exceeds
+    /// [`Semaphore::MAX_PERMITS`].
+    pub fn add_permits(&self, n: usize) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            state.permits += n;
+            assert!(state.permits <= Self::MAX_PERMITS, "too many permits");
+            state.assign_permits()
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Acquires a permit from the semaphore.
+    ///
+    /// Returns an error if the semaphore has been closed. Dropping the
+    /// returned future before it completes removes the task from the queue.
+    #[inline]
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Acquires `n` permits from the semaphore. See [`Semaphore::acquire`].
+    ///
+    /// # Panics
+    /// Panics if `n` exceeds [`Semaphore::MAX_PERMITS`].
+    #[inline]
+    pub fn acquire_many(&self, n: usize) -> Acquire<'_> {
+        assert!(n <= Self::MAX_PERMITS, "too many permits");
+        Acquire {
+            semaphore: self,
+            permits: n,
+            waiter: None,
+        }
+    }
+
+    /// Tries to acquire a permit without waiting.
+    #[inline]
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
+    /// Tries to acquire `n` permits without waiting.
+    ///
+    /// Fails with [`TryAcquireError::NoPermits`] if there are other tasks
+    /// waiting for permits even if there are enough permits available, so
+    /// that the waiting tasks aren't starved.
+    pub fn try_acquire_many(&self, n: usize) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            return Err(TryAcquireError::Closed);
+        }
+        if !state.waiters.is_empty() || state.permits < n {
+            return Err(TryAcquireError::NoPermits);
+        }
+        state.permits -= n;
+        Ok(SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+
+    /// Closes the semaphore. All the pending and future `acquire` calls fail
+    /// with [`AcquireError`]. The permits which are already acquired stay
+    /// valid.
+    pub fn close(&self) {
+        let waiters = {
+            let mut state = self.state.borrow_mut();
+            state.closed = true;
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.state.set(WaiterState::Closed);
+            if let Some(waker) = waiter.waker.take() {
+                waker.wake()
+            }
+        }
+    }
+
+    /// Returns `true` if the semaphore has been closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.state.borrow().closed
+    }
+
+    /// Remove the waiter from the queue if it's there. If the permits have
+    /// already been granted to it, they are returned to the semaphore.
+    fn cancel(&self, waiter: &Rc<Waiter>) {
+        match waiter.state.get() {
+            WaiterState::Waiting => {
+                let wakers = {
+                    let mut state = self.state.borrow_mut();
+                    state.waiters.retain(|w| !Rc::ptr_eq(w, waiter));
+                    // The removed waiter could've been blocking the ones
+                    // behind it
+                    state.assign_permits()
+                };
+                wakers.into_iter().for_each(Waker::wake);
+            }
+            WaiterState::Granted => self.add_permits(waiter.permits),
+            WaiterState::Closed => {}
+        }
+    }
+}
+
+/// Future returned by [`Semaphore::acquire`] and [`Semaphore::acquire_many`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    waiter: Option<Rc<Waiter>>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Result<SemaphorePermit<'a>, AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let permits = self.permits;
+        let permit = || SemaphorePermit { semaphore, permits };
+
+        if let Some(waiter) = &self.waiter {
+            let res = match waiter.state.get() {
+                WaiterState::Waiting => {
+                    waiter.waker.set(Some(cx.waker().clone()));
+                    return Poll::Pending;
+                }
+                WaiterState::Granted => Ok(permit()),
+                WaiterState::Closed => Err(AcquireError),
+            };
+            self.waiter = None;
+            return Poll::Ready(res);
+        }
+
+        let mut state = semaphore.state.borrow_mut();
+        if state.closed {
+            return Poll::Ready(Err(AcquireError));
+        }
+        if state.waiters.is_empty() && state.permits >= permits {
+            state.permits -= permits;
+            return Poll::Ready(Ok(permit()));
+        }
+        let waiter = Rc::new(Waiter {
+            permits,
+            state: Cell::new(WaiterState::Waiting),
+            waker: Cell::new(Some(cx.waker().clone())),
+        });
+        state.waiters.push_back(waiter.clone());
+        drop(state);
+        self.waiter = Some(waiter);
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            self.semaphore.cancel(&waiter)
+        }
+    }
+}
+
+impl Debug for Acquire<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Acquire")
+            .field("permits", &self.permits)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A permit acquired from a [`Semaphore`]. The permits are returned to the
+/// semaphore when this is dropped.
+#[must_use = "the permits are released immediately if the guard is unused"]
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Returns the number of permits held by `self`.
+    #[inline(always)]
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Forgets the permits without returning them to the semaphore.
+    #[inline(always)]
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits)
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{spawn, timeout::IntoTimeout as _};
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn try_acquire() {
+        let s = Semaphore::new(2);
+        let p1 = s.try_acquire().unwrap();
+        let p2 = s.try_acquire().unwrap();
+        assert_eq!(s.try_acquire().unwrap_err(), TryAcquireError::NoPermits);
+        drop(p1);
+        assert_eq!(s.available_permits(), 1);
+        p2.forget();
+        assert_eq!(s.available_permits(), 1);
+        s.close();
+        assert_eq!(s.try_acquire().unwrap_err(), TryAcquireError::Closed);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn fifo_order() {
+        let s = Rc::new(Semaphore::new(0));
+        let order = Rc::new(RefCell::new(vec![]));
+        fiber::block_on(async {
+            let tasks: Vec<_> = [2, 1, 1]
+                .iter()
+                .enumerate()
+                .map(|(i, &n)| {
+                    let s = s.clone();
+                    let order = order.clone();
+                    spawn(async move {
+                        let _p = s.acquire_many(n).await.unwrap();
+                        order.borrow_mut().push(i);
+                    })
+                })
+                .collect();
+            fiber::r#async::timer::sleep(Duration::from_millis(1)).await;
+            // Not enough for the first one, so nobody gets a permit
+            s.add_permits(1);
+            fiber::r#async::timer::sleep(Duration::from_millis(1)).await;
+            assert!(order.borrow().is_empty());
+            s.add_permits(1);
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+        assert_eq!(*order.borrow(), [0, 1, 2]);
+        assert_eq!(s.available_permits(), 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancel_acquire() {
+        let s = Semaphore::new(1);
+        fiber::block_on(async {
+            let p = s.acquire().await.unwrap();
+            let res = s.acquire_many(1).timeout(Duration::from_millis(1)).await;
+            assert!(res.is_err());
+            drop(p);
+            assert_eq!(s.available_permits(), 1);
+            let _p = s.acquire().await.unwrap();
+        });
+        assert_eq!(s.available_permits(), 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn close_wakes_waiters() {
+        let s = Rc::new(Semaphore::new(0));
+        fiber::block_on(async {
+            let task = spawn({
+                let s = s.clone();
+                async move { s.acquire().await.map(|p| p.forget()) }
+            });
+            fiber::r#async::timer::sleep(Duration::from_millis(1)).await;
+            s.close();
+            assert_eq!(task.await.unwrap(), Err(AcquireError));
+        });
+    }
+}
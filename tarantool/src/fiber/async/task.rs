@@ -0,0 +1,741 @@
+//! Lightweight tasks executed concurrently on a single fiber.
+//!
+//! [`spawn`] starts a new task on the async runtime of the current fiber (i.e.
+//! the one driven by [`block_on`]). Unlike [`fiber::start_async`] it doesn't
+//! create a new fiber, so thousands of tasks can be in flight at the same time
+//! without the overhead of a fiber stack per each of them.
+//!
+//! Tasks are cancelled when the future passed to [`block_on`] completes.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber;
+//! use tarantool::fiber::r#async::{oneshot, spawn};
+//!
+//! let res = fiber::block_on(async {
+//!     let (tx, rx) = oneshot::channel();
+//!     let task = spawn(async move { rx.await.unwrap() * 2 });
+//!     tx.send(21).unwrap();
+//!     task.await.unwrap()
+//! });
+//! assert_eq!(res, 42);
+//! ```
+//!
+//! [`block_on`]: super::block_on
+//! [`fiber::start_async`]: crate::fiber::start_async
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use super::coio::IoRequest;
+use super::context::ContextExt;
+use super::reactor::Reactor;
+use super::waker::{self, FiberWaker, RcWake};
+
+type TaskId = u64;
+
+////////////////////////////////////////////////////////////////////////////////
+// spawn
+////////////////////////////////////////////////////////////////////////////////
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<Executor>>> = const { RefCell::new(None) };
+}
+
+/// Spawn a new task on the async runtime of the current fiber.
+///
+/// The task starts executing the next time the fiber's runtime is idle, i.e.
+/// the current task returns [`Poll::Pending`]. The returned [`JoinHandle`] can
+/// be used to await the task's result or to [`abort`](JoinHandle::abort) it.
+/// Dropping the handle detaches the task, it still runs to completion.
+///
+/// # Panics
+/// Panics if called outside of [`block_on`](super::block_on).
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let executor = CURRENT.with(|current| current.borrow().clone());
+    let executor = executor.expect("`spawn` must be called from within `fiber::block_on`");
+    executor.spawn(future)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinError
+////////////////////////////////////////////////////////////////////////////////
+
+/// Error returned when awaiting a [`JoinHandle`] of a task which didn't
+/// complete.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was aborted with [`JoinHandle::abort`] or cancelled because
+    /// the runtime it was spawned on was stopped.
+    #[error("task was cancelled")]
+    Cancelled,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinHandle
+////////////////////////////////////////////////////////////////////////////////
+
+struct TaskState<T> {
+    result: RefCell<Option<Result<T, JoinError>>>,
+    is_finished: Cell<bool>,
+    join_waker: RefCell<Option<Waker>>,
+}
+
+impl<T> TaskState<T> {
+    fn new() -> Self {
+        Self {
+            result: RefCell::new(None),
+            is_finished: Cell::new(false),
+            join_waker: RefCell::new(None),
+        }
+    }
+
+    fn finish(&self, result: Result<T, JoinError>) {
+        if self.is_finished.replace(true) {
+            return;
+        }
+        *self.result.borrow_mut() = Some(result);
+        if let Some(waker) = self.join_waker.borrow_mut().take() {
+            waker.wake()
+        }
+    }
+}
+
+/// Type erased [`TaskState`].
+trait Cancel {
+    fn cancel(&self);
+    fn is_finished(&self) -> bool;
+}
+
+impl<T> Cancel for TaskState<T> {
+    fn cancel(&self) {
+        self.finish(Err(JoinError::Cancelled))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.is_finished.get()
+    }
+}
+
+/// An owned permission to await the result of a task spawned with [`spawn`].
+///
+/// Awaiting the handle returns the task's output or a [`JoinError`] if the
+/// task was cancelled. Dropping the handle detaches the task.
+#[must_use = "dropping the handle detaches the task"]
+pub struct JoinHandle<T> {
+    id: TaskId,
+    state: Rc<TaskState<T>>,
+    executor: Weak<Executor>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancel the task. The task's future is dropped right away unless the
+    /// task is currently running (i.e. it aborts itself) in which case it is
+    /// dropped as soon as it returns [`Poll::Pending`].
+    ///
+    /// Awaiting the handle after the task is aborted returns
+    /// [`JoinError::Cancelled`] unless the task has already completed.
+    pub fn abort(&self) {
+        if self.state.is_finished() {
+            return;
+        }
+        self.state.cancel();
+        if let Some(executor) = self.executor.upgrade() {
+            executor.remove(self.id);
+        }
+    }
+
+    /// Returns `true` if the task has completed or was cancelled.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(result) = self.state.result.borrow_mut().take() {
+            return Poll::Ready(result);
+        }
+        if self.state.is_finished() {
+            // The result was already taken
+            return Poll::Ready(Err(JoinError::Cancelled));
+        }
+        *self.state.join_waker.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> std::fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("id", &self.id)
+            .field("is_finished", &self.is_finished())
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinSet
+////////////////////////////////////////////////////////////////////////////////
+
+/// A collection of tasks spawned on the async runtime of the current fiber.
+///
+/// All the tasks are aborted when the `JoinSet` is dropped.
+///
+/// ```no_run
+/// use tarantool::fiber;
+/// use tarantool::fiber::r#async::JoinSet;
+///
+/// let sum = fiber::block_on(async {
+///     let mut set = JoinSet::new();
+///     for i in 0..10 {
+///         set.spawn(async move { i });
+///     }
+///     let mut sum = 0;
+///     while let Some(res) = set.join_next().await {
+///         sum += res.unwrap();
+///     }
+///     sum
+/// });
+/// assert_eq!(sum, 45);
+/// ```
+pub struct JoinSet<T> {
+    handles: Vec<JoinHandle<T>>,
+}
+
+impl<T> JoinSet<T> {
+    /// Create an empty `JoinSet`.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            handles: Vec::new(),
+        }
+    }
+
+    /// Returns the number of tasks in the set.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Returns `true` if there are no tasks in the set.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Abort all the tasks in the set. The tasks stay in the set until they're
+    /// joined with [`JoinSet::join_next`].
+    pub fn abort_all(&mut self) {
+        for handle in &self.handles {
+            handle.abort()
+        }
+    }
+
+    /// Wait for any of the tasks in the set to complete and return its
+    /// result. Returns `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        futures::future::poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T, JoinError>>> {
+        if self.handles.is_empty() {
+            return Poll::Ready(None);
+        }
+        for i in 0..self.handles.len() {
+            if let Poll::Ready(res) = Pin::new(&mut self.handles[i]).poll(cx) {
+                // The task has finished, the handle is no longer needed
+                drop(self.handles.swap_remove(i));
+                return Poll::Ready(Some(res));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T: 'static> JoinSet<T> {
+    /// Spawn a new task on the async runtime of the current fiber and add it
+    /// to the set. See [`spawn`] for details.
+    pub fn spawn<F>(&mut self, future: F)
+    where
+        F: Future<Output = T> + 'static,
+    {
+        self.handles.push(spawn(future))
+    }
+}
+
+impl<T> Default for JoinSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for JoinSet<T> {
+    fn drop(&mut self) {
+        self.abort_all()
+    }
+}
+
+impl<T> std::fmt::Debug for JoinSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinSet").field("len", &self.len()).finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Executor
+////////////////////////////////////////////////////////////////////////////////
+
+/// State shared between the executor and the task wakers.
+struct Shared {
+    ready: RefCell<VecDeque<TaskId>>,
+    fiber_waker: Rc<FiberWaker>,
+}
+
+struct TaskWaker {
+    id: TaskId,
+    is_queued: Cell<bool>,
+    shared: Weak<Shared>,
+}
+
+impl RcWake for TaskWaker {
+    fn wake(&self) {
+        if let Some(shared) = self.shared.upgrade() {
+            if !self.is_queued.replace(true) {
+                shared.ready.borrow_mut().push_back(self.id);
+            }
+            shared.fiber_waker.wake();
+        }
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    state: Rc<dyn Cancel>,
+    task_waker: Rc<TaskWaker>,
+    waker: Waker,
+    deadline: Option<Instant>,
+    /// Blocking operation requested by the task, performed by the executor's
+    /// reactor.
+    io: Option<IoRequest>,
+}
+
+/// Executes the tasks spawned within a [`block_on`](super::block_on) call.
+pub(super) struct Executor {
+    shared: Rc<Shared>,
+    tasks: RefCell<HashMap<TaskId, Task>>,
+    next_id: Cell<TaskId>,
+    /// Deadlines requested by the pending tasks, the earliest on top. An entry
+    /// becomes stale once the task is dropped or requests another deadline,
+    /// such entries are skipped.
+    timers: RefCell<BinaryHeap<Reverse<(Instant, TaskId)>>>,
+    reactor: RefCell<Reactor>,
+}
+
+/// Makes the executor current until dropped.
+pub(super) struct EnterGuard {
+    prev: Option<Rc<Executor>>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
+
+impl Executor {
+    pub(super) fn new() -> Rc<Self> {
+        Rc::new(Self {
+            shared: Rc::new(Shared {
+                ready: Default::default(),
+                fiber_waker: Default::default(),
+            }),
+            tasks: Default::default(),
+            next_id: Cell::new(1),
+            timers: Default::default(),
+            reactor: Default::default(),
+        })
+    }
+
+    /// The waker used to wake up the fiber running the executor.
+    pub(super) fn fiber_waker(&self) -> Rc<FiberWaker> {
+        self.shared.fiber_waker.clone()
+    }
+
+    /// Make `self` the executor used by [`spawn`] until the returned guard is
+    /// dropped.
+    pub(super) fn enter(self: &Rc<Self>) -> EnterGuard {
+        let prev = CURRENT.with(|current| current.borrow_mut().replace(self.clone()));
+        EnterGuard { prev }
+    }
+
+    fn spawn<F>(self: &Rc<Self>, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        let state = Rc::new(TaskState::new());
+        let task_waker = Rc::new(TaskWaker {
+            id,
+            is_queued: Cell::new(true),
+            shared: Rc::downgrade(&self.shared),
+        });
+        let future = {
+            let state = state.clone();
+            async move { state.finish(Ok(future.await)) }
+        };
+        let task = Task {
+            future: Box::pin(future),
+            state: state.clone(),
+            waker: waker::with_rcw(task_waker.clone()),
+            task_waker,
+            deadline: None,
+            io: None,
+        };
+        self.tasks.borrow_mut().insert(id, task);
+        self.shared.ready.borrow_mut().push_back(id);
+        self.shared.fiber_waker.wake();
+
+        JoinHandle {
+            id,
+            state,
+            executor: Rc::downgrade(self),
+        }
+    }
+
+    /// Drop the task with the given `id`.
+    fn remove(&self, id: TaskId) {
+        let task = self.tasks.borrow_mut().remove(&id);
+        // The task is dropped after the borrow is released, because dropping
+        // the future can execute arbitrary code.
+        drop(task);
+    }
+
+    pub(super) fn has_tasks(&self) -> bool {
+        !self.tasks.borrow().is_empty()
+    }
+
+    /// Returns `true` if the timer entry still corresponds to the deadline
+    /// requested by the task.
+    fn is_timer_valid(&self, deadline: Instant, id: TaskId) -> bool {
+        matches!(self.tasks.borrow().get(&id), Some(t) if t.deadline == Some(deadline))
+    }
+
+    /// Returns the earliest deadline requested by the pending tasks.
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        let mut timers = self.timers.borrow_mut();
+        while let Some(&Reverse((deadline, id))) = timers.peek() {
+            if self.is_timer_valid(deadline, id) {
+                return Some(deadline);
+            }
+            timers.pop();
+        }
+        None
+    }
+
+    /// Poll the tasks which were woken up or whose deadline has expired.
+    pub(super) fn poll_tasks(&self) {
+        let now = Instant::now();
+        let mut due: Vec<_> = self.shared.ready.borrow_mut().drain(..).collect();
+        {
+            let mut timers = self.timers.borrow_mut();
+            while let Some(&Reverse((deadline, id))) = timers.peek() {
+                if deadline > now {
+                    break;
+                }
+                timers.pop();
+                if self.is_timer_valid(deadline, id) {
+                    due.push(id);
+                }
+            }
+        }
+        due.sort_unstable();
+        due.dedup();
+
+        for id in due {
+            let task = self.tasks.borrow_mut().remove(&id);
+            let mut task = match task {
+                Some(task) => task,
+                None => continue,
+            };
+            task.task_waker.is_queued.set(false);
+
+            let mut cx = ContextExt::from_waker(&task.waker);
+            let poll = task.future.as_mut().poll(cx.cx());
+            if poll.is_ready() || task.state.is_finished() {
+                // The task has completed or aborted itself
+                continue;
+            }
+            if let Some(deadline) = cx.deadline {
+                // Entries which are due have been popped from the heap above,
+                // otherwise the entry for an unchanged deadline is still there
+                if task.deadline != Some(deadline) || deadline <= now {
+                    self.timers.borrow_mut().push(Reverse((deadline, id)));
+                }
+            }
+            task.deadline = cx.deadline;
+            task.io = cx.take_io_request();
+            self.tasks.borrow_mut().insert(id, task);
+        }
+    }
+
+    /// Block the fiber until any of the tasks is woken up or its blocking
+    /// operation is completed, or until the `timeout` expires.
+    /// `main_request` is the blocking operation requested by the main future
+    /// (the one passed to `block_on`).
+    pub(super) fn wait(
+        &self,
+        main_request: Option<IoRequest>,
+        main_waker: &Waker,
+        timeout: Duration,
+    ) {
+        let mut requests: Vec<_> = self
+            .tasks
+            .borrow()
+            .values()
+            .filter_map(|task| Some((task.io.clone()?, task.waker.clone())))
+            .collect();
+        match main_request {
+            // Nothing else to wait for, no need for the reactor
+            Some(request) if requests.is_empty() && !self.has_tasks() => {
+                request.perform(timeout);
+                return;
+            }
+            Some(request) => requests.push((request, main_waker.clone())),
+            None => {}
+        }
+        // The tasks can't be borrowed while the fiber is blocked, because
+        // they can be aborted from other fibers
+        self.reactor.borrow_mut().wait(
+            requests.iter().map(|(r, w)| (r, w)),
+            &self.shared.fiber_waker,
+            timeout,
+        );
+    }
+
+    /// Cancel all the pending tasks.
+    pub(super) fn shutdown(&self) {
+        loop {
+            let tasks = std::mem::take(&mut *self.tasks.borrow_mut());
+            if tasks.is_empty() {
+                break;
+            }
+            for (_, task) in tasks {
+                task.state.cancel();
+            }
+        }
+        // Stops the fibers used for the blocking operations
+        drop(self.reactor.take());
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::timeout::{self, IntoTimeout as _};
+    use crate::fiber::r#async::{block_on, oneshot, IntoOnDrop as _};
+    use futures::FutureExt as _;
+
+    const _10_MS: Duration = Duration::from_millis(10);
+
+    async fn always_pending() {
+        loop {
+            futures::pending!()
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn spawn_and_join() {
+        let res = block_on(async {
+            let (tx, rx) = oneshot::channel();
+            let consumer = spawn(async move { rx.await.unwrap() * 2 });
+            let producer = spawn(async move { tx.send(21) });
+            producer.await.unwrap().unwrap();
+            consumer.await.unwrap()
+        });
+        assert_eq!(res, 42);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn wake_from_another_fiber() {
+        let (tx, rx) = oneshot::channel();
+        let jh = fiber::start(move || block_on(async move { spawn(rx).await.unwrap() }));
+        fiber::sleep(_10_MS);
+        tx.send(13).unwrap();
+        assert_eq!(jh.join(), Ok(13));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn deadline_in_task() {
+        let res = block_on(async { spawn(always_pending().timeout(_10_MS)).await.unwrap() });
+        assert_eq!(res, Err(timeout::Expired));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn abort() {
+        let dropped = Rc::new(Cell::new(false));
+        block_on(async {
+            let task = spawn(always_pending().on_drop({
+                let dropped = dropped.clone();
+                move || dropped.set(true)
+            }));
+            // Let the task start
+            spawn(async {}).await.unwrap();
+            assert!(!task.is_finished());
+            task.abort();
+            assert!(dropped.get());
+            assert!(task.is_finished());
+            assert_eq!(task.await, Err(JoinError::Cancelled));
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancelled_on_exit() {
+        let dropped = Rc::new(Cell::new(false));
+        let mut task = None;
+        block_on(async {
+            task = Some(spawn(always_pending().on_drop({
+                let dropped = dropped.clone();
+                move || dropped.set(true)
+            })));
+        });
+        let task = task.unwrap();
+        assert!(dropped.get());
+        assert!(task.is_finished());
+        assert_eq!(block_on(task), Err(JoinError::Cancelled));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn join_set() {
+        let sum = block_on(async {
+            let mut set = JoinSet::new();
+            for i in 0..100 {
+                set.spawn(async move {
+                    futures::pending!();
+                    i
+                });
+            }
+            assert_eq!(set.len(), 100);
+            let mut sum = 0;
+            while let Some(res) = set.join_next().await {
+                sum += res.unwrap();
+            }
+            assert!(set.is_empty());
+            sum
+        });
+        assert_eq!(sum, 4950);
+
+        let dropped = Rc::new(Cell::new(0));
+        block_on(async {
+            let mut set = JoinSet::new();
+            for _ in 0..3 {
+                let dropped = dropped.clone();
+                set.spawn(always_pending().on_drop(move || dropped.set(dropped.get() + 1)));
+            }
+            // Let the tasks start
+            spawn(async {}).await.unwrap();
+            drop(set);
+            assert_eq!(dropped.get(), 3);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn io_without_fibers() {
+        use crate::ffi::tarantool as ffi;
+        use crate::fiber::r#async::timer;
+        use std::io::{self, Read as _, Write as _};
+        use std::os::unix::io::AsRawFd as _;
+        use std::os::unix::net::UnixStream;
+
+        struct ReadByte<'a>(&'a UnixStream);
+
+        impl Future for ReadByte<'_> {
+            type Output = u8;
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u8> {
+                let mut buf = [0];
+                match (&*self.0).read(&mut buf) {
+                    Ok(_) => Poll::Ready(buf[0]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        let fd = self.0.as_raw_fd();
+                        unsafe { ContextExt::set_coio_wait(cx, fd, ffi::CoIOFlags::READ) };
+                        Poll::Pending
+                    }
+                    Err(e) => panic!("{}", e),
+                }
+            }
+        }
+
+        fn fiber_count() -> usize {
+            crate::lua_state()
+                .eval(
+                    "local n = 0 for _ in pairs(require('fiber').info()) do n = n + 1 end return n",
+                )
+                .unwrap()
+        }
+
+        let before = fiber_count();
+        let sum = block_on(async {
+            let mut set = JoinSet::new();
+            let mut writers = vec![];
+            for _ in 0..100 {
+                let (tx, rx) = UnixStream::pair().unwrap();
+                rx.set_nonblocking(true).unwrap();
+                set.spawn(async move { ReadByte(&rx).await as u32 });
+                writers.push(tx);
+            }
+            // Let the tasks block on the sockets
+            timer::sleep(_10_MS).await;
+            assert_eq!(fiber_count(), before);
+
+            for (i, tx) in writers.iter_mut().enumerate() {
+                tx.write_all(&[i as u8]).unwrap();
+            }
+            let mut sum = 0;
+            while let Some(res) = set.join_next().await {
+                sum += res.unwrap();
+            }
+            sum
+        });
+        assert_eq!(sum, 4950);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn combinators() {
+        let (a, b) = block_on(async {
+            let a = spawn(async { 1 });
+            let b = spawn(async { 2 });
+            crate::fiber::r#async::join!(a, b)
+        });
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 2));
+
+        let res = block_on(async {
+            let (_tx, rx) = oneshot::channel::<i32>();
+            let mut rx = rx.fuse();
+            let mut timer = spawn(async {}).fuse();
+            crate::fiber::r#async::select! {
+                _ = rx => "received",
+                _ = timer => "timer",
+            }
+        });
+        assert_eq!(res, "timer");
+    }
+}
@@ -54,6 +54,27 @@ pub fn timeout<F: Future>(timeout: Duration, f: F) -> Timeout<F> {
     }
 }
 
+/// Requires a `Future` to complete before the specified `deadline`.
+///
+/// Same as [`timeout`], but the time limit is specified as an absolute
+/// instant, so that several futures can share the same deadline.
+#[inline]
+pub fn timeout_at<F: Future>(deadline: Instant, f: F) -> Timeout<F> {
+    Timeout {
+        future: f,
+        deadline: Some(deadline),
+    }
+}
+
+/// Same as [`timeout_at`], but `None` means there's no time limit.
+#[inline]
+pub(crate) fn timeout_at_opt<F: Future>(deadline: Option<Instant>, f: F) -> Timeout<F> {
+    Timeout {
+        future: f,
+        deadline,
+    }
+}
+
 impl<F: Future> Timeout<F> {
     #[inline]
     fn pin_get_future(self: Pin<&mut Self>) -> Pin<&mut F> {
@@ -105,6 +126,12 @@ pub trait IntoTimeout: Future + Sized {
     fn timeout(self, timeout: Duration) -> Timeout<Self> {
         self::timeout(timeout, self)
     }
+
+    /// Adds a deadline to a future. See [`timeout_at`].
+    #[inline]
+    fn timeout_at(self, deadline: Instant) -> Timeout<Self> {
+        self::timeout_at(deadline, self)
+    }
 }
 
 impl<T> IntoTimeout for T where T: Future + Sized {}
@@ -160,6 +187,21 @@ mod tests {
         assert_eq!(jh.join(), Ok(Ok(400)));
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn shared_deadline() {
+        let (tx1, rx1) = oneshot::channel::<i32>();
+        let (_tx2, rx2) = oneshot::channel::<i32>();
+        let deadline = Instant::now() + Duration::from_millis(10);
+        tx1.send(1).unwrap();
+        let res = fiber::block_on(async move {
+            let a = rx1.timeout_at(deadline).await;
+            let b = timeout_at(deadline, rx2).await;
+            (a, b)
+        });
+        assert_eq!(res, (Ok(Ok(1)), Err(Expired)));
+        assert!(Instant::now() >= deadline);
+    }
+
     #[crate::test(tarantool = "crate")]
     fn timeout_duration_max() {
         // must not panic
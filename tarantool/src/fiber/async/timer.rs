@@ -0,0 +1,461 @@
+//! Utilities for tracking time in async code.
+//!
+//! Timers don't create any fibers or condition variables. A pending timer
+//! reports its deadline to the async runtime (see [`block_on`]), which sleeps
+//! until the earliest of the deadlines requested by all the pending futures.
+//! This means that nested timers and timeouts compose naturally and any
+//! number of them can be in flight at the same time.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber;
+//! use tarantool::fiber::r#async::timer;
+//! use std::time::Duration;
+//!
+//! fiber::block_on(async {
+//!     let mut interval = timer::interval(Duration::from_millis(10));
+//!     for _ in 0..3 {
+//!         interval.tick().await;
+//!     }
+//!     timer::sleep(Duration::from_millis(10)).await;
+//! });
+//! ```
+//!
+//! [`block_on`]: super::block_on
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::context::ContextExt;
+use super::timeout::{self, Timeout};
+
+////////////////////////////////////////////////////////////////////////////////
+// Sleep
+////////////////////////////////////////////////////////////////////////////////
+
+/// Future returned by [`sleep`] and [`sleep_until`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    deadline: Option<Instant>,
+}
+
+impl Sleep {
+    /// Returns the instant at which the future will complete. `None` means
+    /// the future never completes.
+    #[inline(always)]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Reset the future to complete at the new `deadline`.
+    #[inline(always)]
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline)
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Poll::Ready(()),
+            Some(deadline) => {
+                // SAFETY: This is safe as long as the `Context` really
+                // is the `ContextExt`. It's always true within provided
+                // `block_on` async runtime.
+                unsafe { ContextExt::set_deadline(cx, deadline) };
+                Poll::Pending
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Waits until `duration` has elapsed.
+///
+/// Unlike [`fiber::sleep`] this doesn't block the fiber, so other futures on
+/// the same fiber can make progress in the meantime.
+///
+/// A `duration` equal to [`Duration::ZERO`] guarantees that awaiting this
+/// future will **not** result in a fiber yield.
+///
+/// [`fiber::sleep`]: crate::fiber::sleep
+#[inline]
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now().checked_add(duration),
+    }
+}
+
+/// Waits until `deadline` is reached.
+#[inline]
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline: Some(deadline),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Deadline
+////////////////////////////////////////////////////////////////////////////////
+
+/// A point in time by which an operation must be completed.
+///
+/// A `Deadline` is meant to be passed down the call stack, so that the nested
+/// operations share the time limit of the outer one instead of each setting
+/// their own timeout. Use [`Deadline::min`] to further restrict it.
+///
+/// ```no_run
+/// use tarantool::fiber::r#async::timer::Deadline;
+/// use std::time::Duration;
+///
+/// async fn step(deadline: Deadline) {
+///     // Each step takes at most 1 second, but all of them must complete
+///     // before the outer deadline.
+///     let deadline = deadline.min(Deadline::after(Duration::from_secs(1)));
+///     let _ = deadline.run(async { /* ... */ }).await;
+/// }
+///
+/// async fn all_steps() {
+///     let deadline = Deadline::after(Duration::from_secs(3));
+///     for _ in 0..5 {
+///         step(deadline).await;
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Deadline {
+    instant: Option<Instant>,
+}
+
+impl Deadline {
+    /// A deadline which expires after `timeout` from now.
+    #[inline]
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            instant: Instant::now().checked_add(timeout),
+        }
+    }
+
+    /// A deadline which expires at `instant`.
+    #[inline(always)]
+    pub fn at(instant: Instant) -> Self {
+        Self {
+            instant: Some(instant),
+        }
+    }
+
+    /// A deadline which never expires.
+    #[inline(always)]
+    pub fn never() -> Self {
+        Self { instant: None }
+    }
+
+    /// Returns the instant at which the deadline expires or `None` if it
+    /// never expires.
+    #[inline(always)]
+    pub fn instant(&self) -> Option<Instant> {
+        self.instant
+    }
+
+    /// Returns the earliest of the two deadlines.
+    #[inline]
+    pub fn min(self, other: Self) -> Self {
+        match (self.instant, other.instant) {
+            (Some(a), Some(b)) => Self::at(a.min(b)),
+            (a, b) => Self { instant: a.or(b) },
+        }
+    }
+
+    /// Returns the time left until the deadline expires. Returns
+    /// [`Duration::MAX`] if it never expires.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        match self.instant {
+            Some(instant) => instant.saturating_duration_since(Instant::now()),
+            None => Duration::MAX,
+        }
+    }
+
+    /// Returns `true` if the deadline has been reached.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        matches!(self.instant, Some(instant) if Instant::now() >= instant)
+    }
+
+    /// Returns a future which completes when the deadline is reached.
+    #[inline(always)]
+    pub fn sleep(&self) -> Sleep {
+        Sleep {
+            deadline: self.instant,
+        }
+    }
+
+    /// Requires the future `f` to complete before the deadline. See
+    /// [`Timeout`].
+    #[inline(always)]
+    pub fn run<F: Future>(&self, f: F) -> Timeout<F> {
+        timeout::timeout_at_opt(self.instant, f)
+    }
+}
+
+impl From<Instant> for Deadline {
+    #[inline(always)]
+    fn from(instant: Instant) -> Self {
+        Self::at(instant)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Interval
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines the behavior of an [`Interval`] when it misses a tick, i.e. when
+/// [`Interval::tick`] wasn't awaited for longer than the period.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MissedTickBehavior {
+    /// Ticks as fast as possible until it catches up with the schedule. The
+    /// ticks are scheduled at `start + n * period`. This is the default.
+    Burst,
+    /// Restarts the schedule from the moment the late tick happened, i.e. the
+    /// next tick is scheduled at `now + period`.
+    Delay,
+    /// Skips the missed ticks and ticks at the next point of the original
+    /// schedule.
+    Skip,
+}
+
+impl Default for MissedTickBehavior {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Burst
+    }
+}
+
+/// A stream of ticks happening every `period`. Created with [`interval`] or
+/// [`interval_at`].
+#[derive(Debug)]
+pub struct Interval {
+    next: Instant,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// Creates an [`Interval`] which ticks every `period`. The first tick
+/// completes immediately.
+///
+/// # Panics
+/// Panics if `period` is zero.
+#[inline]
+pub fn interval(period: Duration) -> Interval {
+    interval_at(Instant::now(), period)
+}
+
+/// Creates an [`Interval`] which ticks every `period` with the first tick at
+/// `start`.
+///
+/// # Panics
+/// Panics if `period` is zero.
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    assert!(period > Duration::ZERO, "`period` must be non-zero");
+    Interval {
+        next: start,
+        period,
+        missed_tick_behavior: MissedTickBehavior::default(),
+    }
+}
+
+impl Interval {
+    /// Completes when the next tick is reached. Returns the instant at which
+    /// the tick was scheduled.
+    pub async fn tick(&mut self) -> Instant {
+        futures::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick. See [`Interval::tick`].
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        let now = Instant::now();
+        if now < self.next {
+            // SAFETY: This is safe as long as the `Context` really
+            // is the `ContextExt`. It's always true within provided
+            // `block_on` async runtime.
+            unsafe { ContextExt::set_deadline(cx, self.next) };
+            return Poll::Pending;
+        }
+
+        let tick = self.next;
+        let next = tick + self.period;
+        self.next = if now < next {
+            next
+        } else {
+            match self.missed_tick_behavior {
+                MissedTickBehavior::Burst => next,
+                MissedTickBehavior::Delay => now + self.period,
+                MissedTickBehavior::Skip => {
+                    let period = self.period.as_nanos();
+                    let behind = (now - tick).as_nanos() % period;
+                    now + Duration::from_nanos((period - behind) as _)
+                }
+            }
+        };
+        Poll::Ready(tick)
+    }
+
+    /// Resets the interval so that the next tick happens after `period` from
+    /// now.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.next = Instant::now() + self.period
+    }
+
+    /// Returns the period of the interval.
+    #[inline(always)]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the current [`MissedTickBehavior`].
+    #[inline(always)]
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Sets the [`MissedTickBehavior`].
+    #[inline(always)]
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::check_yield;
+    use crate::fiber::r#async::{spawn, timeout::Expired};
+    use crate::fiber::YieldResult::{DidntYield, Yielded};
+
+    const _10_MS: Duration = Duration::from_millis(10);
+    const _100_MS: Duration = Duration::from_millis(100);
+
+    #[crate::test(tarantool = "crate")]
+    fn sleep_zero_doesnt_yield() {
+        assert_eq!(
+            check_yield(|| fiber::block_on(sleep(Duration::ZERO))),
+            DidntYield(())
+        );
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn sleep_and_sleep_until() {
+        let start = Instant::now();
+        assert_eq!(check_yield(|| fiber::block_on(sleep(_10_MS))), Yielded(()));
+        assert!(start.elapsed() >= _10_MS);
+
+        let deadline = Instant::now() + _10_MS;
+        fiber::block_on(sleep_until(deadline));
+        assert!(Instant::now() >= deadline);
+
+        let mut s = sleep_until(Instant::now() + _100_MS);
+        let deadline = Instant::now() + _10_MS;
+        s.reset(deadline);
+        assert_eq!(s.deadline(), Some(deadline));
+        fiber::block_on(s);
+        assert!(Instant::now() >= deadline);
+        assert!(start.elapsed() < _100_MS * 10);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn many_sleeps() {
+        let start = Instant::now();
+        fiber::block_on(async {
+            let tasks: Vec<_> = (0..100)
+                .map(|i| spawn(sleep(Duration::from_millis(i % 10))))
+                .collect();
+            sleep(_10_MS).await;
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+        let elapsed = start.elapsed();
+        assert!(elapsed >= _10_MS);
+        assert!(elapsed < _100_MS * 10);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn interval_ticks() {
+        fiber::block_on(async {
+            let start = Instant::now();
+            let mut interval = interval_at(start, _10_MS);
+            assert_eq!(interval.period(), _10_MS);
+            assert_eq!(interval.missed_tick_behavior(), MissedTickBehavior::Burst);
+            assert_eq!(interval.tick().await, start);
+            assert_eq!(interval.tick().await, start + _10_MS);
+            assert_eq!(interval.tick().await, start + _10_MS * 2);
+            assert!(Instant::now() >= start + _10_MS * 2);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn interval_missed_ticks() {
+        fiber::block_on(async {
+            // Burst: the missed ticks happen immediately
+            let start = Instant::now();
+            let mut interval = interval_at(start, _10_MS);
+            interval.tick().await;
+            fiber::sleep(_10_MS * 3 + _10_MS / 2);
+            assert_eq!(interval.tick().await, start + _10_MS);
+            assert_eq!(interval.tick().await, start + _10_MS * 2);
+            assert_eq!(interval.tick().await, start + _10_MS * 3);
+
+            // Delay: the schedule is shifted
+            let start = Instant::now();
+            let mut interval = interval_at(start, _10_MS);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+            fiber::sleep(_10_MS * 3 + _10_MS / 2);
+            assert_eq!(interval.tick().await, start + _10_MS);
+            let late = Instant::now();
+            assert!(interval.tick().await >= late + _10_MS);
+
+            // Skip: the missed ticks are skipped, the schedule is kept
+            let start = Instant::now();
+            let mut interval = interval_at(start, _10_MS);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            interval.tick().await;
+            fiber::sleep(_10_MS * 3 + _10_MS / 2);
+            assert_eq!(interval.tick().await, start + _10_MS);
+            let next = interval.tick().await;
+            assert!(next > start + _10_MS * 3);
+            assert_eq!((next - start).as_nanos() % _10_MS.as_nanos(), 0);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn deadline() {
+        let never = Deadline::never();
+        assert!(!never.is_expired());
+        assert_eq!(never.remaining(), Duration::MAX);
+        assert_eq!(never.instant(), None);
+
+        let soon = Deadline::after(_10_MS);
+        let later = Deadline::after(_100_MS);
+        assert_eq!(soon.min(later), soon);
+        assert_eq!(later.min(soon), soon);
+        assert_eq!(never.min(soon), soon);
+        assert_eq!(soon.min(never), soon);
+        assert!(soon.remaining() <= _10_MS);
+
+        fiber::block_on(async {
+            assert_eq!(soon.run(async { 42 }).await, Ok(42));
+            assert_eq!(soon.run(later.sleep()).await, Err(Expired));
+            assert!(soon.is_expired());
+            assert!(!later.is_expired());
+            assert_eq!(never.run(sleep(_10_MS)).await, Ok(()));
+        });
+    }
+}
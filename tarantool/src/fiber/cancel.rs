@@ -0,0 +1,521 @@
+//! Cooperative cancellation shared between fibers.
+//!
+//! [`Fiber::cancel`] and [`fiber::is_cancelled`] only concern a single fiber.
+//! A [`CancellationToken`] instead can be shared by any number of fibers and
+//! async tasks working on the same thing (e.g. a request), and cancelling it
+//! cancels all of them. Tokens form a tree: cancelling a token also cancels all
+//! of its [child tokens](CancellationToken::child_token), but not the parent.
+//!
+//! A fiber [attached](CancellationToken::attach) to a token is
+//! [cancelled](Fiber::cancel) along with it. Cancellation interrupts the
+//! blocking operations of the fiber, which then return the [`Cancelled`]
+//! error:
+//! - [`Cond::wait_cancellable`] & [`Cond::wait_timeout_cancellable`],
+//! - [`Channel`] sends & receives, which return the `Cancelled` variants of
+//!   the errors,
+//! - [`fiber::sleep_cancellable`],
+//! - [`CoIOStream`] reads & writes, which return an [`io::Error`] for which
+//!   [`Cancelled::is_cause_of`] returns `true`.
+//!
+//! Futures can be wrapped with [`IntoCancellable::with_cancellation`] to
+//! complete with `Err(Cancelled)` once the token is cancelled, and
+//! [`CancellationToken::cancelled`] waits for the cancellation in async code.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::{self, CancellationToken};
+//!
+//! let token = CancellationToken::new();
+//! let worker = fiber::start({
+//!     let token = token.child_token();
+//!     move || {
+//!         let _attached = token.attach();
+//!         while fiber::sleep_cancellable(std::time::Duration::from_secs(1)).is_ok() {
+//!             // do some work
+//!         }
+//!     }
+//! });
+//! // cancels the worker along with any other work started for the token
+//! token.cancel();
+//! worker.join();
+//! ```
+//!
+//! [`Fiber::cancel`]: super::Fiber::cancel
+//! [`fiber::is_cancelled`]: super::is_cancelled
+//! [`fiber::sleep_cancellable`]: super::sleep_cancellable
+//! [`Cond::wait_cancellable`]: super::Cond::wait_cancellable
+//! [`Cond::wait_timeout_cancellable`]: super::Cond::wait_timeout_cancellable
+//! [`Channel`]: super::Channel
+//! [`CoIOStream`]: crate::coio::CoIOStream
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use super::FiberId;
+
+/// Error returned by a blocking operation interrupted by the cancellation of
+/// the fiber or by a future cancelled via a [`CancellationToken`].
+#[derive(thiserror::Error, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[error("fiber is cancelled")]
+pub struct Cancelled;
+
+impl Cancelled {
+    /// Returns `Err(Cancelled)` if the current fiber is cancelled.
+    #[inline]
+    pub fn check() -> Result<(), Self> {
+        if super::is_cancelled() {
+            return Err(Self);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the io error was caused by the cancellation of the
+    /// fiber, e.g. the one returned from [`CoIOStream`] reads.
+    ///
+    /// [`CoIOStream`]: crate::coio::CoIOStream
+    pub fn is_cause_of(e: &io::Error) -> bool {
+        e.get_ref().map_or(false, |e| e.is::<Self>())
+    }
+}
+
+impl From<Cancelled> for io::Error {
+    /// `Other` is used instead of `Interrupted`, because the std io helpers
+    /// retry the interrupted operations.
+    #[inline]
+    fn from(e: Cancelled) -> Self {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// CancellationToken
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Node {
+    is_cancelled: Cell<bool>,
+    children: RefCell<Vec<Weak<Node>>>,
+    next_key: Cell<u64>,
+    /// Attached fibers by attachment key.
+    fibers: RefCell<HashMap<u64, FiberId>>,
+    /// Wakers of the waiting futures by registration key.
+    wakers: RefCell<HashMap<u64, Waker>>,
+}
+
+impl Node {
+    fn next_key(&self) -> u64 {
+        let key = self.next_key.get();
+        self.next_key.set(key + 1);
+        key
+    }
+
+    fn cancel(&self) {
+        if self.is_cancelled.replace(true) {
+            return;
+        }
+        // Nothing is borrowed while the fibers are cancelled and the wakers
+        // are called, as they may access the token.
+        let wakers = std::mem::take(&mut *self.wakers.borrow_mut());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+        let fibers: Vec<_> = self.fibers.borrow().values().copied().collect();
+        for fiber in fibers {
+            fiber.cancel();
+        }
+        let children = std::mem::take(&mut *self.children.borrow_mut());
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+/// A token for cooperative cancellation of fibers and futures. See the
+/// [module level documentation](self) for details.
+///
+/// Cloned tokens refer to the same cancellation state.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    node: Rc<Node>,
+}
+
+impl CancellationToken {
+    /// Creates a new token which isn't cancelled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a child token, which is cancelled when this token is
+    /// cancelled. Cancelling the child doesn't affect this token.
+    ///
+    /// If this token is already cancelled the child is cancelled as well.
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+        if self.is_cancelled() {
+            child.node.is_cancelled.set(true);
+            return child;
+        }
+        let mut children = self.node.children.borrow_mut();
+        children.retain(|c| c.strong_count() > 0);
+        children.push(Rc::downgrade(&child.node));
+        child
+    }
+
+    /// Cancels the token along with all of its children: the attached fibers
+    /// are [cancelled](super::Fiber::cancel) and the waiting futures are woken
+    /// up. Cancelling a token again does nothing.
+    ///
+    /// This function doesn't yield.
+    #[inline]
+    pub fn cancel(&self) {
+        self.node.cancel()
+    }
+
+    /// Returns `true` if the token is cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.node.is_cancelled.get()
+    }
+
+    /// Returns `Err(Cancelled)` if the token is cancelled.
+    #[inline]
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Attaches the current fiber to the token: until the returned guard is
+    /// dropped, cancelling the token also [cancels](super::Fiber::cancel) the
+    /// fiber, interrupting its blocking operations.
+    ///
+    /// If the token is already cancelled the fiber is cancelled immediately.
+    ///
+    /// Note that tarantool fibers can't be "uncancelled", so the fiber stays
+    /// cancelled after the guard is dropped.
+    #[must_use = "the fiber is detached when the guard is dropped"]
+    pub fn attach(&self) -> AttachGuard {
+        let fiber = super::id();
+        let key = self.node.next_key();
+        if self.is_cancelled() {
+            fiber.cancel();
+        } else {
+            self.node.fibers.borrow_mut().insert(key, fiber);
+        }
+        AttachGuard {
+            node: self.node.clone(),
+            key,
+        }
+    }
+
+    /// Returns a future which completes when the token is cancelled.
+    #[inline]
+    pub fn cancelled(&self) -> WaitForCancellation {
+        WaitForCancellation {
+            waiter: Waiter::new(self.node.clone()),
+        }
+    }
+
+    /// Returns a guard which cancels the token when dropped, e.g. when the
+    /// request handler which owns it returns or panics.
+    #[inline]
+    pub fn drop_guard(self) -> DropGuard {
+        DropGuard { token: Some(self) }
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Detaches the fiber from the token when dropped, see
+/// [`CancellationToken::attach`].
+pub struct AttachGuard {
+    node: Rc<Node>,
+    key: u64,
+}
+
+impl Drop for AttachGuard {
+    fn drop(&mut self) {
+        self.node.fibers.borrow_mut().remove(&self.key);
+    }
+}
+
+impl fmt::Debug for AttachGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttachGuard").finish_non_exhaustive()
+    }
+}
+
+/// Cancels the token when dropped, see [`CancellationToken::drop_guard`].
+#[derive(Debug)]
+pub struct DropGuard {
+    token: Option<CancellationToken>,
+}
+
+impl DropGuard {
+    /// Returns the token without cancelling it.
+    #[inline]
+    pub fn disarm(mut self) -> CancellationToken {
+        self.token.take().expect("only taken here or in drop")
+    }
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        if let Some(token) = self.token.take() {
+            token.cancel()
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// futures
+////////////////////////////////////////////////////////////////////////////////
+
+/// Registration of a future's waker in a token, removed on drop.
+struct Waiter {
+    node: Rc<Node>,
+    key: Option<u64>,
+}
+
+impl Waiter {
+    #[inline(always)]
+    fn new(node: Rc<Node>) -> Self {
+        Self { node, key: None }
+    }
+
+    /// Returns `true` if the token is cancelled, otherwise registers the
+    /// waker.
+    fn poll_cancelled(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.node.is_cancelled.get() {
+            return true;
+        }
+        let key = match self.key {
+            Some(key) => key,
+            None => {
+                let key = self.node.next_key();
+                self.key = Some(key);
+                key
+            }
+        };
+        let mut wakers = self.node.wakers.borrow_mut();
+        match wakers.get_mut(&key) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => *waker = cx.waker().clone(),
+            None => {
+                wakers.insert(key, cx.waker().clone());
+            }
+        }
+        false
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.node.wakers.borrow_mut().remove(&key);
+        }
+    }
+}
+
+/// Future returned by [`CancellationToken::cancelled`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitForCancellation {
+    waiter: Waiter,
+}
+
+impl Future for WaitForCancellation {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.waiter.poll_cancelled(cx) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl fmt::Debug for WaitForCancellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitForCancellation")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Future returned by [`IntoCancellable::with_cancellation`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Cancellable<F> {
+    future: F,
+    waiter: Waiter,
+}
+
+impl<F> Cancellable<F> {
+    #[inline]
+    fn pin_project(self: Pin<&mut Self>) -> (Pin<&mut F>, &mut Waiter) {
+        // This is okay because `future` is pinned when `self` is and `waiter`
+        // is never pinned.
+        unsafe {
+            let this = self.get_unchecked_mut();
+            (Pin::new_unchecked(&mut this.future), &mut this.waiter)
+        }
+    }
+}
+
+impl<F: Future> Future for Cancellable<F> {
+    type Output = Result<F::Output, Cancelled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (future, waiter) = self.pin_project();
+        if waiter.poll_cancelled(cx) {
+            return Poll::Ready(Err(Cancelled));
+        }
+        future.poll(cx).map(Ok)
+    }
+}
+
+impl<F> fmt::Debug for Cancellable<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cancellable").finish_non_exhaustive()
+    }
+}
+
+/// Futures implementing this trait can be stopped by a [`CancellationToken`].
+pub trait IntoCancellable: Future + Sized {
+    /// Wraps the future, so that it completes with `Err(Cancelled)` as soon
+    /// as the `token` is cancelled. Otherwise it completes with `Ok` of the
+    /// future's output.
+    ///
+    /// The inner future is dropped without being polled to completion, so
+    /// it should be ready to be dropped at any await point.
+    #[inline]
+    fn with_cancellation(self, token: &CancellationToken) -> Cancellable<Self> {
+        Cancellable {
+            future: self,
+            waiter: Waiter::new(token.node.clone()),
+        }
+    }
+}
+
+impl<T: Future> IntoCancellable for T {}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::fiber::r#async::{oneshot, timer};
+    use std::time::Duration;
+
+    const _1_MS: Duration = Duration::from_millis(1);
+
+    #[crate::test(tarantool = "crate")]
+    fn token_tree() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+        let other = parent.child_token();
+        child.cancel();
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!parent.is_cancelled());
+        assert!(!other.is_cancelled());
+        parent.cancel();
+        assert!(other.is_cancelled());
+        assert_eq!(parent.check(), Err(Cancelled));
+        assert!(parent.child_token().is_cancelled());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn drop_guard() {
+        let token = CancellationToken::new();
+        drop(token.clone().drop_guard());
+        assert!(token.is_cancelled());
+
+        let token = CancellationToken::new();
+        let token = token.drop_guard().disarm();
+        assert!(!token.is_cancelled());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn attached_fibers_are_cancelled() {
+        let token = CancellationToken::new();
+        let sleeper = fiber::start({
+            let token = token.child_token();
+            move || {
+                let _attached = token.attach();
+                fiber::sleep_cancellable(Duration::from_secs(100))
+            }
+        });
+        let cond = Rc::new(fiber::Cond::new());
+        let waiter = fiber::start({
+            let token = token.clone();
+            let cond = cond.clone();
+            move || {
+                let _attached = token.attach();
+                cond.wait_cancellable()
+            }
+        });
+        let chan = fiber::Channel::<i32>::new(0);
+        let receiver = fiber::start({
+            let token = token.clone();
+            let chan = chan.clone();
+            move || {
+                let _attached = token.attach();
+                chan.recv_timeout(Duration::from_secs(100))
+            }
+        });
+        token.cancel();
+        assert_eq!(sleeper.join(), Err(Cancelled));
+        assert_eq!(waiter.join(), Err(Cancelled));
+        assert_eq!(receiver.join(), Err(fiber::RecvError::Cancelled));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn detached_fiber_is_not_cancelled() {
+        let token = CancellationToken::new();
+        let jh = fiber::start({
+            let token = token.clone();
+            move || {
+                drop(token.attach());
+                fiber::sleep(_1_MS);
+                fiber::is_cancelled()
+            }
+        });
+        token.cancel();
+        assert!(!jh.join());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancellable_future() {
+        let token = CancellationToken::new();
+        let (_tx, rx) = oneshot::channel::<()>();
+        let res = fiber::block_on(async {
+            let t = token.clone();
+            let canceller = crate::fiber::r#async::spawn(async move {
+                timer::sleep(_1_MS).await;
+                t.cancel();
+            });
+            let res = rx.with_cancellation(&token).await;
+            canceller.await.unwrap();
+            res
+        });
+        assert_eq!(res, Err(Cancelled));
+        // Completes immediately for a cancelled token
+        fiber::block_on(token.cancelled());
+        let res = fiber::block_on(async { 1 }.with_cancellation(&CancellationToken::new()));
+        assert_eq!(res, Ok(1));
+    }
+}
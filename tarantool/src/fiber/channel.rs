@@ -110,6 +110,8 @@ impl<T> SendTimeout<T> for Channel<T> {
                 // box_error_message returns "time out"
                 if TarantoolErrorCode::last() == TarantoolErrorCode::System {
                     Err(SendError::Timeout(t))
+                } else if super::is_cancelled() {
+                    Err(SendError::Cancelled(t))
                 } else {
                     Err(SendError::Disconnected(t))
                 }
@@ -140,6 +142,8 @@ impl<T> RecvTimeout<T> for Channel<T> {
                 // box_error_message returns "time out"
                 if TarantoolErrorCode::last() == TarantoolErrorCode::System {
                     Err(RecvError::Timeout)
+                } else if super::is_cancelled() {
+                    Err(RecvError::Cancelled)
                 } else {
                     Err(RecvError::Disconnected)
                 }
@@ -170,7 +174,8 @@ pub trait SendTimeout<T> {
     ///
     /// In case the channel was closed or the current fiber was cancelled the
     /// function returns `SendError<T>` which contains the original message, so
-    /// that the caller has an option to reuse the value.
+    /// that the caller has an option to reuse the value. Cancellation of the
+    /// fiber is reported as [`SendError::Cancelled`].
     ///
     /// This function may perform a **yield** in case the channel buffer is full
     /// and there are no readers ready to receive the message.
@@ -184,7 +189,7 @@ pub trait SendTimeout<T> {
     {
         match self.send_maybe_timeout(t, None) {
             Ok(()) => Ok(()),
-            Err(SendError::Disconnected(t)) => Err(t),
+            Err(SendError::Disconnected(t) | SendError::Cancelled(t)) => Err(t),
             Err(SendError::Timeout(_)) => {
                 unreachable!("100 years have passed, wake up!")
             }
@@ -210,12 +215,16 @@ pub trait SendTimeout<T> {
 pub enum SendError<T> {
     Timeout(T),
     Disconnected(T),
+    /// The current fiber was cancelled, see [`fiber::cancel`].
+    ///
+    /// [`fiber::cancel`]: crate::fiber::cancel
+    Cancelled(T),
 }
 
 impl<T> SendError<T> {
     pub fn into_inner(self) -> T {
         match self {
-            Self::Timeout(t) | Self::Disconnected(t) => t,
+            Self::Timeout(t) | Self::Disconnected(t) | Self::Cancelled(t) => t,
         }
     }
 }
@@ -224,12 +233,16 @@ impl<T> SendError<T> {
 pub enum TrySendError<T> {
     Full(T),
     Disconnected(T),
+    /// The current fiber was cancelled, see [`fiber::cancel`].
+    ///
+    /// [`fiber::cancel`]: crate::fiber::cancel
+    Cancelled(T),
 }
 
 impl<T> TrySendError<T> {
     pub fn into_inner(self) -> T {
         match self {
-            Self::Full(t) | Self::Disconnected(t) => t,
+            Self::Full(t) | Self::Disconnected(t) | Self::Cancelled(t) => t,
         }
     }
 }
@@ -239,6 +252,7 @@ impl<T> From<SendError<T>> for TrySendError<T> {
         match e {
             SendError::Disconnected(t) => Self::Disconnected(t),
             SendError::Timeout(t) => Self::Full(t),
+            SendError::Cancelled(t) => Self::Cancelled(t),
         }
     }
 }
@@ -247,7 +261,8 @@ pub trait RecvTimeout<T> {
     /// Receive a message from the channel.
     ///
     /// In case the channel was closed or the current fiber was cancelled the
-    /// function returns `None`.
+    /// function returns `None`. Use [`Self::recv_timeout`] to distinguish
+    /// between the two.
     ///
     /// This function may perform a **yield** in case there is no message ready.
     fn recv_maybe_timeout(&self, timeout: Option<Duration>) -> Result<T, RecvError>;
@@ -385,12 +400,20 @@ impl<T> Drop for ChannelBox<T> {
 pub enum RecvError {
     Timeout,
     Disconnected,
+    /// The current fiber was cancelled, see [`fiber::cancel`].
+    ///
+    /// [`fiber::cancel`]: crate::fiber::cancel
+    Cancelled,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TryRecvError {
     Empty,
     Disconnected,
+    /// The current fiber was cancelled, see [`fiber::cancel`].
+    ///
+    /// [`fiber::cancel`]: crate::fiber::cancel
+    Cancelled,
 }
 
 impl From<RecvError> for TryRecvError {
@@ -398,6 +421,7 @@ impl From<RecvError> for TryRecvError {
         match e {
             RecvError::Disconnected => Self::Disconnected,
             RecvError::Timeout => Self::Empty,
+            RecvError::Cancelled => Self::Cancelled,
         }
     }
 }
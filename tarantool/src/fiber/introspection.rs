@@ -0,0 +1,374 @@
+//! Inspecting the fibers: ids, names, statuses and resource usage.
+//!
+//! See also:
+//! - [Lua reference: Module fiber](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/)
+
+use std::ffi::CStr;
+use std::fmt;
+use std::num::NonZeroI32;
+
+use once_cell::sync::Lazy;
+
+use crate::error::Error;
+use crate::ffi::helper::has_dyn_symbol;
+use crate::ffi::tarantool as ffi;
+use crate::tlua;
+
+/// Returns `true` if the fiber introspection C api is supported by the
+/// current tarantool executable (since 2.11). Otherwise the information is
+/// requested from lua, which is much slower.
+fn has_c_api() -> bool {
+    static HAS_FIBER_ID: Lazy<bool> =
+        Lazy::new(|| unsafe { has_dyn_symbol(crate::c_str!("fiber_id")) });
+    *HAS_FIBER_ID
+}
+
+/// Identifier of a fiber. Fiber ids are unique and are never reused within
+/// the lifetime of a tarantool instance.
+///
+/// Can be used to refer to any fiber, including the ones which weren't
+/// started from rust, e.g. to [wake it up](Self::wakeup) or
+/// [cancel](Self::cancel) it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FiberId(u64);
+
+impl FiberId {
+    /// Creates a `FiberId` from a numeric fiber id.
+    #[inline(always)]
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Returns the numeric fiber id.
+    #[inline(always)]
+    pub const fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if the fiber exists, i.e. hasn't finished yet or is
+    /// finished but hasn't been joined.
+    #[inline]
+    pub fn exists(&self) -> bool {
+        if has_c_api() {
+            !self.find().is_null()
+        } else {
+            self.status().is_some()
+        }
+    }
+
+    /// Returns the fiber's status or `None` if there's no such fiber.
+    pub fn status(&self) -> Option<FiberStatus> {
+        crate::lua_state()
+            .eval_with(
+                "local f = require('fiber').find(...)
+                if f ~= nil then return f:status() end",
+                self.0,
+            )
+            .ok()
+            .flatten()
+    }
+
+    /// Returns the fiber's name or `None` if there's no such fiber.
+    pub fn name(&self) -> Option<String> {
+        if has_c_api() {
+            let f = self.find();
+            if f.is_null() {
+                return None;
+            }
+            // SAFETY: the name is copied before any yield can happen.
+            let name = unsafe { CStr::from_ptr(ffi::fiber_name(f)) };
+            return Some(name.to_string_lossy().into_owned());
+        }
+        crate::lua_state()
+            .eval_with(
+                "local f = require('fiber').find(...)
+                if f ~= nil then return f:name() end",
+                self.0,
+            )
+            .ok()
+            .flatten()
+    }
+
+    /// Returns the number of context switches of the fiber or `None` if
+    /// there's no such fiber.
+    ///
+    /// Also returns `None` if the current tarantool version doesn't support
+    /// getting the context switches of a single fiber (before 2.11).
+    pub fn csw(&self) -> Option<u64> {
+        if has_c_api() {
+            let f = self.find();
+            if f.is_null() {
+                return None;
+            }
+            return Some(unsafe { ffi::fiber_csw(f) });
+        }
+        crate::lua_state()
+            .eval_with(
+                "local f = require('fiber').find(...)
+                if f ~= nil and f.csw ~= nil then return f:csw() end",
+                self.0,
+            )
+            .ok()
+            .flatten()
+    }
+
+    /// Wakes up the fiber if it's sleeping or waiting for something. Returns
+    /// `false` if there's no such fiber.
+    ///
+    /// The fiber must be prepared for spurious wakeups, see
+    /// [`Fiber::wakeup`](super::Fiber::wakeup).
+    pub fn wakeup(&self) -> bool {
+        if has_c_api() {
+            let f = self.find();
+            if f.is_null() {
+                return false;
+            }
+            unsafe { ffi::fiber_wakeup(f) };
+            return true;
+        }
+        self.call_method("wakeup")
+    }
+
+    /// Cancels the fiber, see [`Fiber::cancel`](super::Fiber::cancel).
+    /// Returns `false` if there's no such fiber.
+    ///
+    /// Unlike `Fiber::cancel`, this doesn't wait for the fiber to finish.
+    /// Can be called from a fiber which is itself cancelled.
+    pub fn cancel(&self) -> bool {
+        if has_c_api() {
+            let f = self.find();
+            if f.is_null() {
+                return false;
+            }
+            unsafe { ffi::fiber_cancel(f) };
+            return true;
+        }
+        self.call_method("cancel")
+    }
+
+    #[inline(always)]
+    fn find(&self) -> *mut ffi::Fiber {
+        unsafe { ffi::fiber_find(self.0) }
+    }
+
+    /// Lua fallback for [`Self::wakeup`] & [`Self::cancel`]. The method is
+    /// called in `pcall`, because it raises if the fiber is dead or if the
+    /// calling fiber is cancelled (even though the method has already done
+    /// its job by then).
+    fn call_method(&self, method: &str) -> bool {
+        crate::lua_state()
+            .eval_with(
+                "local id, method = ...
+                local f = require('fiber').find(id)
+                if f == nil then return false end
+                pcall(f[method], f)
+                return true",
+                (self.0, method),
+            )
+            .unwrap_or(false)
+    }
+}
+
+impl From<u64> for FiberId {
+    #[inline(always)]
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<FiberId> for u64 {
+    #[inline(always)]
+    fn from(id: FiberId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for FiberId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<L: tlua::AsLua> tlua::LuaRead<L> for FiberId {
+    fn lua_read_at_position(lua: L, index: NonZeroI32) -> Result<Self, L> {
+        u64::lua_read_at_position(lua, index).map(Self)
+    }
+}
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Status of a fiber, see [`FiberId::status`].
+    pub enum FiberStatus {
+        /// The fiber is currently executing.
+        Running = "running",
+        /// The fiber is waiting for something or hasn't started yet.
+        Suspended = "suspended",
+        /// The fiber has finished, but hasn't been joined yet.
+        Dead = "dead",
+    }
+}
+
+/// Returns the [`FiberId`] of the current fiber, which can be passed to other
+/// fibers to [wake up](FiberId::wakeup) or [cancel](FiberId::cancel) this one.
+pub fn id() -> FiberId {
+    if has_c_api() {
+        return FiberId(unsafe { ffi::fiber_id(std::ptr::null()) });
+    }
+    crate::lua_state()
+        .eval("return require('fiber').id()")
+        .expect("fiber.id() never fails")
+}
+
+/// Returns the name of the current fiber.
+pub fn name() -> String {
+    if has_c_api() {
+        // SAFETY: the name is copied before any yield can happen.
+        let name = unsafe { CStr::from_ptr(ffi::fiber_name(std::ptr::null())) };
+        return name.to_string_lossy().into_owned();
+    }
+    crate::lua_state()
+        .eval("return require('fiber').self():name()")
+        .expect("fiber.name() never fails")
+}
+
+/// Sets the name of the current fiber. Names longer than 255 bytes are
+/// truncated by tarantool.
+pub fn set_name(name: &str) {
+    if has_c_api() {
+        let len = name.len().min(u32::MAX as usize) as u32;
+        unsafe { ffi::fiber_set_name_n(std::ptr::null_mut(), name.as_ptr().cast(), len) };
+        return;
+    }
+    crate::lua_state()
+        .exec_with("require('fiber').self():name(...)", name)
+        .expect("fiber.name() never fails")
+}
+
+/// Information about a fiber returned by [`info`].
+#[derive(Clone, Debug, PartialEq, Eq, tlua::LuaRead)]
+pub struct FiberInfo {
+    /// Fiber id.
+    pub id: FiberId,
+    /// Fiber name.
+    pub name: String,
+    /// Number of context switches.
+    pub csw: u64,
+    /// Memory used by the fiber in bytes.
+    pub memory_used: u64,
+    /// Memory allocated for the fiber (including the stack) in bytes.
+    pub memory_total: u64,
+    /// Stack frames of the fiber. Empty unless tarantool was built with
+    /// backtrace support.
+    pub backtrace: Vec<String>,
+}
+
+/// Returns information about all the existing fibers, ordered by fiber id.
+///
+/// Collecting the backtraces may be slow, so this shouldn't be called too
+/// often.
+pub fn info() -> Result<Vec<FiberInfo>, Error> {
+    let mut res: Vec<FiberInfo> = crate::lua_state().eval(
+        "local res = {}
+        for id, f in pairs(require('fiber').info()) do
+            local backtrace = {}
+            for _, frame in ipairs(f.backtrace or {}) do
+                if type(frame) == 'table' then
+                    local parts = {}
+                    for k, v in pairs(frame) do
+                        table.insert(parts, string.format('%s: %s', k, v))
+                    end
+                    table.insert(backtrace, table.concat(parts, ', '))
+                else
+                    table.insert(backtrace, tostring(frame))
+                end
+            end
+            table.insert(res, {
+                id = id,
+                name = f.name,
+                csw = f.csw,
+                memory_used = f.memory.used,
+                memory_total = f.memory.total,
+                backtrace = backtrace,
+            })
+        end
+        return res",
+    )?;
+    res.sort_unstable_by_key(|f| f.id);
+    Ok(res)
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn id_and_name() {
+        let me = id();
+        assert_eq!(me.status(), Some(FiberStatus::Running));
+        assert_eq!(me.name(), Some(name()));
+
+        let jh = fiber::Builder::new()
+            .name("introspected")
+            .func(|| {
+                let before = name();
+                set_name("renamed");
+                (id(), before, name())
+            })
+            .start()
+            .unwrap();
+        let (child, before, after) = jh.join();
+        assert_ne!(child, id());
+        assert_eq!(before, "introspected");
+        assert_eq!(after, "renamed");
+        assert!(!child.exists());
+        assert_eq!(child.name(), None);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn wakeup_and_cancel() {
+        let woken = Rc::new(Cell::new(false));
+        let handle = Rc::new(Cell::new(None));
+        let jh = fiber::start({
+            let woken = woken.clone();
+            let handle = handle.clone();
+            move || {
+                handle.set(Some(id()));
+                fiber::fiber_yield();
+                woken.set(true);
+                fiber::sleep(Duration::from_secs(100));
+                fiber::is_cancelled()
+            }
+        });
+        let child = handle.get().unwrap();
+        assert_eq!(child.status(), Some(FiberStatus::Suspended));
+        assert!(child.wakeup());
+        fiber::sleep(Duration::ZERO);
+        assert!(woken.get());
+        assert!(child.cancel());
+        assert!(jh.join());
+        assert!(!child.wakeup());
+        assert!(!FiberId::new(u64::MAX).cancel());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn info_lists_fibers() {
+        let jh = fiber::Builder::new()
+            .name("listed")
+            .func(|| fiber::sleep(Duration::from_millis(10)))
+            .start()
+            .unwrap();
+        let all = info().unwrap();
+        let me = all.iter().find(|f| f.id == id()).unwrap();
+        assert_eq!(me.name, name());
+        assert!(me.memory_total > 0);
+        assert!(all.iter().any(|f| f.name == "listed"));
+        if let Some(csw) = id().csw() {
+            assert!(csw > 0);
+        }
+        jh.join();
+    }
+}
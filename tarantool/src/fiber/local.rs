@@ -0,0 +1,393 @@
+//! Fiber-local storage.
+//!
+//! All the fibers on the same thread share the values declared with
+//! [`thread_local!`], which means e.g. a request context stored there is
+//! visible to the other requests processed concurrently. Values declared with
+//! [`fiber_local!`] are instead stored separately for each fiber.
+//!
+//! A fiber's values are dropped when the fiber exits. For the fibers started
+//! with [`fiber::start`], [`fiber::defer`] and the like this happens right
+//! after the fiber function returns. For the other fibers the values are
+//! bound to the fiber's [`fiber.storage`] and are dropped when it's garbage
+//! collected. Tarantool reuses the fibers processing the requests, but each
+//! request gets a new `fiber.storage`, so the values set while processing one
+//! request are never visible to the next one.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::{fiber, fiber_local};
+//! use std::cell::Cell;
+//!
+//! fiber_local! {
+//!     static TRACE_ID: Cell<u64> = Cell::new(0);
+//! }
+//!
+//! TRACE_ID.with(|id| id.set(1));
+//! fiber::start(|| {
+//!     // each fiber has its own value
+//!     assert_eq!(TRACE_ID.with(Cell::get), 0);
+//!     TRACE_ID.with(|id| id.set(2));
+//! })
+//! .join();
+//! assert_eq!(TRACE_ID.with(Cell::get), 1);
+//! ```
+//!
+//! [`fiber::start`]: crate::fiber::start
+//! [`fiber::defer`]: crate::fiber::defer
+//! [`fiber.storage`]: https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/#fiber-storage
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::log::{say, SayLevel};
+use crate::tlua;
+
+/// Declares new fiber-local storage keys of type [`FiberLocal`].
+///
+/// The syntax is the same as for [`thread_local!`]. The initializer
+/// expression is evaluated lazily, once for each fiber which accesses the
+/// value.
+///
+/// ```no_run
+/// use tarantool::fiber_local;
+/// use std::cell::RefCell;
+///
+/// fiber_local! {
+///     static USER: RefCell<Option<String>> = RefCell::new(None);
+///     pub static COUNTER: std::cell::Cell<u32> = Default::default();
+/// }
+/// ```
+#[macro_export]
+macro_rules! fiber_local {
+    () => {};
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $name:ident : $t:ty = $init:expr;
+        $($rest:tt)*
+    ) => {
+        $(#[$attr])*
+        $vis static $name: $crate::fiber::FiberLocal<$t> = {
+            fn __init() -> $t {
+                $init
+            }
+            $crate::fiber::FiberLocal::new(__init)
+        };
+        $crate::fiber_local! { $($rest)* }
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $name:ident : $t:ty = $init:expr
+    ) => {
+        $crate::fiber_local! { $(#[$attr])* $vis static $name: $t = $init; }
+    };
+}
+
+type Values = HashMap<usize, Rc<dyn Any>>;
+
+thread_local! {
+    /// Values of all the fiber-local keys for each fiber by the id of the
+    /// fiber's storage, see [`storage_id`].
+    static LOCALS: RefCell<HashMap<u64, Values>> = RefCell::new(HashMap::new());
+    static NEXT_STORAGE_ID: Cell<u64> = const { Cell::new(1) };
+}
+
+/// Key of the guard in `fiber.storage`, see [`register_storage`].
+const STORAGE_KEY: &str = "tarantool::fiber_local";
+
+/// A fiber-local storage key which owns its contents. Declared with the
+/// [`fiber_local!`] macro.
+///
+/// Each fiber gets its own copy of the value, which is initialized lazily on
+/// the first access and is dropped when the fiber exits. See the
+/// [module level documentation](self) for details.
+pub struct FiberLocal<T: 'static> {
+    init: fn() -> T,
+}
+
+impl<T: 'static> FiberLocal<T> {
+    #[doc(hidden)]
+    pub const fn new(init: fn() -> T) -> Self {
+        Self { init }
+    }
+
+    #[inline(always)]
+    fn key(&'static self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn get(&'static self, storage: Option<u64>) -> Option<Rc<T>> {
+        let value = LOCALS.with(|l| l.borrow().get(&storage?)?.get(&self.key()).cloned())?;
+        Some(value.downcast().expect("key is unique for the type"))
+    }
+
+    /// Store `value` for the fiber returning the previous one.
+    fn insert(&'static self, storage: Option<u64>, value: Rc<T>) -> Option<Rc<T>> {
+        let storage = storage.unwrap_or_else(register_storage);
+        let old = LOCALS.with(|l| {
+            l.borrow_mut()
+                .entry(storage)
+                .or_default()
+                .insert(self.key(), value)
+        })?;
+        Some(old.downcast().expect("key is unique for the type"))
+    }
+
+    /// Calls `f` with a reference to the current fiber's value, initializing
+    /// it first if needed.
+    ///
+    /// `f` may access other fiber-local values and even replace this one,
+    /// in which case the old value will stay alive until `f` returns.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let storage = storage_id();
+        let value = match self.get(storage) {
+            Some(value) => value,
+            None => {
+                let value = Rc::new((self.init)());
+                self.insert(storage, value.clone());
+                value
+            }
+        };
+        f(&value)
+    }
+
+    /// Sets the current fiber's value. The previous value is dropped.
+    #[inline]
+    pub fn set(&'static self, value: T) {
+        drop(self.replace(value));
+    }
+
+    /// Sets the current fiber's value returning the previous one if it was
+    /// initialized.
+    ///
+    /// # Panics
+    /// Panics if the previous value is currently borrowed by [`Self::with`].
+    pub fn replace(&'static self, value: T) -> Option<T> {
+        self.insert(storage_id(), Rc::new(value)).map(unwrap_rc)
+    }
+
+    /// Takes the current fiber's value out leaving it uninitialized. The next
+    /// call to [`Self::with`] will initialize it again.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed by [`Self::with`].
+    pub fn take(&'static self) -> Option<T> {
+        let storage = storage_id()?;
+        let value = LOCALS.with(|l| l.borrow_mut().get_mut(&storage)?.remove(&self.key()))?;
+        Some(unwrap_rc(
+            value.downcast().expect("key is unique for the type"),
+        ))
+    }
+}
+
+impl<T: 'static> fmt::Debug for FiberLocal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FiberLocal").finish_non_exhaustive()
+    }
+}
+
+fn unwrap_rc<T>(value: Rc<T>) -> T {
+    Rc::try_unwrap(value)
+        .ok()
+        .expect("fiber-local value is borrowed")
+}
+
+/// Drops the values stored for the `fiber.storage` with the given id.
+fn remove_values(storage: u64) {
+    // The values are dropped after the borrow is released, so that their
+    // destructors can access fiber-local storage.
+    let values = LOCALS.with(|l| l.borrow_mut().remove(&storage));
+    drop(values);
+}
+
+/// Returns the id of the current fiber's `fiber.storage` or `None` if no
+/// values were stored for it yet.
+///
+/// The values are keyed by the storage rather than by the fiber id, because
+/// the fibers processing the requests are reused, and each request gets a new
+/// `fiber.storage`.
+fn storage_id() -> Option<u64> {
+    if LOCALS.with(|l| l.borrow().is_empty()) {
+        return None;
+    }
+    let res = crate::lua_state().eval_with(
+        "local guard = require('fiber').self().storage[...]
+        if guard ~= nil then return guard() end",
+        STORAGE_KEY,
+    );
+    match res {
+        Ok(id) => id,
+        Err(e) => {
+            let msg = format!("failed to get fiber-local storage: {}", e);
+            say(SayLevel::Warn, std::file!(), std::line!() as _, None, &msg);
+            None
+        }
+    }
+}
+
+/// Assigns an id to the current fiber's `fiber.storage` and puts a guard into
+/// it, which drops the values when the storage is garbage collected. This
+/// makes sure the values are dropped even if the fiber wasn't started from
+/// rust.
+fn register_storage() -> u64 {
+    struct Guard(u64);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            remove_values(self.0)
+        }
+    }
+
+    let id = NEXT_STORAGE_ID.with(|next| next.replace(next.get() + 1));
+    let guard = Guard(id);
+    let res = crate::lua_state().exec_with(
+        "local key, guard = ...
+        require('fiber').self().storage[key] = guard",
+        (STORAGE_KEY, tlua::function0(move || guard.0)),
+    );
+    if let Err(e) = res {
+        let msg = format!("failed to set up fiber-local storage cleanup: {}", e);
+        say(SayLevel::Warn, std::file!(), std::line!() as _, None, &msg);
+    }
+    id
+}
+
+/// Drops the values of the current fiber. Called when a fiber started from
+/// rust returns.
+pub(crate) fn on_fiber_exit() {
+    if let Some(storage) = storage_id() {
+        remove_values(storage)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use crate::fiber;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    crate::fiber_local! {
+        static COUNTER: Cell<u32> = Cell::new(0);
+        static NAME: RefCell<String> = RefCell::new("init".into());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn separate_values() {
+        COUNTER.with(|c| c.set(1));
+        let jh = fiber::start(|| {
+            let before = COUNTER.with(Cell::get);
+            COUNTER.with(|c| c.set(2));
+            fiber::sleep(std::time::Duration::ZERO);
+            (before, COUNTER.with(Cell::get))
+        });
+        COUNTER.with(|c| c.set(3));
+        assert_eq!(jh.join(), (0, 2));
+        assert_eq!(COUNTER.with(Cell::get), 3);
+        COUNTER.take();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn set_take_replace() {
+        assert_eq!(NAME.take(), None);
+        NAME.with(|n| assert_eq!(*n.borrow(), "init"));
+        NAME.set(RefCell::new("foo".into()));
+        assert_eq!(
+            NAME.replace(RefCell::new("bar".into()))
+                .unwrap()
+                .into_inner(),
+            "foo"
+        );
+        assert_eq!(NAME.take().unwrap().into_inner(), "bar");
+        NAME.with(|n| assert_eq!(*n.borrow(), "init"));
+        NAME.take();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn dropped_on_fiber_exit() {
+        struct DropCheck(Rc<Cell<bool>>);
+        impl Drop for DropCheck {
+            fn drop(&mut self) {
+                self.0.set(true)
+            }
+        }
+
+        crate::fiber_local! {
+            static VALUE: RefCell<Option<DropCheck>> = RefCell::new(None);
+        }
+
+        let dropped = Rc::new(Cell::new(false));
+        let d = dropped.clone();
+        fiber::start(move || {
+            VALUE.with(|v| *v.borrow_mut() = Some(DropCheck(d)));
+        })
+        .join();
+        assert!(dropped.get());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn pooled_fiber_requests() {
+        use crate::net_box::{Conn, ConnOptions, Options};
+        use crate::test::TARANTOOL_LISTEN;
+        use crate::tlua;
+
+        crate::fiber_local! {
+            static REQUEST: Cell<u32> = Cell::new(0);
+        }
+
+        let lua = crate::lua_state();
+        lua.set(
+            "fiber_local_test_set",
+            tlua::function1(|request: u32| {
+                (
+                    fiber::id().as_u64(),
+                    REQUEST.replace(Cell::new(request)).map(Cell::into_inner),
+                )
+            }),
+        );
+        lua.set(
+            "fiber_local_test_get",
+            tlua::function0(|| REQUEST.with(Cell::get)),
+        );
+
+        let conn = Conn::new(
+            ("localhost", TARANTOOL_LISTEN),
+            ConnOptions {
+                user: "test_user".into(),
+                password: "password".into(),
+                ..ConnOptions::default()
+            },
+            None,
+        )
+        .unwrap();
+        // The previous request's storage is garbage collected after the
+        // value is set.
+        let request = |request: u32| -> (u64, Option<u32>, u32) {
+            conn.eval(
+                "local fid, prev = fiber_local_test_set(...)
+                collectgarbage()
+                collectgarbage()
+                return fid, prev, fiber_local_test_get()",
+                &(request,),
+                &Options::default(),
+            )
+            .unwrap()
+            .unwrap()
+            .decode()
+            .unwrap()
+        };
+        let (fid_1, prev_1, value_1) = request(1);
+        let (fid_2, prev_2, value_2) = request(2);
+        // The requests are processed by the same fiber from the pool.
+        assert_eq!(fid_1, fid_2);
+        assert_eq!((prev_1, value_1), (None, 1));
+        assert_eq!((prev_2, value_2), (None, 2));
+
+        lua.set("fiber_local_test_set", tlua::Nil);
+        lua.set("fiber_local_test_get", tlua::Nil);
+    }
+}
@@ -0,0 +1,580 @@
+//! A pool of long-lived worker fibers processing jobs from a bounded queue.
+//!
+//! Starting a new fiber for every job with [`fiber::start`] is relatively
+//! expensive and puts no limit on the number of jobs processed concurrently.
+//! [`Pool`] instead starts a fixed number of worker fibers once, which take
+//! jobs from a bounded queue. When the queue is full [`Pool::submit`] blocks
+//! until there's space and [`Pool::try_submit`] returns an error, which can be
+//! used to apply backpressure.
+//!
+//! The queue is based on [`fiber::Channel`], which isn't available in all
+//! versions of tarantool, see [`has_fiber_channel`].
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::Pool;
+//!
+//! let pool = Pool::builder().workers(4).queue_size(100).build().unwrap();
+//! let handle = pool.submit(|| 6 * 7).unwrap();
+//! assert_eq!(handle.join().unwrap(), 42);
+//!
+//! let metrics = pool.metrics();
+//! assert_eq!(metrics.completed_jobs, 1);
+//!
+//! // Wait for the queued jobs to finish and stop the workers.
+//! pool.shutdown();
+//! ```
+//!
+//! [`fiber::start`]: crate::fiber::start
+//! [`fiber::Channel`]: crate::fiber::Channel
+//! [`has_fiber_channel`]: crate::ffi::has_fiber_channel
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use super::r#async::oneshot;
+use super::{Builder, Channel, Cond, TrySendError, UnitJoinHandle};
+use crate::Result;
+
+/// A job along with the code which sends its result to the [`JobHandle`].
+/// Returns `false` if the job panicked.
+type Job = Box<dyn FnOnce() -> bool>;
+
+enum Message {
+    Job(Job),
+    /// Tells a worker to exit. Sent once for each worker on shutdown after
+    /// all the queued jobs, so the queue is drained first.
+    Stop,
+}
+
+struct Shared {
+    queue: Channel<Message>,
+    /// Signalled whenever a job is taken from the queue or the pool is shut
+    /// down.
+    space: Cond,
+    is_shut_down: Cell<bool>,
+    busy_workers: Cell<usize>,
+    completed_jobs: Cell<u64>,
+    panicked_jobs: Cell<u64>,
+}
+
+impl Shared {
+    /// Returns `true` if a message can be sent without blocking.
+    #[inline]
+    fn has_space(&self) -> bool {
+        self.queue.count() < self.queue.size() || self.queue.has_readers()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Pool
+////////////////////////////////////////////////////////////////////////////////
+
+/// A pool of worker fibers. See the [module level documentation](self) for
+/// details.
+///
+/// Dropping the pool [shuts it down](Self::shutdown), i.e. yields until all
+/// the queued jobs are processed.
+pub struct Pool {
+    shared: Rc<Shared>,
+    workers: RefCell<Vec<UnitJoinHandle<'static>>>,
+}
+
+impl Pool {
+    /// Creates a pool with `workers` worker fibers and a queue of `queue_size`
+    /// jobs. Use [`Pool::builder`] to configure other parameters.
+    #[inline]
+    pub fn new(workers: usize, queue_size: u32) -> Result<Self> {
+        Self::builder()
+            .workers(workers)
+            .queue_size(queue_size)
+            .build()
+    }
+
+    /// Returns a builder for configuring a new pool.
+    #[inline(always)]
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder::new()
+    }
+
+    /// Submits a job to the pool returning a handle to its result.
+    ///
+    /// If the queue is full the current fiber **yields** until there's space.
+    ///
+    /// Returns an error containing `f` if the pool was shut down or the
+    /// current fiber was cancelled while waiting.
+    pub fn submit<F, T>(&self, f: F) -> std::result::Result<JobHandle<T>, SubmitError<F>>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        loop {
+            if self.shared.is_shut_down.get() {
+                return Err(SubmitError::ShutDown(f));
+            }
+            if self.shared.has_space() {
+                break;
+            }
+            if super::is_cancelled() {
+                return Err(SubmitError::Cancelled(f));
+            }
+            self.shared.space.wait();
+        }
+        self.push(f)
+    }
+
+    /// Submits a job to the pool returning a handle to its result. Never
+    /// yields.
+    ///
+    /// Returns an error containing `f` if the queue is full or the pool was
+    /// shut down.
+    pub fn try_submit<F, T>(&self, f: F) -> std::result::Result<JobHandle<T>, SubmitError<F>>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        if self.shared.is_shut_down.get() {
+            return Err(SubmitError::ShutDown(f));
+        }
+        if !self.shared.has_space() {
+            return Err(SubmitError::Full(f));
+        }
+        self.push(f)
+    }
+
+    /// Puts the job into the queue. There should be space in the queue, but
+    /// if sending fails anyway `f` is returned back in the error.
+    fn push<F, T>(&self, f: F) -> std::result::Result<JobHandle<T>, SubmitError<F>>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        // The job is type erased once it's boxed, so `f` is kept in a shared
+        // slot to be able to get it back if the job isn't sent.
+        let slot = Rc::new(Cell::new(Some(f)));
+        let job: Job = Box::new({
+            let slot = slot.clone();
+            move || {
+                let f = slot.take().expect("a job is only run once");
+                let res = panic::catch_unwind(AssertUnwindSafe(f));
+                let is_ok = res.is_ok();
+                // The handle may have been dropped, that's ok
+                let _ = tx.send(res);
+                is_ok
+            }
+        });
+        let res = self.shared.queue.try_send(Message::Job(job));
+        let take = || slot.take().expect("the job wasn't run");
+        match res {
+            Ok(()) => Ok(JobHandle { rx }),
+            Err(TrySendError::Full(_)) => Err(SubmitError::Full(take())),
+            Err(TrySendError::Disconnected(_)) => Err(SubmitError::ShutDown(take())),
+            Err(TrySendError::Cancelled(_)) => Err(SubmitError::Cancelled(take())),
+        }
+    }
+
+    /// Shuts the pool down: new jobs are rejected, the jobs already in the
+    /// queue are processed and then the workers exit. The current fiber
+    /// **yields** until all the workers are joined.
+    ///
+    /// Calling this more than once does nothing. Must not be called from the
+    /// pool's own jobs, as the worker would wait for itself.
+    pub fn shutdown(&self) {
+        if self.shared.is_shut_down.replace(true) {
+            return;
+        }
+        // Wake up the fibers blocked in `submit`
+        self.shared.space.broadcast();
+        let workers = std::mem::take(&mut *self.workers.borrow_mut());
+        let mut is_stop_sent = true;
+        for _ in &workers {
+            if self.shared.queue.send(Message::Stop).is_err() {
+                is_stop_sent = false;
+                break;
+            }
+        }
+        if !is_stop_sent {
+            // The current fiber was cancelled, discard the queued jobs so
+            // that the workers exit right away. The job handles will get
+            // `JobError::Cancelled`.
+            self.shared.queue.clone().close();
+        }
+        for worker in workers {
+            worker.join();
+        }
+    }
+
+    /// Returns `true` if [`Pool::shutdown`] was called.
+    #[inline]
+    pub fn is_shut_down(&self) -> bool {
+        self.shared.is_shut_down.get()
+    }
+
+    /// Returns the number of jobs waiting in the queue.
+    #[inline]
+    pub fn queue_len(&self) -> usize {
+        self.shared.queue.count() as _
+    }
+
+    /// Returns the number of workers currently processing a job.
+    #[inline]
+    pub fn busy_workers(&self) -> usize {
+        self.shared.busy_workers.get()
+    }
+
+    /// Returns a snapshot of the pool's metrics.
+    pub fn metrics(&self) -> PoolMetrics {
+        let shared = &self.shared;
+        PoolMetrics {
+            workers: self.workers.borrow().len(),
+            busy_workers: shared.busy_workers.get(),
+            queue_len: shared.queue.count() as _,
+            queue_size: shared.queue.size() as _,
+            completed_jobs: shared.completed_jobs.get(),
+            panicked_jobs: shared.panicked_jobs.get(),
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shutdown()
+    }
+}
+
+impl Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("metrics", &self.metrics())
+            .field("is_shut_down", &self.is_shut_down())
+            .finish()
+    }
+}
+
+fn worker(shared: Rc<Shared>) {
+    while let Some(msg) = shared.queue.recv() {
+        let job = match msg {
+            Message::Job(job) => job,
+            Message::Stop => break,
+        };
+        shared.space.signal();
+        shared.busy_workers.set(shared.busy_workers.get() + 1);
+        let is_ok = job();
+        shared.busy_workers.set(shared.busy_workers.get() - 1);
+        shared.completed_jobs.set(shared.completed_jobs.get() + 1);
+        if !is_ok {
+            shared.panicked_jobs.set(shared.panicked_jobs.get() + 1);
+        }
+    }
+}
+
+/// Metrics of a [`Pool`] returned by [`Pool::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Number of worker fibers. Zero after the pool is shut down.
+    pub workers: usize,
+    /// Number of workers currently processing a job.
+    pub busy_workers: usize,
+    /// Number of jobs waiting in the queue.
+    pub queue_len: usize,
+    /// Maximum number of jobs in the queue.
+    pub queue_size: usize,
+    /// Number of finished jobs including the panicked ones.
+    pub completed_jobs: u64,
+    /// Number of jobs which panicked.
+    pub panicked_jobs: u64,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PoolBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Configures and creates a [`Pool`], see [`Pool::builder`].
+#[derive(Debug, Clone)]
+pub struct PoolBuilder {
+    workers: usize,
+    queue_size: u32,
+    name: String,
+    stack_size: Option<usize>,
+}
+
+impl PoolBuilder {
+    /// Default number of worker fibers.
+    pub const DEFAULT_WORKERS: usize = 4;
+    /// Default queue size.
+    pub const DEFAULT_QUEUE_SIZE: u32 = 128;
+
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            workers: Self::DEFAULT_WORKERS,
+            queue_size: Self::DEFAULT_QUEUE_SIZE,
+            name: "pool".into(),
+            stack_size: None,
+        }
+    }
+
+    /// Sets the number of worker fibers.
+    ///
+    /// # Panics
+    /// [`PoolBuilder::build`] panics if the number is zero.
+    #[inline]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the maximum number of jobs waiting in the queue. With a zero size
+    /// a job can only be submitted when there's an idle worker.
+    #[inline]
+    pub fn queue_size(mut self, queue_size: u32) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    /// Sets the name of the pool. The worker fibers are named `<name>/<i>`.
+    #[inline]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the stack size of the worker fibers, see
+    /// [`fiber::Builder::stack_size`](super::Builder::stack_size).
+    #[inline]
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Creates the pool and starts the workers.
+    pub fn build(self) -> Result<Pool> {
+        assert!(self.workers > 0, "pool must have at least one worker");
+        let shared = Rc::new(Shared {
+            queue: Channel::new(self.queue_size),
+            space: Cond::new(),
+            is_shut_down: Cell::new(false),
+            busy_workers: Cell::new(0),
+            completed_jobs: Cell::new(0),
+            panicked_jobs: Cell::new(0),
+        });
+        let pool = Pool {
+            shared,
+            workers: RefCell::new(Vec::with_capacity(self.workers)),
+        };
+        for i in 0..self.workers {
+            let mut builder = Builder::new().name(format!("{}/{}", self.name, i));
+            if let Some(stack_size) = self.stack_size {
+                builder = builder.stack_size(stack_size)?;
+            }
+            let shared = pool.shared.clone();
+            // If this fails the already started workers are stopped when the
+            // pool is dropped
+            let jh = builder.proc(move || worker(shared)).start()?;
+            pool.workers.borrow_mut().push(jh);
+        }
+        Ok(pool)
+    }
+}
+
+impl Default for PoolBuilder {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// JobHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle to the result of a job submitted to a [`Pool`].
+///
+/// The result can be received either by blocking the fiber with
+/// [`JobHandle::join`] or by `.await`ing the handle. Dropping the handle
+/// doesn't cancel the job.
+#[must_use = "the job's result is lost if the handle is dropped"]
+pub struct JobHandle<T> {
+    rx: oneshot::Receiver<std::thread::Result<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Yields until the job is finished and returns its result.
+    #[inline]
+    pub fn join(self) -> std::result::Result<T, JobError> {
+        super::block_on(self)
+    }
+}
+
+impl<T> Future for JobHandle<T> {
+    type Output = std::result::Result<T, JobError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match Pin::new(&mut self.rx).poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(match res {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(JobError::Panicked(e)),
+            Err(_) => Err(JobError::Cancelled),
+        })
+    }
+}
+
+impl<T> Debug for JobHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobHandle").finish_non_exhaustive()
+    }
+}
+
+/// Error returned by [`JobHandle::join`].
+#[derive(thiserror::Error)]
+pub enum JobError {
+    /// The job panicked. Contains the panic payload.
+    #[error("job panicked")]
+    Panicked(Box<dyn Any + Send + 'static>),
+    /// The job was discarded without being run.
+    #[error("job was cancelled")]
+    Cancelled,
+}
+
+impl Debug for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(_) => f.write_str("Panicked(..)"),
+            Self::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+/// Error returned by [`Pool::submit`] and [`Pool::try_submit`]. Contains the
+/// job which couldn't be submitted.
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitError<F> {
+    /// The queue is full.
+    #[error("pool queue is full")]
+    Full(F),
+    /// The pool was shut down.
+    #[error("pool is shut down")]
+    ShutDown(F),
+    /// The current fiber was cancelled while waiting for space in the queue.
+    #[error("fiber was cancelled")]
+    Cancelled(F),
+}
+
+impl<F> SubmitError<F> {
+    /// Returns the job which couldn't be submitted.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        match self {
+            Self::Full(f) | Self::ShutDown(f) | Self::Cancelled(f) => f,
+        }
+    }
+}
+
+impl<F> Debug for SubmitError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::ShutDown(_) => f.write_str("ShutDown(..)"),
+            Self::Cancelled(_) => f.write_str("Cancelled(..)"),
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::time::Duration;
+
+    const _1_MS: Duration = Duration::from_millis(1);
+
+    #[crate::test(tarantool = "crate")]
+    fn submit_and_join() {
+        let pool = Pool::new(2, 8).unwrap();
+        let handles: Vec<_> = (0..10)
+            .map(|i| pool.submit(move || i * 2).unwrap())
+            .collect();
+        let res: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(res, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+        let metrics = pool.metrics();
+        assert_eq!(metrics.workers, 2);
+        assert_eq!(metrics.completed_jobs, 10);
+        assert_eq!(metrics.panicked_jobs, 0);
+        assert_eq!(metrics.queue_size, 8);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn backpressure() {
+        let pool = Pool::new(1, 1).unwrap();
+        let blocker = pool.submit(|| fiber::sleep(10 * _1_MS)).unwrap();
+        // Let the worker take the first job
+        fiber::sleep(Duration::ZERO);
+        assert_eq!(pool.busy_workers(), 1);
+        let queued = pool.try_submit(|| 1).unwrap();
+        assert_eq!(pool.queue_len(), 1);
+        let e = pool.try_submit(|| 2).unwrap_err();
+        assert!(matches!(e, SubmitError::Full(_)));
+        assert_eq!((e.into_inner())(), 2);
+        // Blocks until the worker takes the queued job
+        let third = pool.submit(|| 3).unwrap();
+        blocker.join().unwrap();
+        assert_eq!(queued.join().unwrap(), 1);
+        assert_eq!(third.join().unwrap(), 3);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panicking_job() {
+        let pool = Pool::new(1, 1).unwrap();
+        let e = pool.submit(|| panic!("oops")).unwrap().join().unwrap_err();
+        assert!(matches!(e, JobError::Panicked(_)));
+        // The worker survives
+        assert_eq!(pool.submit(|| 1).unwrap().join().unwrap(), 1);
+        assert_eq!(pool.metrics().panicked_jobs, 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn shutdown_drains_queue() {
+        let pool = Pool::builder()
+            .name("test_pool")
+            .workers(2)
+            .queue_size(16)
+            .build()
+            .unwrap();
+        let done = Rc::new(Cell::new(0));
+        for _ in 0..10 {
+            let done = done.clone();
+            let _ = pool.submit(move || {
+                fiber::sleep(_1_MS);
+                done.set(done.get() + 1);
+            });
+        }
+        pool.shutdown();
+        assert_eq!(done.get(), 10);
+        assert!(pool.is_shut_down());
+        assert_eq!(pool.metrics().workers, 0);
+        let e = pool.try_submit(|| ()).unwrap_err();
+        assert!(matches!(e, SubmitError::ShutDown(_)));
+        // Second call does nothing
+        pool.shutdown();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn await_handle() {
+        let pool = Pool::new(1, 0).unwrap();
+        let res = fiber::block_on(async {
+            let h = pool.submit(|| "hello").unwrap();
+            h.await
+        });
+        assert_eq!(res.unwrap(), "hello");
+    }
+}
@@ -0,0 +1,372 @@
+//! Scoped fibers, see [`scope`].
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use super::{Builder, NoFunc, UnitJoinHandle};
+use crate::Result;
+
+type Payload = Box<dyn Any + Send + 'static>;
+
+/// Creates a scope for spawning scoped fibers.
+///
+/// The function passed to `scope` is provided a [`Scope`] object, through
+/// which scoped fibers can be [spawned](Scope::spawn).
+///
+/// Unlike non-scoped fibers, scoped fibers can borrow non-`'static` data, as
+/// the scope guarantees all fibers will be joined at the end of the scope.
+///
+/// All fibers spawned within the scope that haven't been manually joined will
+/// be automatically joined before this function returns. If `f` panics, the
+/// fibers which are still running are [cancelled](super::Fiber::cancel)
+/// first, so they should check [`fiber::is_cancelled`] in long loops.
+///
+/// # Panics
+///
+/// If any of the automatically joined fibers panicked, this function will
+/// resume the first such panic after all the fibers are joined. Panics in the
+/// fibers joined with [`ScopedJoinHandle::join`] are returned from there
+/// instead.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::fiber;
+///
+/// let mut a = vec![1, 2, 3];
+/// let mut x = 0;
+///
+/// fiber::scope(|s| {
+///     s.spawn(|| {
+///         // We can borrow `a` here.
+///         println!("hello from the first scoped fiber: {:?}", a);
+///     });
+///     s.spawn(|| {
+///         // We can even mutably borrow `x` here,
+///         // because no other fibers are using it.
+///         x += a[0] + a[2];
+///     });
+///     println!("hello from the main fiber");
+/// });
+///
+/// // After the scope, we can modify and access our variables again:
+/// a.push(4);
+/// assert_eq!(x, a.len());
+/// ```
+///
+/// [`fiber::is_cancelled`]: super::is_cancelled
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    let scope = Scope {
+        data: ScopeData {
+            fibers: RefCell::new(HashMap::new()),
+            next_id: Cell::new(0),
+            unhandled_panic: Rc::new(RefCell::new(None)),
+        },
+        scope: PhantomData,
+        env: PhantomData,
+    };
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+    if res.is_err() {
+        scope.data.cancel_all();
+    }
+    scope.data.join_all();
+
+    let res = match res {
+        Ok(res) => res,
+        Err(e) => panic::resume_unwind(e),
+    };
+    if let Some(e) = scope.data.unhandled_panic.take() {
+        panic::resume_unwind(e)
+    }
+    res
+}
+
+/// A scope to spawn scoped fibers in. See [`scope`] for details.
+pub struct Scope<'scope, 'env: 'scope> {
+    data: ScopeData,
+    /// Invariance over 'scope, to make sure 'scope cannot shrink,
+    /// which is necessary for soundness.
+    ///
+    /// Without invariance, this would compile fine but be unsound:
+    ///
+    /// ```compile_fail
+    /// tarantool::fiber::scope(|s| {
+    ///     s.spawn(|| {
+    ///         let a = String::from("abcd");
+    ///         s.spawn(|| println!("{a:?}")); // might run after `a` is dropped
+    ///     });
+    /// });
+    /// ```
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+struct ScopeData {
+    /// Fibers which haven't been joined yet. The lifetimes are erased, see
+    /// [`Scope::spawn_with`].
+    fibers: RefCell<HashMap<u64, UnitJoinHandle<'static>>>,
+    next_id: Cell<u64>,
+    /// The first panic of a fiber whose result wasn't received via
+    /// [`ScopedJoinHandle::join`].
+    unhandled_panic: Rc<RefCell<Option<Payload>>>,
+}
+
+impl ScopeData {
+    fn join_all(&self) {
+        // Fibers can spawn more fibers within the scope, so repeat until
+        // there's nothing left
+        loop {
+            let fibers = std::mem::take(&mut *self.fibers.borrow_mut());
+            if fibers.is_empty() {
+                break;
+            }
+            let mut fibers: Vec<_> = fibers.into_iter().collect();
+            fibers.sort_unstable_by_key(|&(id, _)| id);
+            for (_, jh) in fibers {
+                jh.join();
+            }
+        }
+    }
+
+    fn cancel_all(&self) {
+        for jh in self.fibers.borrow().values() {
+            jh.cancel();
+        }
+    }
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawns a new fiber within a scope, returning a [`ScopedJoinHandle`]
+    /// for it. The new fiber starts executing immediately, same as with
+    /// [`fiber::start`](super::start).
+    ///
+    /// Unlike non-scoped fibers, fibers spawned with this function may
+    /// borrow non-`'static` data from the outside the scope. See [`scope`]
+    /// for details.
+    ///
+    /// If you want to specify the name or the stack size of the fiber, use
+    /// [`Scope::spawn_with`] instead.
+    ///
+    /// # Panics
+    /// Panics if the fiber couldn't be created.
+    pub fn spawn<F, T>(&'scope self, f: F) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + 'scope,
+        T: 'scope,
+    {
+        self.spawn_with(Builder::new(), f)
+            .expect("failed to spawn a scoped fiber")
+    }
+
+    /// Spawns a new fiber configured by the `builder` within a scope. See
+    /// [`Scope::spawn`].
+    pub fn spawn_with<F, T>(
+        &'scope self,
+        builder: Builder<NoFunc>,
+        f: F,
+    ) -> Result<ScopedJoinHandle<'scope, T>>
+    where
+        F: FnOnce() -> T + 'scope,
+        T: 'scope,
+    {
+        let packet = Rc::new(Packet {
+            result: RefCell::new(None),
+            unhandled_panic: self.data.unhandled_panic.clone(),
+        });
+        let main: Box<dyn FnOnce() + 'scope> = Box::new({
+            let packet = packet.clone();
+            move || {
+                let res = panic::catch_unwind(AssertUnwindSafe(f));
+                *packet.result.borrow_mut() = Some(res);
+            }
+        });
+        // SAFETY: `scope` joins all the fibers before returning (even if it
+        // panics), so nothing borrowed for 'scope is accessed afterwards.
+        let main: Box<dyn FnOnce() + 'static> = unsafe { std::mem::transmute(main) };
+        let jh = builder.proc(main).start()?;
+
+        let id = self.data.next_id.get();
+        self.data.next_id.set(id + 1);
+        self.data.fibers.borrow_mut().insert(id, jh);
+        Ok(ScopedJoinHandle {
+            id,
+            scope: &self.data,
+            packet,
+        })
+    }
+}
+
+impl std::fmt::Debug for Scope<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scope")
+            .field("num_running_fibers", &self.data.fibers.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The result of a scoped fiber and the shared state of the scope.
+struct Packet<T> {
+    result: RefCell<Option<std::thread::Result<T>>>,
+    unhandled_panic: Rc<RefCell<Option<Payload>>>,
+}
+
+impl<T> Drop for Packet<T> {
+    fn drop(&mut self) {
+        // The result wasn't received, so the panic is propagated by the scope
+        if let Some(Err(e)) = self.result.get_mut().take() {
+            self.unhandled_panic.borrow_mut().get_or_insert(e);
+        }
+    }
+}
+
+/// An owned permission to join on a scoped fiber (block on its termination).
+///
+/// See [`Scope::spawn`] for details.
+pub struct ScopedJoinHandle<'scope, T> {
+    id: u64,
+    scope: &'scope ScopeData,
+    packet: Rc<Packet<T>>,
+}
+
+impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    /// Waits for the associated fiber to finish.
+    ///
+    /// If the fiber panicked, `Err` is returned with the panic payload.
+    pub fn join(self) -> std::thread::Result<T> {
+        let jh = self.scope.fibers.borrow_mut().remove(&self.id);
+        if let Some(jh) = jh {
+            jh.join();
+        }
+        let res = self.packet.result.borrow_mut().take();
+        res.expect("fiber function must have been called")
+    }
+
+    /// Returns `true` if the fiber function has returned or panicked.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.packet.result.borrow().is_some()
+    }
+
+    /// Cancels the fiber, see [`Fiber::cancel`](super::Fiber::cancel). The
+    /// fiber must check [`fiber::is_cancelled`](super::is_cancelled) to stop.
+    pub fn cancel(&self) {
+        if self.is_finished() {
+            return;
+        }
+        if let Some(jh) = self.scope.fibers.borrow().get(&self.id) {
+            jh.cancel();
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ScopedJoinHandle<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopedJoinHandle")
+            .field("is_finished", &self.is_finished())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn borrows_and_joins() {
+        let data = [1, 2, 3];
+        let mut sum = 0;
+        let res = scope(|s| {
+            let h1 = s.spawn(|| {
+                fiber::sleep(Duration::from_millis(1));
+                data.iter().sum::<i32>()
+            });
+            s.spawn(|| {
+                fiber::sleep(Duration::from_millis(1));
+                sum = data.len();
+            });
+            h1.join().unwrap()
+        });
+        assert_eq!(res, 6);
+        // The second fiber was joined automatically
+        assert_eq!(sum, 3);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn nested_spawn() {
+        let count = Cell::new(0);
+        scope(|s| {
+            let count = &count;
+            for _ in 0..3 {
+                s.spawn(move || {
+                    fiber::sleep(Duration::ZERO);
+                    s.spawn(move || {
+                        fiber::sleep(Duration::ZERO);
+                        count.set(count.get() + 1);
+                    });
+                    count.set(count.get() + 1);
+                });
+            }
+        });
+        assert_eq!(count.get(), 6);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panic_in_joined_fiber() {
+        let res = scope(|s| {
+            let h = s.spawn(|| -> i32 { panic!("oops") });
+            h.join()
+        });
+        let e = res.unwrap_err();
+        assert_eq!(e.downcast_ref::<&str>(), Some(&"oops"));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panic_propagated_from_scope() {
+        let res = panic::catch_unwind(|| {
+            scope(|s| {
+                s.spawn(|| panic!("first"));
+                s.spawn(|| panic!("second"));
+            })
+        });
+        let e = res.unwrap_err();
+        assert_eq!(e.downcast_ref::<&str>(), Some(&"first"));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancelled_on_panic() {
+        let cancelled = Cell::new(false);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            scope(|s| {
+                s.spawn(|| {
+                    while !fiber::is_cancelled() {
+                        fiber::sleep(Duration::from_millis(1));
+                    }
+                    cancelled.set(true);
+                });
+                panic!("parent");
+            })
+        }));
+        assert!(res.is_err());
+        assert!(cancelled.get());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn spawn_with_builder() {
+        let name = scope(|s| {
+            s.spawn_with(fiber::Builder::new().name("scoped"), fiber::name)
+                .unwrap()
+                .join()
+                .unwrap()
+        });
+        assert_eq!(name, "scoped");
+    }
+}
@@ -18,7 +18,7 @@ use serde::{Deserialize, Serialize};
 use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
 use crate::msgpack;
-use crate::tuple::{ToTupleBuffer, Tuple, TupleBuffer};
+use crate::tuple::{Encode, ToTupleBuffer, Tuple, TupleBuffer};
 use crate::tuple_from_box_api;
 use crate::util::NumOrStr;
 
@@ -803,6 +803,107 @@ impl Index {
     }
 }
 
+/// Data manipulation methods common for local and remote indexes.
+///
+/// Allows writing code which is generic over where the data is stored, e.g.
+/// test the code with a local [`Index`] and run it against a
+/// [`net_box::RemoteIndex`](crate::net_box::RemoteIndex).
+///
+/// The remote implementations use the default request options, use the
+/// inherent methods of the remote types to specify a timeout.
+///
+/// See also [`SpaceApi`](crate::space::SpaceApi).
+pub trait IndexApi {
+    /// The iterator over the tuples returned from [`IndexApi::select`].
+    type Iterator: Iterator<Item = Tuple>;
+
+    /// Get a tuple from the index by the `key`, see [`Index::get`].
+    fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer;
+
+    /// Select the tuples matching the `key`, see [`Index::select`].
+    fn select<K>(&self, iterator_type: IteratorType, key: &K) -> Result<Self::Iterator, Error>
+    where
+        K: ToTupleBuffer;
+
+    /// Update the tuple identified by the `key`, see [`Index::update`].
+    fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode;
+
+    /// Update or insert a tuple, see [`Index::upsert`].
+    fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode;
+
+    /// Delete the tuple identified by the `key`, see [`Index::delete`].
+    fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer;
+
+    /// Count the tuples matching the `key`, see [`Index::count`].
+    fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer;
+}
+
+impl IndexApi for Index {
+    type Iterator = IndexIterator;
+
+    #[inline(always)]
+    fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        Index::get(self, key)
+    }
+
+    #[inline(always)]
+    fn select<K>(&self, iterator_type: IteratorType, key: &K) -> Result<Self::Iterator, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        Index::select(self, iterator_type, key)
+    }
+
+    #[inline(always)]
+    fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        Index::update(self, key, ops)
+    }
+
+    #[inline(always)]
+    fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        Index::upsert(self, value, ops)
+    }
+
+    #[inline(always)]
+    fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        Index::delete(self, key)
+    }
+
+    #[inline(always)]
+    fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        Index::count(self, iterator_type, key)
+    }
+}
+
 /// Index iterator. Can be used with `for` statement.
 pub struct IndexIterator {
     ptr: *mut ffi::BoxIterator,
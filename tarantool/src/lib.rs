@@ -5,8 +5,10 @@
 //! - [Fibers: fiber attributes, conditional variables, latches](fiber)
 //! - [CoIO](coio)
 //! - [Transactions](transaction)
+//! - [Events](event): broadcasting values to local & remote watchers
 //! - [Schema management](schema)
 //! - [Protocol implementation](net_box) (`net.box`): CRUD, stored procedure call, triggers
+//! - [Async network client](network::client) for the iproto protocol
 //! - [Tuple utils](mod@tuple)
 //! - [Decimal numbers](mod@decimal)
 //! - [Logging](log) (see <https://docs.rs/log/>)
@@ -19,7 +21,10 @@
 //! ### Features
 //!
 //! - `net_box` - Enables protocol implementation (enabled by default)
+//! - `network_client` - Enables the async [network client](network::client)
+//!   (enabled by default)
 //! - `schema` - Enables schema manipulation utils (WIP as for now)
+//! - `tls` - Enables [TLS encrypted connections](tls) (requires OpenSSL)
 //!
 //! ### Prerequisites
 //!
@@ -158,6 +163,7 @@ pub mod decimal;
 #[doc(hidden)]
 pub mod define_str_enum;
 pub mod error;
+pub mod event;
 pub mod ffi;
 pub mod fiber;
 pub mod index;
@@ -165,9 +171,7 @@ pub mod log;
 #[doc(hidden)]
 pub mod msgpack;
 pub mod net_box;
-// Temporarily private as it is in development
-#[allow(unused)]
-mod network;
+pub mod network;
 pub mod proc;
 pub mod schema;
 pub mod sequence;
@@ -176,9 +180,12 @@ pub mod space;
 pub mod sql;
 #[cfg(feature = "test")]
 pub mod test;
+pub mod tls;
 pub mod transaction;
 pub mod trigger;
 pub mod tuple;
+pub mod tx_thread;
+pub mod uri;
 pub mod util;
 pub mod uuid;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
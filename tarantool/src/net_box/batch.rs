@@ -0,0 +1,267 @@
+//! Pipelining of multiple requests, see [`Batch`].
+
+use std::io::{self, Cursor};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::clock::INFINITY;
+use crate::error::Error;
+use crate::fiber::Cond;
+use crate::tuple::{Decode, ToTupleBuffer};
+
+use super::inner::{time_left, ConnInner};
+use super::options::Options;
+use super::promise::{Promise, TryGet};
+use super::protocol::{self, Sync};
+
+type RequestProducer<'a> = Box<dyn FnOnce(&mut Cursor<Vec<u8>>, Sync) -> Result<(), Error> + 'a>;
+
+/// A set of requests to be sent over a [`Conn`](super::Conn) at once, see
+/// [`Conn::batch`](super::Conn::batch).
+///
+/// The requests are not sent until [`Batch::send`] is called, after which all
+/// of them are flushed to the connection together, without waiting for each
+/// other's responses. This cuts the latency compared to sending the requests
+/// one by one.
+///
+/// The response to each of the requests is decoded into `R`, use
+/// [`Tuple`](crate::tuple::Tuple) if the responses have different types.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tarantool::net_box::{Conn, Options};
+///
+/// # fn get_conn() -> Conn { todo!() }
+/// let conn: Conn = get_conn();
+/// let mut batch = conn.batch::<(String,)>();
+/// for id in 1..=10 {
+///     batch.call("get_name", (id,));
+/// }
+/// let results = batch.send(&Options::default()).unwrap();
+/// for (i, res) in results.wait_all(Duration::from_secs(1)).into_iter().enumerate() {
+///     match res {
+///         Ok((name,)) => println!("{}: {}", i + 1, name),
+///         Err(e) => println!("{}: failed: {}", i + 1, e),
+///     }
+/// }
+/// ```
+pub struct Batch<'a, R> {
+    conn_inner: Rc<ConnInner>,
+    requests: Vec<RequestProducer<'a>>,
+    marker: std::marker::PhantomData<fn() -> R>,
+}
+
+impl<'a, R> Batch<'a, R>
+where
+    R: for<'de> Decode<'de> + 'static,
+{
+    pub(crate) fn new(conn_inner: Rc<ConnInner>) -> Self {
+        Self {
+            conn_inner,
+            requests: Vec::new(),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Adds a call of a remote stored procedure to the batch.
+    ///
+    /// The requests are numbered in the order they're added starting from 0.
+    pub fn call<A>(&mut self, func: &'a str, args: A) -> &mut Self
+    where
+        A: ToTupleBuffer + 'a,
+    {
+        self.requests
+            .push(Box::new(protocol::request_producer(protocol::Call(
+                func, args,
+            ))));
+        self
+    }
+
+    /// Adds an evaluation of a lua expression to the batch.
+    ///
+    /// The requests are numbered in the order they're added starting from 0.
+    pub fn eval<A>(&mut self, expr: &'a str, args: A) -> &mut Self
+    where
+        A: ToTupleBuffer + 'a,
+    {
+        self.requests
+            .push(Box::new(protocol::request_producer(protocol::Eval(
+                expr, args,
+            ))));
+        self
+    }
+
+    /// Returns the number of requests in the batch.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns `true` if there are no requests in the batch.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Sends all the requests of the batch. Consumes `self`.
+    ///
+    /// - `options` – the supported option is `timeout`, which applies to each
+    ///   of the requests, see
+    ///   [`Conn::call_async_with_options`](super::Conn::call_async_with_options)
+    ///
+    /// Either all of the requests are sent or none of them, in which case an
+    /// error is returned. Yields only if the connection isn't established yet.
+    pub fn send(self, options: &Options) -> Result<BatchResult<R>, Error> {
+        let deadline = options
+            .timeout
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut promises = self
+            .conn_inner
+            .request_batch::<_, R>(self.requests, options)?;
+        let cond = Rc::new(Cond::new());
+        for promise in &mut promises {
+            promise.replace_cond(cond.clone());
+        }
+        Ok(BatchResult {
+            pending: promises.len(),
+            promises: promises.into_iter().map(Some).collect(),
+            cond,
+            deadline,
+        })
+    }
+}
+
+/// Responses to the requests of a [`Batch`].
+///
+/// The responses can either be iterated in the order they're received, in
+/// which case the iterator yields pairs of the request's number and its result,
+/// or waited for all at once via [`BatchResult::wait_all`].
+///
+/// A failure of one of the requests doesn't affect the others. The requests
+/// whose responses weren't taken when the `BatchResult` is dropped are
+/// cancelled, see [`Promise::cancel`].
+pub struct BatchResult<R> {
+    promises: Vec<Option<Promise<R>>>,
+    pending: usize,
+    cond: Rc<Cond>,
+    /// Deadline of the requests set by the `timeout` passed to
+    /// [`Batch::send`].
+    deadline: Option<Instant>,
+}
+
+impl<R> BatchResult<R> {
+    /// Returns the total number of requests in the batch.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.promises.len()
+    }
+
+    /// Returns `true` if there were no requests in the batch.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.promises.is_empty()
+    }
+
+    /// Returns the number of requests whose responses weren't taken yet.
+    #[inline(always)]
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Waits for the response to any of the pending requests until the
+    /// `timeout` expires.
+    ///
+    /// Returns the number of the request and its result or `None` if there
+    /// are no pending requests or the `timeout` expired.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<(usize, Result<R, Error>)> {
+        self.next_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Waits for the responses to all of the pending requests until the
+    /// `timeout` expires. Consumes `self`.
+    ///
+    /// Returns the results in the order the requests were added to the batch.
+    /// Requests which didn't complete in time result in an
+    /// [`io::ErrorKind::TimedOut`] error, requests whose results were already
+    /// taken via the iterator result in an [`io::ErrorKind::NotFound`] error.
+    pub fn wait_all(mut self, timeout: Duration) -> Vec<Result<R, Error>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut results: Vec<_> = self
+            .promises
+            .iter()
+            .map(|p| {
+                let kind = if p.is_some() {
+                    io::ErrorKind::TimedOut
+                } else {
+                    io::ErrorKind::NotFound
+                };
+                Err(io::Error::from(kind).into())
+            })
+            .collect();
+        while let Some((i, res)) = self.next_until(deadline) {
+            results[i] = res;
+        }
+        results
+    }
+
+    fn next_until(&mut self, deadline: Option<Instant>) -> Option<(usize, Result<R, Error>)> {
+        loop {
+            if self.pending == 0 {
+                return None;
+            }
+
+            for (i, slot) in self.promises.iter_mut().enumerate() {
+                let promise = match slot.take() {
+                    Some(promise) => promise,
+                    None => continue,
+                };
+                match promise.try_get() {
+                    TryGet::Ok(v) => {
+                        self.pending -= 1;
+                        return Some((i, Ok(v)));
+                    }
+                    TryGet::Err(e) => {
+                        self.pending -= 1;
+                        return Some((i, Err(e)));
+                    }
+                    TryGet::Pending(promise) => *slot = Some(promise),
+                }
+            }
+
+            // The promises may expire a bit later than the batch, but the
+            // requests are timed out anyway
+            if matches!(time_left(self.deadline), Some(t) if t.is_zero()) {
+                let i = self.promises.iter().position(Option::is_some)?;
+                self.promises[i] = None;
+                self.pending -= 1;
+                return Some((i, Err(io::Error::from(io::ErrorKind::TimedOut).into())));
+            }
+
+            let deadline = match (deadline, self.deadline) {
+                (Some(deadline), Some(batch_deadline)) => Some(deadline.min(batch_deadline)),
+                (deadline, batch_deadline) => deadline.or(batch_deadline),
+            };
+            match time_left(deadline) {
+                Some(timeout) if timeout.is_zero() => return None,
+                Some(timeout) => self.cond.wait_timeout(timeout),
+                None => self.cond.wait_timeout(INFINITY),
+            };
+        }
+    }
+}
+
+impl<R> Iterator for BatchResult<R> {
+    type Item = (usize, Result<R, Error>);
+
+    /// Waits for the response to any of the pending requests. The waiting is
+    /// only limited by the `timeout` passed to [`Batch::send`].
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_until(None)
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.pending, Some(self.pending))
+    }
+}
@@ -1,12 +1,13 @@
+use std::io::{self, Write};
 use std::rc::Rc;
 use std::vec::IntoIter;
 
 use crate::error::Error;
-use crate::index::IteratorType;
+use crate::index::{IndexApi, IteratorType, Part};
 use crate::tuple::{Encode, ToTupleBuffer, Tuple};
 
 use super::inner::ConnInner;
-use super::protocol;
+use super::protocol::{self, StreamId};
 use super::Options;
 
 /// Remote index (a group of key values and pointers)
@@ -14,14 +15,21 @@ pub struct RemoteIndex {
     conn_inner: Rc<ConnInner>,
     space_id: u32,
     index_id: u32,
+    stream_id: Option<StreamId>,
 }
 
 impl RemoteIndex {
-    pub(crate) fn new(conn_inner: Rc<ConnInner>, space_id: u32, index_id: u32) -> Self {
+    pub(crate) fn new(
+        conn_inner: Rc<ConnInner>,
+        space_id: u32,
+        index_id: u32,
+        stream_id: Option<StreamId>,
+    ) -> Self {
         RemoteIndex {
             conn_inner,
             space_id,
             index_id,
+            stream_id,
         }
     }
 
@@ -60,6 +68,7 @@ impl RemoteIndex {
                 protocol::encode_select(
                     buf,
                     sync,
+                    self.stream_id,
                     self.space_id,
                     self.index_id,
                     options.limit.unwrap_or(u32::max_value()),
@@ -90,7 +99,17 @@ impl RemoteIndex {
         Op: Encode,
     {
         self.conn_inner.request(
-            |buf, sync| protocol::encode_update(buf, sync, self.space_id, self.index_id, key, ops),
+            |buf, sync| {
+                protocol::encode_update(
+                    buf,
+                    sync,
+                    self.stream_id,
+                    self.space_id,
+                    self.index_id,
+                    key,
+                    ops,
+                )
+            },
             protocol::decode_single_row,
             options,
         )
@@ -110,7 +129,15 @@ impl RemoteIndex {
     {
         self.conn_inner.request(
             |buf, sync| {
-                protocol::encode_upsert(buf, sync, self.space_id, self.index_id, value, ops)
+                protocol::encode_upsert(
+                    buf,
+                    sync,
+                    self.stream_id,
+                    self.space_id,
+                    self.index_id,
+                    value,
+                    ops,
+                )
             },
             protocol::decode_single_row,
             options,
@@ -124,11 +151,224 @@ impl RemoteIndex {
         K: ToTupleBuffer,
     {
         self.conn_inner.request(
-            |buf, sync| protocol::encode_delete(buf, sync, self.space_id, self.index_id, key),
+            |buf, sync| {
+                protocol::encode_delete(
+                    buf,
+                    sync,
+                    self.stream_id,
+                    self.space_id,
+                    self.index_id,
+                    key,
+                )
+            },
             protocol::decode_single_row,
             options,
         )
     }
+
+    /// Returns the parts of the index as loaded from the remote `_vindex`
+    /// system space.
+    ///
+    /// The field numbers of the parts are 1-based, same as when
+    /// [creating](crate::index::Builder::part) an index, unlike the 0-based
+    /// ones returned by [`RemoteSpace::field_no`](super::RemoteSpace::field_no).
+    ///
+    /// Returns [`Error::MetaNotFound`] if the index no longer exists.
+    pub fn parts(&self) -> Result<Vec<Part>, Error> {
+        self.conn_inner
+            .index_parts(self.space_id, self.index_id)?
+            .ok_or(Error::MetaNotFound)
+    }
+
+    /// The remote-call equivalent of the local call `Index::len(...)`
+    /// (see [details](../index/struct.Index.html#method.len)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn len(&self, options: &Options) -> Result<usize, Error> {
+        self.eval_index_method(
+            "local space_id, index_id = ...
+            return box.space[space_id].index[index_id]:len()",
+            &IndexMethodArgs {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                key_and_iterator: None::<(&(), _)>,
+            },
+            options,
+        )
+    }
+
+    /// The remote-call equivalent of the local call `Index::count(...)`
+    /// (see [details](../index/struct.Index.html#method.count)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn count<K>(
+        &self,
+        iterator_type: IteratorType,
+        key: &K,
+        options: &Options,
+    ) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.eval_index_method(
+            "local space_id, index_id, key, iterator = ...
+            return box.space[space_id].index[index_id]:count(key, {iterator = iterator})",
+            &IndexMethodArgs {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                key_and_iterator: Some((key, iterator_type)),
+            },
+            options,
+        )
+    }
+
+    /// The remote-call equivalent of the local call `Index::min(...)`
+    /// (see [details](../index/struct.Index.html#method.min)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn min<K>(&self, key: &K, options: &Options) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.first(IteratorType::Eq, key, options)
+    }
+
+    /// The remote-call equivalent of the local call `Index::max(...)`
+    /// (see [details](../index/struct.Index.html#method.max)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn max<K>(&self, key: &K, options: &Options) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.first(IteratorType::Req, key, options)
+    }
+
+    fn first<K>(
+        &self,
+        iterator_type: IteratorType,
+        key: &K,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        Ok(self
+            .select(
+                iterator_type,
+                key,
+                &Options {
+                    offset: 0,
+                    limit: Some(1),
+                    ..options.clone()
+                },
+            )?
+            .next())
+    }
+
+    /// There are no iproto requests for some of the index methods, so they're
+    /// called via `eval`. The lua `net.box` module `call`s them by name
+    /// instead (e.g. `box.space.foo.index.bar:len`), but here the space and
+    /// the index are referred to by id, which isn't supported by `call`.
+    fn eval_index_method<A>(&self, expr: &str, args: &A, options: &Options) -> Result<usize, Error>
+    where
+        A: ToTupleBuffer,
+    {
+        let res = self.conn_inner.request(
+            |buf, sync| protocol::encode_eval(buf, sync, self.stream_id, expr, args),
+            protocol::decode_call,
+            options,
+        )?;
+        match res {
+            Some(tuple) => Ok(tuple.decode::<(usize,)>()?.0),
+            None => Err(io::Error::from(io::ErrorKind::InvalidData).into()),
+        }
+    }
+}
+
+impl IndexApi for RemoteIndex {
+    type Iterator = RemoteIndexIterator;
+
+    #[inline(always)]
+    fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        RemoteIndex::get(self, key, &Options::default())
+    }
+
+    #[inline(always)]
+    fn select<K>(&self, iterator_type: IteratorType, key: &K) -> Result<Self::Iterator, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        RemoteIndex::select(self, iterator_type, key, &Options::default())
+    }
+
+    #[inline(always)]
+    fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        RemoteIndex::update(self, key, ops, &Options::default())
+    }
+
+    #[inline(always)]
+    fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        RemoteIndex::upsert(self, value, ops, &Options::default()).map(drop)
+    }
+
+    #[inline(always)]
+    fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        RemoteIndex::delete(self, key, &Options::default())
+    }
+
+    #[inline(always)]
+    fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        RemoteIndex::count(self, iterator_type, key, &Options::default())
+    }
+}
+
+/// Arguments of an index method called via `eval`: space id, index id and
+/// optionally a key and an iterator type.
+struct IndexMethodArgs<K> {
+    space_id: u32,
+    index_id: u32,
+    key_and_iterator: Option<(K, IteratorType)>,
+}
+
+impl<K> ToTupleBuffer for IndexMethodArgs<&K>
+where
+    K: ToTupleBuffer + ?Sized,
+{
+    fn write_tuple_data(&self, w: &mut impl Write) -> crate::Result<()> {
+        match &self.key_and_iterator {
+            None => {
+                rmp::encode::write_array_len(w, 2)?;
+                rmp::encode::write_uint(w, self.space_id as _)?;
+                rmp::encode::write_uint(w, self.index_id as _)?;
+            }
+            Some((key, iterator_type)) => {
+                rmp::encode::write_array_len(w, 4)?;
+                rmp::encode::write_uint(w, self.space_id as _)?;
+                rmp::encode::write_uint(w, self.index_id as _)?;
+                key.write_tuple_data(w)?;
+                rmp::encode::write_sint(w, *iterator_type as _)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Remote index iterator. Can be used with `for` statement
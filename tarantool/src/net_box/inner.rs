@@ -2,19 +2,23 @@ use core::cell::RefCell;
 use std::cell::Cell;
 use std::io::{self, Cursor, Read, Write};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::{Rc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::coio::CoIOStream;
 use crate::error::Error;
 use crate::fiber::{is_cancelled, set_cancellable, sleep, time, Cond, Fiber};
+use crate::index::Part;
 use crate::net_box::stream::ConnStream;
+use crate::network::protocol::{ProtocolFeature, ProtocolFeatures, PROTOCOL_VERSION};
+use crate::space::Field;
 use crate::tuple::Decode;
 use crate::unwrap_or;
 
 use super::options::{ConnOptions, ConnTriggers, Options};
 use super::promise::Promise;
-use super::protocol::{self, Header, Request};
+use super::protocol::{self, Header, Request, StreamId};
 use super::recv_queue::RecvQueue;
 use super::schema::ConnSchema;
 use super::send_queue::{self, SendQueue};
@@ -31,13 +35,31 @@ enum ConnState {
     Closed,
 }
 
+/// Address of the remote instance.
+#[derive(Debug, Clone)]
+pub enum ConnAddrs {
+    Inet {
+        /// Addresses the host name resolves to, which are tried in order.
+        addrs: Vec<SocketAddr>,
+        /// The host name or ip address as specified by the caller, used to
+        /// verify the server's TLS certificate. If not known, the ip address
+        /// the connection is made to is used.
+        #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+        host: Option<String>,
+    },
+    /// Path to a unix domain socket.
+    Unix(PathBuf),
+}
+
 pub struct ConnInner {
-    addrs: Vec<SocketAddr>,
+    addrs: ConnAddrs,
     options: ConnOptions,
     state: Cell<ConnState>,
     state_change_cond: Cond,
     schema: Rc<ConnSchema>,
     schema_version: Cell<Option<u32>>,
+    last_stream_id: Cell<StreamId>,
+    features: Cell<ProtocolFeatures>,
     stream: RefCell<Option<ConnStream>>,
     send_queue: SendQueue,
     recv_queue: RecvQueue,
@@ -49,7 +71,7 @@ pub struct ConnInner {
 
 impl ConnInner {
     pub fn new(
-        addrs: Vec<SocketAddr>,
+        addrs: ConnAddrs,
         options: ConnOptions,
         triggers: Option<Rc<dyn ConnTriggers>>,
     ) -> Rc<Self> {
@@ -67,6 +89,8 @@ impl ConnInner {
             state_change_cond: Cond::new(),
             schema: ConnSchema::acquire(&addrs),
             schema_version: Cell::new(None),
+            last_stream_id: Cell::new(0),
+            features: Cell::new(ProtocolFeatures::default()),
             stream: RefCell::new(None),
             send_queue: SendQueue::new(
                 options.send_buffer_size,
@@ -135,6 +159,7 @@ impl ConnInner {
         Fp: FnOnce(&mut Cursor<Vec<u8>>, u64) -> Result<(), Error>,
         Fc: FnOnce(&mut Cursor<Vec<u8>>, &Header) -> Result<R, Error>,
     {
+        let deadline = deadline_from(options);
         loop {
             let state = self.state.get();
             match state {
@@ -143,15 +168,14 @@ impl ConnInner {
                 }
                 ConnState::Active => {
                     return match self.send_queue.send(request_producer) {
-                        Ok(sync) => {
-                            self.recv_queue
-                                .recv(sync, response_consumer, options)
-                                .map(|response| {
-                                    self.schema_version
-                                        .set(Some(response.header.schema_version));
-                                    response.payload
-                                })
-                        }
+                        Ok(sync) => self
+                            .recv_queue
+                            .recv(sync, response_consumer, time_left(deadline))
+                            .map(|response| {
+                                self.schema_version
+                                    .set(Some(response.header.schema_version));
+                                response.payload
+                            }),
                         Err(err) => Err(self.handle_error(err).err().unwrap()),
                     };
                 }
@@ -161,17 +185,24 @@ impl ConnInner {
                     return Err(io::Error::from(io::ErrorKind::NotConnected).into())
                 }
                 _ => {
-                    self.wait_state_changed(None);
+                    if !self.wait_state_changed(time_left(deadline)) {
+                        return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+                    }
                 }
             };
         }
     }
 
-    pub(crate) fn request_async<I, O>(self: &Rc<Self>, request: I) -> crate::Result<Promise<O>>
+    pub(crate) fn request_async<I, O>(
+        self: &Rc<Self>,
+        request: I,
+        options: &Options,
+    ) -> crate::Result<Promise<O>>
     where
         I: Request,
         O: for<'de> Decode<'de> + 'static,
     {
+        let deadline = deadline_from(options);
         loop {
             match self.state.get() {
                 ConnState::Init => {
@@ -182,7 +213,7 @@ impl ConnInner {
                         .send_queue
                         .send(protocol::request_producer(request))
                         .map_err(|err| self.handle_error(err).err().unwrap())?;
-                    let promise = Promise::new(Rc::downgrade(self));
+                    let promise = Promise::new(Rc::downgrade(self), sync, deadline);
                     self.recv_queue.add_consumer(sync, promise.downgrade());
                     return Ok(promise);
                 }
@@ -192,12 +223,109 @@ impl ConnInner {
                     return Err(io::Error::from(io::ErrorKind::NotConnected).into())
                 }
                 _ => {
-                    self.wait_state_changed(None);
+                    if !self.wait_state_changed(time_left(deadline)) {
+                        return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+                    }
                 }
             }
         }
     }
 
+    /// Enqueues all the requests at once and returns a promise for each of
+    /// them in the same order.
+    pub(crate) fn request_batch<Fp, O>(
+        self: &Rc<Self>,
+        request_producers: Vec<Fp>,
+        options: &Options,
+    ) -> crate::Result<Vec<Promise<O>>>
+    where
+        Fp: FnOnce(&mut Cursor<Vec<u8>>, u64) -> Result<(), Error>,
+        O: for<'de> Decode<'de> + 'static,
+    {
+        let deadline = deadline_from(options);
+        loop {
+            match self.state.get() {
+                ConnState::Init => {
+                    self.init()?;
+                }
+                ConnState::Active => {
+                    let syncs = self.send_queue.send_batch(request_producers)?;
+                    let promises = syncs
+                        .into_iter()
+                        .map(|sync| {
+                            let promise = Promise::new(Rc::downgrade(self), sync, deadline);
+                            self.recv_queue.add_consumer(sync, promise.downgrade());
+                            promise
+                        })
+                        .collect();
+                    return Ok(promises);
+                }
+                ConnState::Error => self.disconnect(),
+                ConnState::ErrorReconnect => self.reconnect_or_fail()?,
+                ConnState::Closed => {
+                    return Err(io::Error::from(io::ErrorKind::NotConnected).into())
+                }
+                _ => {
+                    if !self.wait_state_changed(time_left(deadline)) {
+                        return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Forgets the pending asynchronous request with the given `sync`, so
+    /// its response is discarded once received.
+    pub(crate) fn forget_request(&self, sync: u64) {
+        self.recv_queue.remove_consumer(sync);
+    }
+
+    /// Enqueues a request without waiting for the response, which is
+    /// discarded once received. Fails if the connection isn't active.
+    ///
+    /// Doesn't yield.
+    pub fn send_only<Fp>(&self, request_producer: Fp) -> Result<(), Error>
+    where
+        Fp: FnOnce(&mut Cursor<Vec<u8>>, u64) -> Result<(), Error>,
+    {
+        if !self.is_connected() {
+            return Err(io::Error::from(io::ErrorKind::NotConnected).into());
+        }
+        self.send_queue.send(request_producer)?;
+        Ok(())
+    }
+
+    /// Returns a stream id which wasn't used on this connection before.
+    pub fn next_stream_id(&self) -> StreamId {
+        let stream_id = self.last_stream_id.get() + 1;
+        self.last_stream_id.set(stream_id);
+        stream_id
+    }
+
+    /// Returns the protocol features supported by the server, connecting to it
+    /// first if needed.
+    pub fn protocol_features(self: &Rc<Self>) -> Result<ProtocolFeatures, Error> {
+        self.wait_connected(Some(self.options.connect_timeout))?;
+        Ok(self.features.get())
+    }
+
+    /// Returns an error if `feature` isn't supported by the server.
+    ///
+    /// Features are only known while the connection is active, so this waits
+    /// for the connection to be established for at most `timeout` (`None`
+    /// means no limit). If the connection is closed the check is skipped and
+    /// it's up to the request itself to report the connection error.
+    pub fn require_feature(
+        self: &Rc<Self>,
+        feature: ProtocolFeature,
+        timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        if self.wait_connected(timeout)? {
+            self.features.get().require(feature)?;
+        }
+        Ok(())
+    }
+
     pub fn lookup_space(self: &Rc<Self>, name: &str) -> Result<Option<u32>, Error> {
         self.refresh_schema()?;
         Ok(self.schema.lookup_space(name))
@@ -208,6 +336,25 @@ impl ConnInner {
         Ok(self.schema.lookup_index(name, space_id))
     }
 
+    pub fn space_format(self: &Rc<Self>, space_id: u32) -> Result<Option<Vec<Field>>, Error> {
+        self.refresh_schema()?;
+        Ok(self.schema.space_format(space_id))
+    }
+
+    pub fn field_no(self: &Rc<Self>, space_id: u32, name: &str) -> Result<Option<u32>, Error> {
+        self.refresh_schema()?;
+        Ok(self.schema.field_no(space_id, name))
+    }
+
+    pub fn index_parts(
+        self: &Rc<Self>,
+        space_id: u32,
+        index_id: u32,
+    ) -> Result<Option<Vec<Part>>, Error> {
+        self.refresh_schema()?;
+        Ok(self.schema.index_parts(space_id, index_id))
+    }
+
     pub fn close(self: &Rc<Self>) {
         let state = self.state.get();
         if matches!(state, ConnState::Connecting) || matches!(state, ConnState::Auth) {
@@ -243,23 +390,50 @@ impl ConnInner {
 
         // connect
         let connect_timeout = self.options.connect_timeout;
-        let mut stream = if connect_timeout.subsec_nanos() == 0 && connect_timeout.as_secs() == 0 {
-            CoIOStream::connect(&*self.addrs)?
-        } else {
-            CoIOStream::connect_timeout(self.addrs.first().unwrap(), connect_timeout)?
+        let mut stream = match &self.addrs {
+            ConnAddrs::Unix(path) => CoIOStream::connect_unix(path)?,
+            ConnAddrs::Inet { addrs, .. } => {
+                if connect_timeout.subsec_nanos() == 0 && connect_timeout.as_secs() == 0 {
+                    CoIOStream::connect(&**addrs)?
+                } else {
+                    CoIOStream::connect_timeout(addrs.first().unwrap(), connect_timeout)?
+                }
+            }
         };
 
-        // receive greeting msg
-        let salt = protocol::decode_greeting(&mut stream)?;
-
-        // auth if required
-        if !self.options.user.is_empty() {
-            self.update_state(ConnState::Auth);
-            self.auth(&mut stream, &salt)?;
-        }
+        // establish tls session if required
+        #[cfg(feature = "tls")]
+        let stream = match &self.options.tls {
+            Some(config) => {
+                let host = match &self.addrs {
+                    ConnAddrs::Unix(_) => "localhost".into(),
+                    ConnAddrs::Inet {
+                        host: Some(host), ..
+                    } => host.clone(),
+                    ConnAddrs::Inet { host: None, .. } => peer_host(&stream)?,
+                };
+                let connector = crate::tls::TlsConnector::new(config)?;
+                let mut stream = if connect_timeout == Duration::ZERO {
+                    connector.connect(&host, stream)?
+                } else {
+                    connector.connect_timeout(&host, stream, connect_timeout)?
+                };
+                self.handshake(&mut stream)?;
+                ConnStream::with_tls(stream)?
+            }
+            None => {
+                self.handshake(&mut stream)?;
+                ConnStream::new(stream)?
+            }
+        };
+        #[cfg(not(feature = "tls"))]
+        let stream = {
+            self.handshake(&mut stream)?;
+            ConnStream::new(stream)?
+        };
 
         // if ok: put stream to result + set state to active
-        self.stream.replace(Some(ConnStream::new(stream)?));
+        self.stream.replace(Some(stream));
         self.update_state(ConnState::Active);
 
         // call trigger (if available)
@@ -270,7 +444,58 @@ impl ConnInner {
         Ok(())
     }
 
-    fn auth(&self, stream: &mut CoIOStream, salt: &[u8]) -> Result<(), Error> {
+    fn handshake(&self, stream: &mut (impl Read + Write)) -> Result<(), Error> {
+        // receive greeting msg
+        let salt = protocol::decode_greeting(stream)?;
+
+        // negotiate protocol features
+        self.features.set(self.identify(stream)?);
+
+        // auth if required
+        if !self.options.user.is_empty() {
+            self.update_state(ConnState::Auth);
+            self.auth(stream, &salt)?;
+        }
+
+        Ok(())
+    }
+
+    fn identify(&self, stream: &mut (impl Read + Write)) -> Result<ProtocolFeatures, Error> {
+        let mut cur = Cursor::new(Vec::new());
+
+        // send id request
+        let sync = self.send_queue.next_sync();
+        let client_features = ProtocolFeatures {
+            version: PROTOCOL_VERSION,
+            streams: true,
+            transactions: true,
+            ..Default::default()
+        };
+        send_queue::write_to_buffer(&mut cur, sync, |buf, sync| {
+            protocol::encode_id(buf, sync, &client_features)
+        })?;
+        stream.write_all(cur.get_ref())?;
+
+        // handle response
+        let response_len = rmp::decode::read_u32(stream)?;
+        {
+            let buffer = cur.get_mut();
+            buffer.clear();
+            buffer.reserve(response_len as usize);
+            stream.take(response_len as u64).read_to_end(buffer)?;
+            cur.set_position(0);
+        }
+
+        let header = protocol::decode_header(&mut cur)?;
+        if header.status_code != 0 {
+            // Servers older than 2.10 don't support `IPROTO_ID`
+            return Ok(ProtocolFeatures::default());
+        }
+
+        protocol::decode_id(&mut cur)
+    }
+
+    fn auth(&self, stream: &mut (impl Read + Write), salt: &[u8]) -> Result<(), Error> {
         let buf = Vec::new();
         let mut cur = Cursor::new(buf);
 
@@ -461,3 +686,29 @@ fn recv_worker(conn: Box<Weak<ConnInner>>) -> i32 {
         }
     }
 }
+
+/// Converts the request timeout into a deadline. The timeout covers both
+/// waiting for the connection to be established and waiting for the response.
+fn deadline_from(options: &Options) -> Option<Instant> {
+    options
+        .timeout
+        .and_then(|timeout| Instant::now().checked_add(timeout))
+}
+
+/// Returns the time left until the `deadline` or `None` if there's no
+/// deadline.
+pub(crate) fn time_left(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// Returns the ip address the `stream` is connected to, which is the server
+/// name for the TLS certificate verification if the host isn't known.
+#[cfg(feature = "tls")]
+fn peer_host(stream: &CoIOStream) -> Result<String, Error> {
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    // SAFETY: the socket is owned by the `stream` and is not closed here
+    let socket = ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_fd(stream.as_raw_fd()) });
+    Ok(socket.peer_addr()?.ip().to_string())
+}
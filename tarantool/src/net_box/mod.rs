@@ -1,7 +1,8 @@
 //! The `net_box` module contains connector to remote Tarantool server instances via a network.
 //!
 //! You can call the following methods:
-//! - [Conn::new()](struct.Conn.html#method.new) to connect and get a connection object (named `conn` for examples in this section),
+//! - [Conn::new()](struct.Conn.html#method.new) or [Conn::from_uri()](struct.Conn.html#method.from_uri) to connect
+//!   and get a connection object (named `conn` for examples in this section),
 //! - other `net_box` routines, to execute requests on the remote database system,
 //! - [conn.close()](struct.Conn.html#method.close) to disconnect.
 //!
@@ -40,25 +41,32 @@
 #![cfg(feature = "net_box")]
 
 use core::time::Duration;
+use std::io;
 use std::net::ToSocketAddrs;
 use std::rc::Rc;
 
+pub use crate::network::protocol::{ProtocolFeature, ProtocolFeatures};
 pub use index::{RemoteIndex, RemoteIndexIterator};
-use inner::ConnInner;
+use inner::{ConnAddrs, ConnInner};
 pub use options::{ConnOptions, ConnTriggers, Options};
 use promise::Promise;
 pub(crate) use protocol::ResponseError;
+pub use remote_stream::RemoteStream;
 pub use space::RemoteSpace;
 
 use crate::error::Error;
 use crate::tuple::{Decode, ToTupleBuffer, Tuple};
+use crate::uri::{Address, Uri};
 
+pub mod batch;
 mod index;
 mod inner;
 mod options;
+pub mod pool;
 pub mod promise;
 mod protocol;
 mod recv_queue;
+mod remote_stream;
 mod schema;
 mod send_queue;
 mod space;
@@ -83,8 +91,52 @@ impl Conn {
         options: ConnOptions,
         triggers: Option<Rc<dyn ConnTriggers>>,
     ) -> Result<Self, Error> {
+        let addrs = ConnAddrs::Inet {
+            addrs: addr.to_socket_addrs()?.collect(),
+            host: None,
+        };
         Ok(Conn {
-            inner: ConnInner::new(addr.to_socket_addrs()?.collect(), options, triggers),
+            inner: ConnInner::new(addrs, options, triggers),
+            is_master: true,
+        })
+    }
+
+    /// Create a new connection to the instance at `uri`, e.g.
+    /// `"localhost:3301"` or `"admin:secret@unix/:/var/run/tarantool.sock"`.
+    /// See [`uri`](crate::uri) for the supported formats.
+    ///
+    /// The login and password specified in the `uri` take precedence over
+    /// [`ConnOptions::user`] and [`ConnOptions::password`].
+    ///
+    /// See also: [`Conn::new`]
+    pub fn from_uri(
+        uri: &str,
+        options: ConnOptions,
+        triggers: Option<Rc<dyn ConnTriggers>>,
+    ) -> Result<Self, Error> {
+        let uri: Uri = uri.parse().map_err(io::Error::from)?;
+        Self::from_parsed_uri(uri, options, triggers)
+    }
+
+    /// Same as [`Conn::from_uri`], but for an already parsed `uri`.
+    pub(crate) fn from_parsed_uri(
+        uri: Uri,
+        mut options: ConnOptions,
+        triggers: Option<Rc<dyn ConnTriggers>>,
+    ) -> Result<Self, Error> {
+        if let Some(login) = uri.login {
+            options.user = login;
+            options.password = uri.password.unwrap_or_default();
+        }
+        let addrs = match uri.address {
+            Address::Inet { host, port } => ConnAddrs::Inet {
+                addrs: (host.as_str(), port).to_socket_addrs()?.collect(),
+                host: Some(host),
+            },
+            Address::Unix(path) => ConnAddrs::Unix(path),
+        };
+        Ok(Conn {
+            inner: ConnInner::new(addrs, options, triggers),
             is_master: true,
         })
     }
@@ -111,6 +163,15 @@ impl Conn {
         self.inner.is_connected()
     }
 
+    /// Returns the iproto protocol version and features supported by the
+    /// remote server, which are negotiated via `IPROTO_ID` every time the
+    /// connection is established.
+    ///
+    /// Connects to the server if it wasn't connected yet.
+    pub fn protocol_features(&self) -> Result<ProtocolFeatures, Error> {
+        self.inner.protocol_features()
+    }
+
     /// Close a connection.
     pub fn close(&self) {
         self.inner.close()
@@ -141,7 +202,7 @@ impl Conn {
         T: ?Sized,
     {
         self.inner.request(
-            |buf, sync| protocol::encode_call(buf, sync, function_name, args),
+            |buf, sync| protocol::encode_call(buf, sync, None, function_name, args),
             protocol::decode_call,
             options,
         )
@@ -156,7 +217,25 @@ impl Conn {
         A: ToTupleBuffer,
         R: for<'de> Decode<'de> + 'static,
     {
-        self.inner.request_async(protocol::Call(func, args))
+        self.call_async_with_options(func, args, &Options::default())
+    }
+
+    /// Same as [`Conn::call_async`], but with `options`.
+    ///
+    /// - `options` – the supported option is `timeout`, which limits the time
+    ///   to wait for the connection to be established and for the response
+    pub fn call_async_with_options<A, R>(
+        &self,
+        func: &str,
+        args: A,
+        options: &Options,
+    ) -> crate::Result<Promise<R>>
+    where
+        A: ToTupleBuffer,
+        R: for<'de> Decode<'de> + 'static,
+    {
+        self.inner
+            .request_async(protocol::Call(func, args), options)
     }
 
     /// Evaluates and executes the expression in Lua-string, which may be any statement or series of statements.
@@ -177,7 +256,7 @@ impl Conn {
         T: ?Sized,
     {
         self.inner.request(
-            |buf, sync| protocol::encode_eval(buf, sync, expression, args),
+            |buf, sync| protocol::encode_eval(buf, sync, None, expression, args),
             protocol::decode_call,
             options,
         )
@@ -192,7 +271,34 @@ impl Conn {
         A: ToTupleBuffer,
         R: for<'de> Decode<'de> + 'static,
     {
-        self.inner.request_async(protocol::Eval(expr, args))
+        self.eval_async_with_options(expr, args, &Options::default())
+    }
+
+    /// Same as [`Conn::eval_async`], but with `options`.
+    ///
+    /// - `options` – the supported option is `timeout`, which limits the time
+    ///   to wait for the connection to be established and for the response
+    pub fn eval_async_with_options<A, R>(
+        &self,
+        expr: &str,
+        args: A,
+        options: &Options,
+    ) -> crate::Result<Promise<R>>
+    where
+        A: ToTupleBuffer,
+        R: for<'de> Decode<'de> + 'static,
+    {
+        self.inner
+            .request_async(protocol::Eval(expr, args), options)
+    }
+
+    /// Create a [`Batch`](batch::Batch) of requests which are sent together
+    /// in a single flush. The response to each request is decoded into `R`.
+    pub fn batch<'a, R>(&self) -> batch::Batch<'a, R>
+    where
+        R: for<'de> Decode<'de> + 'static,
+    {
+        batch::Batch::new(self.inner.clone())
     }
 
     /// Search space by name on remote server
@@ -200,7 +306,21 @@ impl Conn {
         Ok(self
             .inner
             .lookup_space(name)?
-            .map(|space_id| RemoteSpace::new(self.inner.clone(), space_id)))
+            .map(|space_id| RemoteSpace::new(self.inner.clone(), space_id, None)))
+    }
+
+    /// Create a new iproto stream within this connection.
+    ///
+    /// Requests sent through a stream are executed by the remote server
+    /// sequentially and can be grouped into an interactive transaction, see
+    /// [`RemoteStream`] for details.
+    ///
+    /// Doesn't yield, the stream exists on the remote side as soon as the
+    /// first request is sent through it. Requests sent through the stream
+    /// fail with [`Error::Unsupported`] if the server doesn't support
+    /// streams.
+    pub fn new_stream(&self) -> RemoteStream {
+        RemoteStream::new(self.inner.clone())
     }
 
     /// Remote execute of sql query.
@@ -211,7 +331,7 @@ impl Conn {
         options: &Options,
     ) -> Result<Vec<Tuple>, Error> {
         self.inner.request(
-            |buf, sync| protocol::encode_execute(buf, sync, sql, bind_params),
+            |buf, sync| protocol::encode_execute(buf, sync, None, sql, bind_params),
             |buf, _| protocol::decode_multiple_rows(buf, None),
             options,
         )
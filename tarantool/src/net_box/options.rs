@@ -13,6 +13,10 @@ pub struct Options {
     /// For example, a method whose `options` argument is `{timeout: Some(Duration::from_secs_f32(1.5)})` will stop
     /// after 1.5 seconds on the local node, although this does not guarantee that execution will stop on the remote
     /// server node.
+    ///
+    /// The timeout includes the time spent waiting for the connection to be established. For the `*_async` methods
+    /// the returned [`Promise`](super::promise::Promise) fails with [`TimedOut`](std::io::ErrorKind::TimedOut) once
+    /// the timeout expires.
     pub timeout: Option<Duration>,
 
     /// The `offset` option specifies the number of rows to skip before starting to return rows from the query.
@@ -88,6 +92,19 @@ pub struct ConnOptions {
     ///
     /// Default: 65536
     pub recv_buffer_size: usize,
+
+    /// If set, the connection is encrypted with TLS according to the given
+    /// parameters. The server's certificate is verified against the
+    /// [`server_name`](crate::tls::TlsConfig::server_name) or, if not
+    /// specified, against the host from the uri passed to
+    /// [`Conn::from_uri`](super::Conn::from_uri) (`localhost` for unix domain
+    /// sockets). [`Conn::new`](super::Conn::new) accepts resolvable addresses
+    /// rather than host names, so the ip address the connection is made to is
+    /// used instead.
+    ///
+    /// Default: `None`
+    #[cfg(feature = "tls")]
+    pub tls: Option<crate::tls::TlsConfig>,
 }
 
 impl Default for ConnOptions {
@@ -101,6 +118,8 @@ impl Default for ConnOptions {
             send_buffer_limit: 64000,
             send_buffer_size: 65536,
             recv_buffer_size: 65536,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 }
@@ -0,0 +1,516 @@
+//! Pool of connections to multiple Tarantool instances, see [`Pool`].
+
+use std::cell::{Cell, RefCell};
+use std::cmp::min;
+use std::io;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::fiber::{self, Cond, UnitJoinHandle};
+use crate::tuple::{ToTupleBuffer, Tuple};
+use crate::uri::Uri;
+
+use super::{Conn, ConnOptions, Options};
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Specifies which nodes of a [`Pool`] a request can be sent to.
+    pub enum Mode {
+        /// Only writable nodes (`box.info.ro == false`).
+        Rw = "rw",
+        /// Only read-only nodes (`box.info.ro == true`).
+        Ro = "ro",
+        /// Read-only nodes if there are any healthy ones, writable nodes
+        /// otherwise.
+        PreferRo = "prefer_ro",
+        /// Any healthy node.
+        Any = "any",
+    }
+}
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Specifies how a [`Pool`] chooses between the nodes suitable for a
+    /// request.
+    pub enum Balancing {
+        /// Nodes are chosen in turn.
+        RoundRobin = "round_robin",
+        /// The node with the least number of requests in progress is chosen.
+        LeastPending = "least_pending",
+    }
+}
+
+impl Default for Balancing {
+    fn default() -> Self {
+        Self::RoundRobin
+    }
+}
+
+/// Connection pool options; see [Pool::new()](struct.Pool.html#method.new)
+#[derive(Clone)]
+pub struct PoolOptions {
+    /// Options of each of the pool's connections.
+    pub conn_options: ConnOptions,
+
+    /// How to choose between the nodes suitable for a request.
+    ///
+    /// Default: [`Balancing::RoundRobin`]
+    pub balancing: Balancing,
+
+    /// Interval between health checks of a healthy node. A health check
+    /// requests `box.info.ro` from the node, which also determines the
+    /// [`Mode`]s the node is suitable for.
+    ///
+    /// Default: 1s
+    pub health_check_interval: Duration,
+
+    /// Time to wait for the response to a health check before the node is
+    /// considered unhealthy.
+    ///
+    /// Default: 1s
+    pub health_check_timeout: Duration,
+
+    /// Delay before the first re-check of an unhealthy node. The delay is
+    /// doubled after each subsequent failed check up to
+    /// [`backoff_max`](Self::backoff_max).
+    ///
+    /// Default: 500ms
+    pub backoff_min: Duration,
+
+    /// Maximum delay between re-checks of an unhealthy node.
+    ///
+    /// Default: 30s
+    pub backoff_max: Duration,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        PoolOptions {
+            conn_options: ConnOptions::default(),
+            balancing: Balancing::default(),
+            health_check_interval: Duration::from_secs(1),
+            health_check_timeout: Duration::from_secs(1),
+            backoff_min: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Status of a single node of a [`Pool`]; see [`Pool::status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeStatus {
+    /// Address of the node as passed to [`Pool::new`] in the normalized
+    /// form and without the password, e.g. `"3301"` becomes
+    /// `"localhost:3301"` and `"/tmp/t.sock"` becomes `"unix/:/tmp/t.sock"`.
+    pub addr: String,
+    /// Whether the last health check succeeded.
+    pub is_healthy: bool,
+    /// Value of `box.info.ro` reported by the last successful health check.
+    pub is_ro: Option<bool>,
+    /// Number of requests in progress.
+    pub pending: usize,
+    /// Number of consecutive failed health checks.
+    pub failures: u32,
+}
+
+struct Node {
+    uri: Uri,
+    /// `uri` formatted without the password.
+    addr: String,
+    conn: RefCell<Rc<Conn>>,
+    is_healthy: Cell<bool>,
+    is_ro: Cell<Option<bool>>,
+    pending: Cell<usize>,
+    failures: Cell<u32>,
+    /// [`fiber::clock`] value after which the next health check is due.
+    next_check: Cell<f64>,
+}
+
+struct PoolInner {
+    nodes: Vec<Rc<Node>>,
+    options: PoolOptions,
+    next: Cell<usize>,
+    is_closed: Cell<bool>,
+    health_check_cond: Cond,
+}
+
+/// Pool of connections to multiple Tarantool instances, e.g. the members of a
+/// replicaset.
+///
+/// Each request is sent to one of the healthy nodes suitable for the request's
+/// [`Mode`], which is chosen according to [`PoolOptions::balancing`]. The
+/// health of the nodes is checked periodically in a background fiber.
+/// Unhealthy nodes are re-checked with an exponential backoff and are
+/// reconnected to when they recover.
+///
+/// Requests which may be safely retried (e.g. reads) can be sent via
+/// [`Pool::with_failover`], in which case they're retried on another node if
+/// the chosen one fails due to a network error.
+///
+/// ```no_run
+/// use tarantool::net_box::{Options, pool::{Mode, Pool, PoolOptions}};
+///
+/// let pool = Pool::new(
+///     ["storage-1:3301", "storage-2:3301"],
+///     PoolOptions::default(),
+/// ).unwrap();
+/// pool.call(Mode::Rw, "put", &(1, "foo"), &Options::default()).unwrap();
+/// let res = pool.with_failover(Mode::PreferRo, |conn| {
+///     conn.call("get", &(1,), &Options::default())
+/// });
+/// ```
+pub struct Pool {
+    inner: Rc<PoolInner>,
+    health_checker: Option<UnitJoinHandle<'static>>,
+}
+
+impl Pool {
+    /// Create a pool of connections to the nodes at `addrs`, which may be in
+    /// any of the formats supported by [`Conn::from_uri`], including unix
+    /// domain sockets.
+    ///
+    /// The initial health check of all the nodes is done before this function
+    /// returns, so it yields for at most
+    /// [`PoolOptions::health_check_timeout`].
+    ///
+    /// Returns an error if any of the addresses couldn't be parsed or
+    /// resolved.
+    pub fn new<I>(addrs: I, options: PoolOptions) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let nodes = addrs
+            .into_iter()
+            .map(|addr| {
+                let uri: Uri = addr.as_ref().parse().map_err(io::Error::from)?;
+                let conn = Conn::from_parsed_uri(uri.clone(), options.conn_options.clone(), None)?;
+                Ok(Rc::new(Node {
+                    addr: uri.to_string(),
+                    uri,
+                    conn: RefCell::new(Rc::new(conn)),
+                    is_healthy: Cell::new(false),
+                    is_ro: Cell::new(None),
+                    pending: Cell::new(0),
+                    failures: Cell::new(0),
+                    next_check: Cell::new(0.0),
+                }))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let inner = Rc::new(PoolInner {
+            nodes,
+            options,
+            next: Cell::new(0),
+            is_closed: Cell::new(false),
+            health_check_cond: Cond::new(),
+        });
+
+        fiber::scope(|s| {
+            for node in &inner.nodes {
+                let inner = &inner;
+                s.spawn(move || inner.check(node));
+            }
+        });
+
+        let health_checker = fiber::Builder::new()
+            .name("net_box_pool_health_checker")
+            .proc({
+                let inner = inner.clone();
+                move || health_check_worker(inner)
+            })
+            .start()?;
+
+        Ok(Pool {
+            inner,
+            health_checker: Some(health_checker),
+        })
+    }
+
+    /// Get a connection to a node suitable for `mode`.
+    ///
+    /// The returned connection is counted as a pending request to the node
+    /// until it's dropped.
+    pub fn conn(&self, mode: Mode) -> Result<PoolConn, Error> {
+        Ok(self.inner.pick(mode, &[])?)
+    }
+
+    /// Call a remote stored procedure on a node suitable for `mode`.
+    ///
+    /// See [`Conn::call`] for details.
+    pub fn call<T>(
+        &self,
+        mode: Mode,
+        function_name: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+        T: ?Sized,
+    {
+        let conn = self.conn(mode)?;
+        let res = conn.call(function_name, args, options);
+        self.inner.handle_result(&conn.node, &res);
+        res
+    }
+
+    /// Evaluate a lua expression on a node suitable for `mode`.
+    ///
+    /// See [`Conn::eval`] for details.
+    pub fn eval<T>(
+        &self,
+        mode: Mode,
+        expression: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+        T: ?Sized,
+    {
+        let conn = self.conn(mode)?;
+        let res = conn.eval(expression, args, options);
+        self.inner.handle_result(&conn.node, &res);
+        res
+    }
+
+    /// Run `f` with a connection to a node suitable for `mode`. If `f` fails
+    /// due to a network error, the node is marked as unhealthy and `f` is
+    /// retried with a connection to another suitable node, until all of them
+    /// are tried.
+    ///
+    /// **Only use it for idempotent requests**, as the request may have been
+    /// executed by the failed node before the connection broke.
+    pub fn with_failover<F, R>(&self, mode: Mode, mut f: F) -> Result<R, Error>
+    where
+        F: FnMut(&PoolConn) -> Result<R, Error>,
+    {
+        let mut tried = Vec::new();
+        let mut last_error = None;
+        loop {
+            let conn = match self.inner.pick(mode, &tried) {
+                Ok(conn) => conn,
+                Err(e) => return Err(last_error.unwrap_or_else(|| e.into())),
+            };
+            match f(&conn) {
+                Err(Error::IO(e)) => {
+                    self.inner.mark_failed(&conn.node);
+                    tried.push(conn.node.clone());
+                    last_error = Some(Error::IO(e));
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the status of each of the pool's nodes in the order they were
+    /// passed to [`Pool::new`].
+    pub fn status(&self) -> Vec<NodeStatus> {
+        self.inner
+            .nodes
+            .iter()
+            .map(|node| NodeStatus {
+                addr: node.addr.clone(),
+                is_healthy: node.is_healthy.get(),
+                is_ro: node.is_ro.get(),
+                pending: node.pending.get(),
+                failures: node.failures.get(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.inner.is_closed.set(true);
+        self.inner.health_check_cond.signal();
+        if let Some(health_checker) = self.health_checker.take() {
+            health_checker.join();
+        }
+    }
+}
+
+impl PoolInner {
+    fn pick(&self, mode: Mode, exclude: &[Rc<Node>]) -> io::Result<PoolConn> {
+        let healthy_with = |is_ro: bool| -> Vec<&Rc<Node>> {
+            self.nodes
+                .iter()
+                .filter(|n| n.is_healthy.get() && n.is_ro.get() == Some(is_ro))
+                .filter(|n| !exclude.iter().any(|e| Rc::ptr_eq(e, n)))
+                .collect()
+        };
+        let candidates = match mode {
+            Mode::Rw => healthy_with(false),
+            Mode::Ro => healthy_with(true),
+            Mode::PreferRo => {
+                let ro = healthy_with(true);
+                if ro.is_empty() {
+                    healthy_with(false)
+                } else {
+                    ro
+                }
+            }
+            Mode::Any => {
+                let mut any = healthy_with(false);
+                any.extend(healthy_with(true));
+                any
+            }
+        };
+        if candidates.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("no healthy nodes in the pool suitable for mode '{}'", mode),
+            ));
+        }
+
+        let start = self.next.get();
+        self.next.set(start.wrapping_add(1));
+        let node = match self.options.balancing {
+            Balancing::RoundRobin => candidates[start % candidates.len()],
+            Balancing::LeastPending => (0..candidates.len())
+                .map(|i| candidates[(start + i) % candidates.len()])
+                .min_by_key(|n| n.pending.get())
+                .expect("candidates aren't empty"),
+        };
+        Ok(PoolConn::new(node.clone()))
+    }
+
+    /// Marks the node as unhealthy if the request failed due to a network
+    /// error.
+    fn handle_result<T>(&self, node: &Node, res: &Result<T, Error>) {
+        if let Err(Error::IO(_)) = res {
+            self.mark_failed(node);
+        }
+    }
+
+    /// Marks the node as unhealthy and schedules an immediate health check.
+    fn mark_failed(&self, node: &Node) {
+        if node.is_healthy.replace(false) {
+            node.next_check.set(fiber::clock());
+            self.health_check_cond.signal();
+        }
+    }
+
+    /// Checks the health of the node and updates its status. Yields.
+    fn check(&self, node: &Node) {
+        let options = Options {
+            timeout: Some(self.options.health_check_timeout),
+            ..Default::default()
+        };
+        let conn = node.conn.borrow().clone();
+        let res = conn
+            .eval("return box.info.ro", &(), &options)
+            .and_then(|res| match res {
+                Some(tuple) => Ok(tuple.decode::<(bool,)>()?.0),
+                None => Err(io::Error::from(io::ErrorKind::InvalidData).into()),
+            });
+        match res {
+            Ok(is_ro) => {
+                node.is_healthy.set(true);
+                node.is_ro.set(Some(is_ro));
+                node.failures.set(0);
+                node.next_check
+                    .set(fiber::clock() + self.options.health_check_interval.as_secs_f64());
+            }
+            Err(_) => {
+                node.is_healthy.set(false);
+                let failures = node.failures.get().saturating_add(1);
+                node.failures.set(failures);
+                let backoff = self
+                    .options
+                    .backoff_min
+                    .checked_mul(1 << min(failures - 1, 16))
+                    .unwrap_or(self.options.backoff_max);
+                let backoff = min(backoff, self.options.backoff_max);
+                node.next_check.set(fiber::clock() + backoff.as_secs_f64());
+
+                // A closed connection is never re-established, so a new one
+                // is needed for the node to recover.
+                if !conn.is_connected() {
+                    if let Ok(new_conn) = Conn::from_parsed_uri(
+                        node.uri.clone(),
+                        self.options.conn_options.clone(),
+                        None,
+                    ) {
+                        node.conn.replace(Rc::new(new_conn));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn health_check_worker(pool: Rc<PoolInner>) {
+    while !pool.is_closed.get() {
+        for node in &pool.nodes {
+            if pool.is_closed.get() {
+                return;
+            }
+            if node.next_check.get() <= fiber::clock() {
+                pool.check(node);
+            }
+        }
+        let next_check = pool
+            .nodes
+            .iter()
+            .map(|n| n.next_check.get())
+            .fold(f64::INFINITY, f64::min);
+        // Nodes are never checked less often than this, so it also bounds
+        // the wait if there are no nodes at all.
+        let max_timeout = pool
+            .options
+            .health_check_interval
+            .max(pool.options.backoff_max)
+            .as_secs_f64();
+        let timeout = (next_check - fiber::clock()).clamp(0.0, max_timeout);
+        if timeout > 0.0 && !pool.is_closed.get() {
+            pool.health_check_cond
+                .wait_timeout(Duration::from_secs_f64(timeout));
+        }
+    }
+}
+
+/// A connection to a node of a [`Pool`], see [`Pool::conn`].
+///
+/// Dereferences to [`Conn`].
+pub struct PoolConn {
+    node: Rc<Node>,
+    conn: Rc<Conn>,
+}
+
+impl PoolConn {
+    fn new(node: Rc<Node>) -> Self {
+        node.pending.set(node.pending.get() + 1);
+        let conn = node.conn.borrow().clone();
+        PoolConn { node, conn }
+    }
+
+    /// Address of the node in the normalized form and without the password,
+    /// see [`NodeStatus::addr`].
+    pub fn addr(&self) -> &str {
+        &self.node.addr
+    }
+
+    /// Value of `box.info.ro` reported by the last successful health check of
+    /// the node.
+    pub fn is_ro(&self) -> Option<bool> {
+        self.node.is_ro.get()
+    }
+}
+
+impl Deref for PoolConn {
+    type Target = Conn;
+
+    fn deref(&self) -> &Conn {
+        &self.conn
+    }
+}
+
+impl Drop for PoolConn {
+    fn drop(&mut self) {
+        self.node.pending.set(self.node.pending.get() - 1);
+    }
+}
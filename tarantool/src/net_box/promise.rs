@@ -1,28 +1,52 @@
 use std::{
     cell::{Cell, UnsafeCell},
+    future::Future,
     io,
+    pin::Pin,
     rc::{Rc, Weak},
+    task::{Context, Poll, Waker},
     time::{Duration, Instant},
 };
 
-use crate::{clock::INFINITY, error::Error, fiber::Cond, tuple::Decode, Result};
+use crate::{
+    clock::INFINITY, error::Error, fiber::r#async::context::ContextExt, fiber::Cond, tuple::Decode,
+    Result,
+};
 
-use super::{inner::ConnInner, protocol::Consumer};
+use super::{
+    inner::{time_left, ConnInner},
+    protocol::{Consumer, Sync},
+};
 
 type StdResult<T, E> = std::result::Result<T, E>;
 
 /// An asynchronous [`net_box::Conn`](crate::net_box::Conn) response.
+///
+/// If the request was sent with [`Options::timeout`] set, the promise fails
+/// with [`io::ErrorKind::TimedOut`] once the timeout expires.
+///
+/// Dropping the promise (or calling [`cancel`]) before it is kept makes the
+/// connection discard the response once it arrives.
+///
+/// The promise can also be `.await`ed within the
+/// [`fiber::block_on`](crate::fiber::block_on) async runtime.
+///
+/// [`Options::timeout`]: super::Options::timeout
+/// [`cancel`]: Self::cancel
 pub struct Promise<T> {
     inner: Rc<InnerPromise<T>>,
 }
 
 impl<T> Promise<T> {
     #[inline]
-    pub(crate) fn new(conn: Weak<ConnInner>) -> Self {
+    pub(crate) fn new(conn: Weak<ConnInner>, sync: Sync, deadline: Option<Instant>) -> Self {
         Self {
             inner: Rc::new(InnerPromise {
                 conn,
+                sync,
+                deadline,
                 cond: UnsafeCell::default(),
+                waker: Cell::new(None),
                 data: Cell::new(None),
             }),
         }
@@ -43,11 +67,19 @@ impl<T> Promise<T> {
     }
 
     #[inline]
-    fn check_connection(&self) -> Result<()> {
-        if self.is_connected() {
-            Ok(())
-        } else {
+    fn is_expired(&self) -> bool {
+        matches!(self.inner.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
+    /// Returns an error if the promise can no longer be kept.
+    #[inline]
+    fn check(&self) -> Result<()> {
+        if !self.is_connected() {
             Err(io::Error::from(io::ErrorKind::NotConnected).into())
+        } else if self.is_expired() {
+            Err(io::Error::from(io::ErrorKind::TimedOut).into())
+        } else {
+            Ok(())
         }
     }
 
@@ -63,10 +95,12 @@ impl<T> Promise<T> {
             } else {
                 State::ReceivedError
             }
-        } else if self.is_connected() {
-            State::Pending
-        } else {
+        } else if !self.is_connected() {
             State::Disconnected
+        } else if self.is_expired() {
+            State::TimedOut
+        } else {
+            State::Pending
         }
     }
 
@@ -81,6 +115,7 @@ impl<T> Promise<T> {
     /// - [`Err`]`(error)` if
     ///     - received a response with error
     ///     - connection was closed
+    ///     - request timeout expired
     /// - [`Pending`]`(self)` otherwise
     ///
     /// [`Ok`]: TryGet::Ok
@@ -88,15 +123,15 @@ impl<T> Promise<T> {
     /// [`Pending`]: TryGet::Pending
     #[inline]
     pub fn try_get(self) -> TryGet<T, Error> {
-        match (self.inner.data.take(), self.check_connection()) {
+        match (self.inner.data.take(), self.check()) {
             (Some(Ok(v)), _) => TryGet::Ok(v),
             (Some(Err(e)), _) | (None, Err(e)) => TryGet::Err(e),
             (None, Ok(())) => TryGet::Pending(self),
         }
     }
 
-    /// Waits indefinitely until the promise is kept or the connection is
-    /// closed. Consumes `self`.
+    /// Waits until the promise is kept, the connection is closed or the
+    /// request timeout expires. Consumes `self`.
     #[inline]
     pub fn wait(self) -> Result<T> {
         match self.wait_timeout(INFINITY) {
@@ -115,7 +150,8 @@ impl<T> Promise<T> {
     /// - [`Err`]`(error)`
     ///     - received a response with error
     ///     - connection was closed
-    /// - [`Pending`](self) on timeout
+    ///     - request timeout expired
+    /// - [`Pending`](self) on `timeout`
     ///
     /// [`Ok`]: TryGet::Ok
     /// [`Err`]: TryGet::Err
@@ -126,12 +162,16 @@ impl<T> Promise<T> {
         }
 
         loop {
-            if let Err(e) = self.check_connection() {
+            if let Err(e) = self.check() {
                 break TryGet::Err(e);
             }
 
+            let wait_for = match time_left(self.inner.deadline) {
+                Some(time_left) => timeout.min(time_left),
+                None => timeout,
+            };
             let last_awake = Instant::now();
-            unsafe { &*self.inner.cond.get() }.wait_timeout(timeout);
+            unsafe { &*self.inner.cond.get() }.wait_timeout(wait_for);
 
             if let Some(res) = self.inner.data.take() {
                 break res.into();
@@ -139,11 +179,25 @@ impl<T> Promise<T> {
 
             timeout = timeout.saturating_sub(last_awake.elapsed());
             if timeout.is_zero() {
-                break TryGet::Pending(self);
+                break match self.check() {
+                    Ok(()) => TryGet::Pending(self),
+                    Err(e) => TryGet::Err(e),
+                };
             }
         }
     }
 
+    /// Cancels the request. Consumes `self`.
+    ///
+    /// The response is discarded once received. Note that this doesn't stop
+    /// the execution of the request on the remote server.
+    ///
+    /// Does not yield.
+    #[inline(always)]
+    pub fn cancel(self) {
+        drop(self)
+    }
+
     /// Replaces the contained `Cond` used for [`wait`] & [`wait_timeout`]
     /// methods with the provided one. Useful if several promises need to be
     /// waited on.
@@ -181,6 +235,7 @@ pub enum State {
     ReceivedError,
     Pending,
     Disconnected,
+    TimedOut,
 }
 
 /// Represents all possible value that can be returned from [`Promise::try_get`]
@@ -263,15 +318,52 @@ impl<T> fmt::Debug for Promise<T> {
     }
 }
 
+impl<T> Drop for Promise<T> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.inner.conn.upgrade() {
+            conn.forget_request(self.inner.sync);
+        }
+    }
+}
+
+impl<T> Future for Promise<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(res) = self.inner.data.take() {
+            return Poll::Ready(res);
+        }
+
+        if let Err(e) = self.check() {
+            return Poll::Ready(Err(e));
+        }
+
+        self.inner.waker.set(Some(cx.waker().clone()));
+        if let Some(deadline) = self.inner.deadline {
+            // SAFETY: This is safe as long as the `Context` really
+            // is the `ContextExt`. It's always true within provided
+            // `block_on` async runtime.
+            unsafe { ContextExt::set_deadline(cx, deadline) };
+        }
+        Poll::Pending
+    }
+}
+
 pub struct InnerPromise<T> {
     conn: Weak<ConnInner>,
+    sync: Sync,
+    deadline: Option<Instant>,
     cond: UnsafeCell<Rc<Cond>>,
+    waker: Cell<Option<Waker>>,
     data: Cell<Option<Result<T>>>,
 }
 
 impl<T> InnerPromise<T> {
     fn signal(&self) {
         unsafe { &*self.cond.get() }.signal();
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
     }
 }
 
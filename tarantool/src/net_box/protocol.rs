@@ -11,11 +11,14 @@ use sha1::{Digest, Sha1};
 use crate::error::Error;
 use crate::index::IteratorType;
 use crate::msgpack;
+use crate::network::protocol::{ProtocolFeature, ProtocolFeatures};
+use crate::transaction::{TransactionOptions, TxnIsolation};
 use crate::tuple::{ToTupleBuffer, Tuple};
 
 const REQUEST_TYPE: u8 = 0x00;
 const SYNC: u8 = 0x01;
 const SCHEMA_VERSION: u8 = 0x05;
+const STREAM_ID: u8 = 0x0a;
 
 const SPACE_ID: u8 = 0x10;
 const INDEX_ID: u8 = 0x11;
@@ -37,6 +40,12 @@ const ERROR: u8 = 0x31;
 const SQL_TEXT: u8 = 0x40;
 const SQL_BIND: u8 = 0x41;
 
+const VERSION: u8 = 0x54;
+const FEATURES: u8 = 0x55;
+
+const TIMEOUT: u8 = 0x56;
+const TXN_ISOLATION: u8 = 0x59;
+
 #[derive(Debug, Clone, Copy, serde::Deserialize, FromPrimitive)]
 #[serde(try_from = "u8")]
 #[repr(u8)]
@@ -44,6 +53,7 @@ enum IProtoKey {
     RequestType = REQUEST_TYPE,
     Sync = SYNC,
     SchemaVersion = SCHEMA_VERSION,
+    StreamId = STREAM_ID,
     SpaceId = SPACE_ID,
     IndexId = INDEX_ID,
     Limit = LIMIT,
@@ -60,6 +70,10 @@ enum IProtoKey {
     Error = ERROR,
     SqlText = SQL_TEXT,
     SqlBind = SQL_BIND,
+    Version = VERSION,
+    Features = FEATURES,
+    Timeout = TIMEOUT,
+    TxnIsolation = TXN_ISOLATION,
 }
 
 impl TryFrom<u8> for IProtoKey {
@@ -72,6 +86,10 @@ impl TryFrom<u8> for IProtoKey {
 
 pub(crate) type Sync = u64;
 
+/// Identifies an iproto stream within a connection. Requests with the same
+/// stream id are executed by the remote server strictly sequentially.
+pub(crate) type StreamId = u64;
+
 pub(crate) enum IProtoType {
     Select = 1,
     Insert = 2,
@@ -83,7 +101,11 @@ pub(crate) enum IProtoType {
     Upsert = 9,
     Call = 10,
     Execute = 11,
+    Begin = 14,
+    Commit = 15,
+    Rollback = 16,
     Ping = 64,
+    Id = 73,
 }
 
 pub(crate) trait Request {
@@ -93,7 +115,7 @@ pub(crate) trait Request {
     where
         W: Write,
     {
-        encode_header(out, sync, ty)
+        encode_header(out, sync, None, ty)
     }
 
     fn encode_body<W>(&self, out: &mut W) -> Result<(), Error>
@@ -184,13 +206,18 @@ pub trait Consumer {
 fn encode_header(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     request_type: IProtoType,
 ) -> Result<(), Error> {
-    rmp::encode::write_map_len(stream, 2)?;
+    rmp::encode::write_map_len(stream, if stream_id.is_some() { 3 } else { 2 })?;
     rmp::encode::write_pfix(stream, REQUEST_TYPE)?;
     rmp::encode::write_pfix(stream, request_type as u8)?;
     rmp::encode::write_pfix(stream, SYNC)?;
     rmp::encode::write_uint(stream, sync)?;
+    if let Some(stream_id) = stream_id {
+        rmp::encode::write_pfix(stream, STREAM_ID)?;
+        rmp::encode::write_uint(stream, stream_id)?;
+    }
     Ok(())
 }
 
@@ -226,7 +253,7 @@ pub fn encode_auth(
         .zip(step_3.iter())
         .for_each(|(a, b)| *a ^= *b);
 
-    encode_header(stream, sync, IProtoType::Auth)?;
+    encode_header(stream, sync, None, IProtoType::Auth)?;
     rmp::encode::write_map_len(stream, 2)?;
 
     // username:
@@ -243,7 +270,71 @@ pub fn encode_auth(
 }
 
 pub fn encode_ping(stream: &mut impl Write, sync: u64) -> Result<(), Error> {
-    encode_header(stream, sync, IProtoType::Ping)?;
+    encode_header(stream, sync, None, IProtoType::Ping)?;
+    rmp::encode::write_map_len(stream, 0)?;
+    Ok(())
+}
+
+pub fn encode_id(
+    stream: &mut impl Write,
+    sync: u64,
+    features: &ProtocolFeatures,
+) -> Result<(), Error> {
+    encode_header(stream, sync, None, IProtoType::Id)?;
+    rmp::encode::write_map_len(stream, 2)?;
+    rmp::encode::write_pfix(stream, VERSION)?;
+    rmp::encode::write_uint(stream, features.version)?;
+    rmp::encode::write_pfix(stream, FEATURES)?;
+    rmp::encode::write_array_len(stream, features.iter().count() as u32)?;
+    for feature in features.iter() {
+        rmp::encode::write_uint(stream, feature.id())?;
+    }
+    Ok(())
+}
+
+pub fn encode_begin(
+    stream: &mut impl Write,
+    sync: u64,
+    stream_id: StreamId,
+    opts: &TransactionOptions,
+) -> Result<(), Error> {
+    encode_header(stream, sync, Some(stream_id), IProtoType::Begin)?;
+    let map_len = opts.timeout.is_some() as u32 + opts.isolation.is_some() as u32;
+    rmp::encode::write_map_len(stream, map_len)?;
+    if let Some(timeout) = opts.timeout {
+        rmp::encode::write_pfix(stream, TIMEOUT)?;
+        rmp::encode::write_f64(stream, timeout.as_secs_f64())?;
+    }
+    if let Some(isolation) = opts.isolation {
+        rmp::encode::write_pfix(stream, TXN_ISOLATION)?;
+        rmp::encode::write_uint(stream, txn_isolation_to_iproto(isolation))?;
+    }
+    Ok(())
+}
+
+/// Numeric values of transaction isolation levels as understood by
+/// `IPROTO_BEGIN`. Zero means the default level of the remote server.
+fn txn_isolation_to_iproto(isolation: TxnIsolation) -> u64 {
+    match isolation {
+        TxnIsolation::ReadCommitted => 1,
+        TxnIsolation::ReadConfirmed => 2,
+        TxnIsolation::BestEffort => 3,
+        TxnIsolation::Linearizable => 4,
+    }
+}
+
+pub fn encode_commit(stream: &mut impl Write, sync: u64, stream_id: StreamId) -> Result<(), Error> {
+    encode_header(stream, sync, Some(stream_id), IProtoType::Commit)?;
+    rmp::encode::write_map_len(stream, 0)?;
+    Ok(())
+}
+
+pub fn encode_rollback(
+    stream: &mut impl Write,
+    sync: u64,
+    stream_id: StreamId,
+) -> Result<(), Error> {
+    encode_header(stream, sync, Some(stream_id), IProtoType::Rollback)?;
     rmp::encode::write_map_len(stream, 0)?;
     Ok(())
 }
@@ -251,10 +342,11 @@ pub fn encode_ping(stream: &mut impl Write, sync: u64) -> Result<(), Error> {
 pub fn encode_execute(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     sql: &str,
     bind_params: &impl ToTupleBuffer,
 ) -> Result<(), Error> {
-    encode_header(stream, sync, IProtoType::Execute)?;
+    encode_header(stream, sync, stream_id, IProtoType::Execute)?;
     rmp::encode::write_map_len(stream, 2)?;
     rmp::encode::write_pfix(stream, SQL_TEXT)?;
     rmp::encode::write_str(stream, sql)?;
@@ -267,6 +359,7 @@ pub fn encode_execute(
 pub fn encode_call<T>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     function_name: &str,
     args: &T,
 ) -> Result<(), Error>
@@ -274,7 +367,7 @@ where
     T: ToTupleBuffer,
     T: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Call)?;
+    encode_header(stream, sync, stream_id, IProtoType::Call)?;
     rmp::encode::write_map_len(stream, 2)?;
     rmp::encode::write_pfix(stream, FUNCTION_NAME)?;
     rmp::encode::write_str(stream, function_name)?;
@@ -305,6 +398,7 @@ impl<'a, A: ToTupleBuffer> Request for Call<'a, A> {
 pub fn encode_eval<T>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     expression: &str,
     args: &T,
 ) -> Result<(), Error>
@@ -312,7 +406,7 @@ where
     T: ToTupleBuffer,
     T: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Eval)?;
+    encode_header(stream, sync, stream_id, IProtoType::Eval)?;
     rmp::encode::write_map_len(stream, 2)?;
     rmp::encode::write_pfix(stream, EXPR)?;
     rmp::encode::write_str(stream, expression)?;
@@ -344,6 +438,7 @@ impl<'a, A: ToTupleBuffer> Request for Eval<'a, A> {
 pub fn encode_select<K>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     index_id: u32,
     limit: u32,
@@ -355,7 +450,7 @@ where
     K: ToTupleBuffer,
     K: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Select)?;
+    encode_header(stream, sync, stream_id, IProtoType::Select)?;
     rmp::encode::write_map_len(stream, 6)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -375,6 +470,7 @@ where
 pub fn encode_insert<T>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     value: &T,
 ) -> Result<(), Error>
@@ -382,7 +478,7 @@ where
     T: ToTupleBuffer,
     T: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Insert)?;
+    encode_header(stream, sync, stream_id, IProtoType::Insert)?;
     rmp::encode::write_map_len(stream, 2)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -394,6 +490,7 @@ where
 pub fn encode_replace<T>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     value: &T,
 ) -> Result<(), Error>
@@ -401,7 +498,7 @@ where
     T: ToTupleBuffer,
     T: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Replace)?;
+    encode_header(stream, sync, stream_id, IProtoType::Replace)?;
     rmp::encode::write_map_len(stream, 2)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -413,6 +510,7 @@ where
 pub fn encode_update<K, Op>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     index_id: u32,
     key: &K,
@@ -423,7 +521,7 @@ where
     Op: ToTupleBuffer,
     Op: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Update)?;
+    encode_header(stream, sync, stream_id, IProtoType::Update)?;
     rmp::encode::write_map_len(stream, 4)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -439,6 +537,7 @@ where
 pub fn encode_upsert<T, Op>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     index_id: u32,
     value: &T,
@@ -449,7 +548,7 @@ where
     Op: ToTupleBuffer,
     Op: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Upsert)?;
+    encode_header(stream, sync, stream_id, IProtoType::Upsert)?;
     rmp::encode::write_map_len(stream, 4)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -465,6 +564,7 @@ where
 pub fn encode_delete<K>(
     stream: &mut impl Write,
     sync: u64,
+    stream_id: Option<StreamId>,
     space_id: u32,
     index_id: u32,
     key: &K,
@@ -473,7 +573,7 @@ where
     K: ToTupleBuffer,
     K: ?Sized,
 {
-    encode_header(stream, sync, IProtoType::Delete)?;
+    encode_header(stream, sync, stream_id, IProtoType::Delete)?;
     rmp::encode::write_map_len(stream, 3)?;
     rmp::encode::write_pfix(stream, SPACE_ID)?;
     rmp::encode::write_u32(stream, space_id)?;
@@ -548,6 +648,31 @@ pub fn decode_greeting(stream: &mut impl Read) -> Result<Vec<u8>, Error> {
     Ok(salt)
 }
 
+pub fn decode_id(buffer: &mut Cursor<Vec<u8>>) -> Result<ProtocolFeatures, Error> {
+    let mut features = ProtocolFeatures::default();
+    let payload_len = rmp::decode::read_map_len(buffer)?;
+    for _ in 0..payload_len {
+        let key = rmp::decode::read_pfix(buffer)?;
+        match key {
+            VERSION => features.version = rmp::decode::read_int(buffer)?,
+            FEATURES => {
+                let count = rmp::decode::read_array_len(buffer)?;
+                for _ in 0..count {
+                    let id: u64 = rmp::decode::read_int(buffer)?;
+                    // Features unknown to this client are ignored
+                    if let Some(feature) = ProtocolFeature::from_id(id) {
+                        features.set(feature);
+                    }
+                }
+            }
+            _ => {
+                msgpack::skip_value(buffer)?;
+            }
+        }
+    }
+    Ok(features)
+}
+
 pub fn decode_call(buffer: &mut Cursor<Vec<u8>>, _: &Header) -> Result<Option<Tuple>, Error> {
     let payload_len = rmp::decode::read_map_len(buffer)?;
     for _ in 0..payload_len {
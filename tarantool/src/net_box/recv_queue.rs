@@ -3,6 +3,7 @@ use std::collections::{hash_map::Iter as HashMapIter, HashMap};
 use std::io::{self, Cursor, Read};
 use std::ops::Range;
 use std::rc::{Rc, Weak};
+use std::time::Duration;
 
 use refpool::{Pool, PoolRef};
 use rmp::decode;
@@ -10,7 +11,6 @@ use rmp::decode;
 use crate::error::Error;
 use crate::fiber::{Cond, Latch};
 
-use super::options::Options;
 use super::protocol::{decode_error, decode_header, Consumer, Header, Response, Sync};
 
 type Consumers = HashMap<Sync, Weak<dyn Consumer>>;
@@ -49,7 +49,7 @@ impl RecvQueue {
         &self,
         sync: u64,
         payload_consumer: F,
-        options: &Options,
+        timeout: Option<Duration>,
     ) -> Result<Response<R>, Error>
     where
         F: FnOnce(&mut Cursor<Vec<u8>>, &Header) -> Result<R, Error>,
@@ -63,14 +63,22 @@ impl RecvQueue {
             self.cond_map.borrow_mut().insert(sync, cond_ref.clone());
         }
 
-        let is_signaled = match options.timeout {
+        let is_signaled = match timeout {
             None => cond_ref.wait(),
             Some(timeout) => cond_ref.wait_timeout(timeout),
         };
+        // If the entry was already taken by the receiver fiber, the response
+        // arrived right as the timeout expired and the receiver fiber is
+        // waiting for it to be consumed, so it must be consumed anyway.
+        let is_signaled = is_signaled || !self.cond_map.borrow().contains_key(&sync);
 
         if is_signaled {
             let result = {
-                let header = self.header_recv_result.replace(None).unwrap();
+                // The result may be missing if the connection was closed,
+                // in which case all the waiters are woken up at once.
+                let header = self.header_recv_result.replace(None).unwrap_or_else(|| {
+                    Err(io::Error::from(io::ErrorKind::ConnectionAborted).into())
+                });
 
                 match header {
                     Ok(header) => {
@@ -96,6 +104,12 @@ impl RecvQueue {
         unsafe { (*self.async_consumers.get()).insert(sync, consumer) };
     }
 
+    /// Forgets the consumer waiting for the response with the given `sync`,
+    /// so the response is discarded once received.
+    pub fn remove_consumer(&self, sync: Sync) {
+        unsafe { (*self.async_consumers.get()).remove(&sync) };
+    }
+
     pub fn get_consumer(&self, sync: Sync) -> Option<Rc<dyn Consumer>> {
         unsafe { &mut *self.async_consumers.get() }
             .remove(&sync)
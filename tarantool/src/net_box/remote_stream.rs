@@ -0,0 +1,202 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::transaction::TransactionOptions;
+use crate::tuple::{ToTupleBuffer, Tuple};
+
+use super::inner::ConnInner;
+use super::options::Options;
+use super::protocol::{self, StreamId};
+use super::space::RemoteSpace;
+use super::ProtocolFeature;
+
+/// An iproto stream within a [`Conn`](super::Conn).
+///
+/// Requests sent through the same stream are executed by the remote server
+/// strictly one after another, in the order they were sent. Streams also make
+/// interactive transactions possible: everything between
+/// [`begin`](Self::begin) and [`commit`](Self::commit) or
+/// [`rollback`](Self::rollback) is executed in a single remote transaction.
+///
+/// If the stream is dropped while a transaction is active, a rollback request
+/// is sent to the remote server (without waiting for the response).
+///
+/// Requires tarantool 2.10 or later on the remote side, otherwise requests
+/// fail with [`Error::Unsupported`](crate::error::Error::Unsupported). Interactive
+/// transactions on memtx spaces also require `box.cfg.memtx_use_mvcc_engine`
+/// to be enabled on the remote side.
+///
+/// ```no_run
+/// use tarantool::net_box::{Conn, ConnOptions, Options};
+/// use tarantool::transaction::TransactionOptions;
+///
+/// let conn = Conn::new("localhost:3301", ConnOptions::default(), None).unwrap();
+/// let stream = conn.new_stream();
+/// let accounts = stream.space("accounts").unwrap().unwrap();
+/// stream.begin(&TransactionOptions::default(), &Options::default()).unwrap();
+/// accounts.replace(&(1, "Alice", 90), &Options::default()).unwrap();
+/// accounts.replace(&(2, "Bob", 110), &Options::default()).unwrap();
+/// stream.commit(&Options::default()).unwrap();
+/// ```
+pub struct RemoteStream {
+    conn_inner: Rc<ConnInner>,
+    stream_id: StreamId,
+    is_in_txn: Cell<bool>,
+}
+
+impl RemoteStream {
+    pub(crate) fn new(conn_inner: Rc<ConnInner>) -> Self {
+        RemoteStream {
+            stream_id: conn_inner.next_stream_id(),
+            conn_inner,
+            is_in_txn: Cell::new(false),
+        }
+    }
+
+    /// Returns the id of the stream, which is unique within the connection.
+    #[inline(always)]
+    pub fn id(&self) -> u64 {
+        self.stream_id
+    }
+
+    /// Returns `true` if a transaction was started via this stream and wasn't
+    /// committed or rolled back yet.
+    #[inline(always)]
+    pub fn is_in_txn(&self) -> bool {
+        self.is_in_txn.get()
+    }
+
+    /// Search space by name on remote server. Requests made via the returned
+    /// space (and its indexes) are sent through this stream.
+    pub fn space(&self, name: &str) -> Result<Option<RemoteSpace>, Error> {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Streams, None)?;
+        Ok(self.conn_inner.lookup_space(name)?.map(|space_id| {
+            RemoteSpace::new(self.conn_inner.clone(), space_id, Some(self.stream_id))
+        }))
+    }
+
+    /// Begin a remote transaction in this stream with the given isolation
+    /// level and timeout.
+    ///
+    /// - `options` – the supported option is `timeout`
+    ///
+    /// Note that `options.timeout` only limits the time to wait for the
+    /// response, use [`TransactionOptions::timeout`] to limit the duration of
+    /// the transaction itself.
+    pub fn begin(&self, txn_options: &TransactionOptions, options: &Options) -> Result<(), Error> {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Transactions, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_begin(buf, sync, self.stream_id, txn_options),
+            |_, _| Ok(()),
+            options,
+        )?;
+        self.is_in_txn.set(true);
+        Ok(())
+    }
+
+    /// Commit the remote transaction active in this stream.
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn commit(&self, options: &Options) -> Result<(), Error> {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Transactions, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_commit(buf, sync, self.stream_id),
+            |_, _| Ok(()),
+            options,
+        )?;
+        self.is_in_txn.set(false);
+        Ok(())
+    }
+
+    /// Roll back the remote transaction active in this stream.
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn rollback(&self, options: &Options) -> Result<(), Error> {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Transactions, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_rollback(buf, sync, self.stream_id),
+            |_, _| Ok(()),
+            options,
+        )?;
+        self.is_in_txn.set(false);
+        Ok(())
+    }
+
+    /// Call a remote stored procedure within this stream.
+    ///
+    /// See [`Conn::call`](super::Conn::call) for details.
+    pub fn call<T>(
+        &self,
+        function_name: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+        T: ?Sized,
+    {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Streams, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_call(buf, sync, Some(self.stream_id), function_name, args),
+            protocol::decode_call,
+            options,
+        )
+    }
+
+    /// Evaluate a lua expression on the remote server within this stream.
+    ///
+    /// See [`Conn::eval`](super::Conn::eval) for details.
+    pub fn eval<T>(
+        &self,
+        expression: &str,
+        args: &T,
+        options: &Options,
+    ) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+        T: ?Sized,
+    {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Streams, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_eval(buf, sync, Some(self.stream_id), expression, args),
+            protocol::decode_call,
+            options,
+        )
+    }
+
+    /// Remote execute of sql query within this stream.
+    pub fn execute(
+        &self,
+        sql: &str,
+        bind_params: &impl ToTupleBuffer,
+        options: &Options,
+    ) -> Result<Vec<Tuple>, Error> {
+        self.conn_inner
+            .require_feature(ProtocolFeature::Streams, options.timeout)?;
+        self.conn_inner.request(
+            |buf, sync| protocol::encode_execute(buf, sync, Some(self.stream_id), sql, bind_params),
+            |buf, _| protocol::decode_multiple_rows(buf, None),
+            options,
+        )
+    }
+}
+
+impl Drop for RemoteStream {
+    fn drop(&mut self) {
+        if self.is_in_txn.get() {
+            let stream_id = self.stream_id;
+            // The transaction is rolled back by the server anyway once the
+            // connection is closed, so there's nothing to do if this fails.
+            let _ = self
+                .conn_inner
+                .send_only(|buf, sync| protocol::encode_rollback(buf, sync, stream_id));
+        }
+    }
+}
@@ -3,15 +3,17 @@ use once_cell::unsync::Lazy;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::error::Error;
 use crate::fiber::{Latch, LatchGuard};
-use crate::index::IteratorType;
-use crate::space::{SystemSpace, SYSTEM_ID_MAX};
+use crate::index::{self, IteratorType, Part};
+use crate::space::{self, Field, SystemSpace, SYSTEM_ID_MAX};
 use crate::tuple::Tuple;
+use crate::util::NumOrStr;
 
-use super::inner::ConnInner;
+use super::inner::{ConnAddrs, ConnInner};
 use super::options::Options;
 use super::protocol::{decode_multiple_rows, encode_select};
 
@@ -20,14 +22,20 @@ pub struct ConnSchema {
     is_updating: Cell<bool>,
     space_ids: RefCell<HashMap<String, u32>>,
     index_ids: RefCell<HashMap<(u32, String), u32>>,
+    space_formats: RefCell<HashMap<u32, Vec<Field>>>,
+    index_parts: RefCell<HashMap<(u32, u32), Vec<Part>>>,
     lock: Latch,
 }
 
 impl ConnSchema {
-    pub fn acquire(addrs: &[SocketAddr]) -> Rc<ConnSchema> {
+    pub fn acquire(addrs: &ConnAddrs) -> Rc<ConnSchema> {
+        let keys: Vec<_> = match addrs {
+            ConnAddrs::Inet { addrs, .. } => addrs.iter().copied().map(CacheKey::Inet).collect(),
+            ConnAddrs::Unix(path) => vec![CacheKey::Unix(path.clone())],
+        };
         let addr = SCHEMA_CACHE.with(|cache| {
             let cache = cache.cache.borrow();
-            addrs.iter().find_map(|addr| cache.get(addr).cloned())
+            keys.iter().find_map(|key| cache.get(key).cloned())
         });
         if let Some(addr) = addr {
             return addr;
@@ -38,13 +46,15 @@ impl ConnSchema {
             is_updating: Cell::new(false),
             space_ids: Default::default(),
             index_ids: Default::default(),
+            space_formats: Default::default(),
+            index_parts: Default::default(),
             lock: Latch::new(),
         });
 
         SCHEMA_CACHE.with(|cache| {
             let mut cache = cache.cache.borrow_mut();
-            for addr in addrs {
-                cache.insert(*addr, schema.clone());
+            for key in keys {
+                cache.insert(key, schema.clone());
             }
         });
 
@@ -79,15 +89,39 @@ impl ConnSchema {
         self.is_updating.set(true);
         let (spaces_data, actual_schema_version) = self.fetch_schema_spaces(conn_inner)?;
         for row in spaces_data {
-            let (id, _, name) = row.decode::<(u32, u32, String)>()?;
+            let (id, _, name, _, _, _, format) = row.decode::<SpaceRow>()?;
             self.space_ids.borrow_mut().insert(name, id);
+            self.space_formats
+                .borrow_mut()
+                .insert(id, format.iter().filter_map(decode_field).collect());
         }
 
+        // Collation names are only used to describe index parts, so the
+        // schema is still usable if they can't be fetched.
+        let collations: HashMap<u32, String> = self
+            .fetch_schema_collations(conn_inner)
+            .map(|rows| {
+                rows.iter()
+                    .filter_map(|row| row.decode::<(u32, String)>().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
         for row in self.fetch_schema_indexes(conn_inner)? {
-            let (space_id, index_id, name) = row.decode::<(u32, u32, String)>()?;
+            let (space_id, index_id, name, _, _, parts) = row.decode::<IndexRow>()?;
             self.index_ids
                 .borrow_mut()
                 .insert((space_id, name), index_id);
+            let parts = match parts {
+                rmpv::Value::Array(parts) => parts
+                    .iter()
+                    .filter_map(|part| decode_part(part, &collations))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            self.index_parts
+                .borrow_mut()
+                .insert((space_id, index_id), parts);
         }
 
         self.version.set(Some(actual_schema_version));
@@ -106,6 +140,26 @@ impl ConnSchema {
             .copied()
     }
 
+    pub fn space_format(&self, space_id: u32) -> Option<Vec<Field>> {
+        self.space_formats.borrow().get(&space_id).cloned()
+    }
+
+    pub fn field_no(&self, space_id: u32, name: &str) -> Option<u32> {
+        self.space_formats
+            .borrow()
+            .get(&space_id)?
+            .iter()
+            .position(|field| field.name == name)
+            .map(|pos| pos as u32)
+    }
+
+    pub fn index_parts(&self, space_id: u32, index_id: u32) -> Option<Vec<Part>> {
+        self.index_parts
+            .borrow()
+            .get(&(space_id, index_id))
+            .cloned()
+    }
+
     fn is_outdated(&self, actual_version: Option<u32>) -> bool {
         match actual_version {
             None => true,
@@ -122,6 +176,7 @@ impl ConnSchema {
                 encode_select(
                     buf,
                     sync,
+                    None,
                     SystemSpace::VSpace as u32,
                     0,
                     u32::max_value(),
@@ -135,6 +190,27 @@ impl ConnSchema {
         )
     }
 
+    fn fetch_schema_collations(&self, conn_inner: &Rc<ConnInner>) -> Result<Vec<Tuple>, Error> {
+        let empty_array: [(); 0] = [];
+        conn_inner.request(
+            |buf, sync| {
+                encode_select(
+                    buf,
+                    sync,
+                    None,
+                    SystemSpace::VCollation as u32,
+                    0,
+                    u32::MAX,
+                    0,
+                    IteratorType::All,
+                    &empty_array,
+                )
+            },
+            |buf, _| decode_multiple_rows(buf, None),
+            &Options::default(),
+        )
+    }
+
     fn fetch_schema_indexes(&self, conn_inner: &Rc<ConnInner>) -> Result<Vec<Tuple>, Error> {
         let empty_array: [(); 0] = [];
         conn_inner.request(
@@ -142,6 +218,7 @@ impl ConnSchema {
                 encode_select(
                     buf,
                     sync,
+                    None,
                     SystemSpace::VIndex as u32,
                     0,
                     u32::max_value(),
@@ -156,8 +233,89 @@ impl ConnSchema {
     }
 }
 
+/// A row of `_vspace`: id, owner, name, engine, field count, flags & format.
+type SpaceRow = (
+    u32,
+    u32,
+    String,
+    rmpv::Value,
+    rmpv::Value,
+    rmpv::Value,
+    Vec<rmpv::Value>,
+);
+
+/// A row of `_vindex`: space id, index id, name, type, options & parts.
+type IndexRow = (u32, u32, String, rmpv::Value, rmpv::Value, rmpv::Value);
+
+fn map_get<'a>(map: &'a [(rmpv::Value, rmpv::Value)], key: &str) -> Option<&'a rmpv::Value> {
+    map.iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+/// Decodes a field definition of a space format, e.g.
+/// `{name = 'id', type = 'unsigned', is_nullable = false}`.
+fn decode_field(field: &rmpv::Value) -> Option<Field> {
+    let field = field.as_map()?;
+    let name = map_get(field, "name")?.as_str()?;
+    let field_type = map_get(field, "type")
+        .and_then(rmpv::Value::as_str)
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(space::FieldType::Any);
+    let is_nullable = map_get(field, "is_nullable")
+        .and_then(rmpv::Value::as_bool)
+        .unwrap_or(false);
+    Some(Field {
+        name: name.into(),
+        field_type,
+        is_nullable,
+    })
+}
+
+/// Decodes an index part definition, which is either
+/// `{field = 1, type = 'unsigned', ...}` or `[1, 'unsigned']` for indexes
+/// created in older tarantool versions.
+///
+/// The field numbers are 0-based in `_vindex`, but are converted to 1-based
+/// ones, same as in the [`Part`]s used to create indexes.
+fn decode_part(part: &rmpv::Value, collations: &HashMap<u32, String>) -> Option<Part> {
+    let field_type =
+        |t: Option<&rmpv::Value>| -> Option<index::FieldType> { t?.as_str()?.parse().ok() };
+    match part {
+        rmpv::Value::Map(part) => {
+            let field = map_get(part, "field")?.as_u64()? as u32 + 1;
+            let collation = map_get(part, "collation")
+                .and_then(rmpv::Value::as_u64)
+                .and_then(|id| collations.get(&(id as u32)).cloned());
+            Some(Part {
+                field: NumOrStr::Num(field),
+                r#type: field_type(map_get(part, "type")),
+                collation,
+                is_nullable: map_get(part, "is_nullable").and_then(rmpv::Value::as_bool),
+                path: map_get(part, "path")
+                    .and_then(rmpv::Value::as_str)
+                    .map(Into::into),
+            })
+        }
+        rmpv::Value::Array(part) => Some(Part {
+            field: NumOrStr::Num(part.first()?.as_u64()? as u32 + 1),
+            r#type: field_type(part.get(1)),
+            collation: None,
+            is_nullable: None,
+            path: None,
+        }),
+        _ => None,
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum CacheKey {
+    Inet(SocketAddr),
+    Unix(PathBuf),
+}
+
 struct ConnSchemaCache {
-    cache: RefCell<HashMap<SocketAddr, Rc<ConnSchema>>>,
+    cache: RefCell<HashMap<CacheKey, Rc<ConnSchema>>>,
 }
 
 unsafe impl Sync for ConnSchemaCache {}
@@ -44,8 +44,9 @@ impl SendQueue {
             let offset = buffer.position();
             match write_to_buffer(buffer, sync, payload_producer) {
                 Err(err) => {
-                    // rollback buffer position on error
+                    // rollback buffer on error
                     buffer.set_position(offset);
+                    buffer.get_mut().truncate(offset as usize);
                     return Err(err);
                 }
                 Ok(_) => offset,
@@ -60,6 +61,45 @@ impl SendQueue {
         Ok(sync)
     }
 
+    /// Enqueues all the requests at once, so that they're flushed to the
+    /// stream together. Either all of the requests are enqueued or none of
+    /// them.
+    ///
+    /// Returns the syncs of the requests in the same order.
+    pub fn send_batch<I, F>(&self, payload_producers: I) -> Result<Vec<u64>, Error>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce(&mut Cursor<Vec<u8>>, u64) -> Result<(), Error>,
+    {
+        let mut syncs = Vec::new();
+        let offset = {
+            let buffer = &mut *self.back_buffer.borrow_mut();
+
+            let offset = buffer.position();
+            for payload_producer in payload_producers {
+                let sync = self.next_sync();
+                if let Err(err) = write_to_buffer(buffer, sync, payload_producer) {
+                    // rollback buffer on error, the whole buffer is flushed
+                    // to the stream, so the partially written data must be
+                    // discarded
+                    buffer.set_position(offset);
+                    buffer.get_mut().truncate(offset as usize);
+                    return Err(err);
+                }
+                syncs.push(sync);
+            }
+            offset
+        };
+
+        // trigger swap condition if buffer was empty before or the limit is
+        // exceeded
+        if offset == 0 || self.back_buffer.borrow().position() >= self.buffer_limit {
+            self.swap_cond.signal();
+        }
+
+        Ok(syncs)
+    }
+
     pub fn next_sync(&self) -> u64 {
         let sync = self.sync.get() + 1;
         self.sync.set(sync);
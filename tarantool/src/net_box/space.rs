@@ -2,24 +2,31 @@ use std::rc::Rc;
 
 use crate::error::Error;
 use crate::index::IteratorType;
-use crate::tuple::{Encode, ToTupleBuffer, Tuple};
+use crate::space::{Field, SpaceApi};
+use crate::tuple::{Decode, Encode, ToTupleBuffer, Tuple};
 
 use super::index::{RemoteIndex, RemoteIndexIterator};
 use super::inner::ConnInner;
 use super::options::Options;
-use super::protocol;
+use super::protocol::{self, StreamId};
 
 /// Remote space
 pub struct RemoteSpace {
     conn_inner: Rc<ConnInner>,
     space_id: u32,
+    stream_id: Option<StreamId>,
 }
 
 impl RemoteSpace {
-    pub(crate) fn new(conn_inner: Rc<ConnInner>, space_id: u32) -> Self {
+    pub(crate) fn new(
+        conn_inner: Rc<ConnInner>,
+        space_id: u32,
+        stream_id: Option<StreamId>,
+    ) -> Self {
         RemoteSpace {
             conn_inner,
             space_id,
+            stream_id,
         }
     }
 
@@ -28,13 +35,20 @@ impl RemoteSpace {
         Ok(self
             .conn_inner
             .lookup_index(name, self.space_id)?
-            .map(|index_id| RemoteIndex::new(self.conn_inner.clone(), self.space_id, index_id)))
+            .map(|index_id| {
+                RemoteIndex::new(
+                    self.conn_inner.clone(),
+                    self.space_id,
+                    index_id,
+                    self.stream_id,
+                )
+            }))
     }
 
     /// Returns index with id = 0
     #[inline(always)]
     pub fn primary_key(&self) -> RemoteIndex {
-        RemoteIndex::new(self.conn_inner.clone(), self.space_id, 0)
+        RemoteIndex::new(self.conn_inner.clone(), self.space_id, 0, self.stream_id)
     }
 
     /// The remote-call equivalent of the local call `Space::get(...)`
@@ -46,6 +60,89 @@ impl RemoteSpace {
         self.primary_key().get(key, options)
     }
 
+    /// The remote-call equivalent of the local call `Space::len(...)`
+    /// (see [details](../space/struct.Space.html#method.len)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn len(&self, options: &Options) -> Result<usize, Error> {
+        self.primary_key().len(options)
+    }
+
+    /// The remote-call equivalent of the local call `Space::count(...)`
+    /// (see [details](../space/struct.Space.html#method.count)).
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn count<K>(
+        &self,
+        iterator_type: IteratorType,
+        key: &K,
+        options: &Options,
+    ) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().count(iterator_type, key, options)
+    }
+
+    /// The remote-call equivalent of the local call `Index::min(...)` on the
+    /// space's primary index.
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn min<K>(&self, key: &K, options: &Options) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().min(key, options)
+    }
+
+    /// The remote-call equivalent of the local call `Index::max(...)` on the
+    /// space's primary index.
+    ///
+    /// - `options` – the supported option is `timeout`
+    pub fn max<K>(&self, key: &K, options: &Options) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().max(key, options)
+    }
+
+    /// Returns the format of the space as loaded from the remote `_vspace`
+    /// system space.
+    ///
+    /// Returns [`Error::MetaNotFound`] if the space no longer exists.
+    pub fn format(&self) -> Result<Vec<Field>, Error> {
+        self.conn_inner
+            .space_format(self.space_id)?
+            .ok_or(Error::MetaNotFound)
+    }
+
+    /// Returns the zero-based number of the field called `name` in the
+    /// space's format or `None` if there's no such field.
+    pub fn field_no(&self, name: &str) -> Result<Option<u32>, Error> {
+        self.conn_inner.field_no(self.space_id, name)
+    }
+
+    /// Returns the value of the field called `name` of a `tuple` returned
+    /// from this space.
+    ///
+    /// Tuples received from a remote server don't carry the space's format,
+    /// so [`Tuple::get`] can't be used with field names. This method resolves
+    /// the name using the space's format instead.
+    ///
+    /// Returns:
+    /// - `Ok(None)` if there's no such field in the format or in the tuple
+    /// - `Err(e)` if deserialization failed
+    /// - `Ok(Some(field value))` otherwise
+    pub fn get_field<'t, T>(&self, tuple: &'t Tuple, name: &str) -> Result<Option<T>, Error>
+    where
+        T: Decode<'t>,
+    {
+        match self.field_no(name)? {
+            Some(field_no) => tuple.field(field_no),
+            None => Ok(None),
+        }
+    }
+
     /// The remote-call equivalent of the local call `Space::select(...)`
     /// (see [details](../space/struct.Space.html#method.select)).
     pub fn select<K>(
@@ -67,7 +164,7 @@ impl RemoteSpace {
         T: ToTupleBuffer,
     {
         self.conn_inner.request(
-            |buf, sync| protocol::encode_insert(buf, sync, self.space_id, value),
+            |buf, sync| protocol::encode_insert(buf, sync, self.stream_id, self.space_id, value),
             protocol::decode_single_row,
             options,
         )
@@ -80,7 +177,7 @@ impl RemoteSpace {
         T: ToTupleBuffer,
     {
         self.conn_inner.request(
-            |buf, sync| protocol::encode_replace(buf, sync, self.space_id, value),
+            |buf, sync| protocol::encode_replace(buf, sync, self.stream_id, self.space_id, value),
             protocol::decode_single_row,
             options,
         )
@@ -125,3 +222,33 @@ impl RemoteSpace {
         self.primary_key().delete(key, options)
     }
 }
+
+impl SpaceApi for RemoteSpace {
+    type Index = RemoteIndex;
+
+    #[inline(always)]
+    fn index(&self, name: &str) -> Result<Option<RemoteIndex>, Error> {
+        RemoteSpace::index(self, name)
+    }
+
+    #[inline(always)]
+    fn primary_key(&self) -> RemoteIndex {
+        RemoteSpace::primary_key(self)
+    }
+
+    #[inline(always)]
+    fn insert<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        RemoteSpace::insert(self, value, &Options::default())
+    }
+
+    #[inline(always)]
+    fn replace<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        RemoteSpace::replace(self, value, &Options::default())
+    }
+}
@@ -7,9 +7,13 @@ use crate::coio::{read, write, CoIOStream};
 use crate::error::Error;
 use crate::ffi::tarantool as ffi;
 use crate::fiber::Cond;
+#[cfg(feature = "tls")]
+use crate::tls::TlsStream;
 
 pub struct ConnStream {
     fd: RawFd,
+    #[cfg(feature = "tls")]
+    tls: Option<Rc<TlsStream>>,
     reader_guard: Rc<ConnStreamGuard>,
     writer_guard: Rc<ConnStreamGuard>,
 }
@@ -18,6 +22,24 @@ impl ConnStream {
     pub fn new(stream: CoIOStream) -> Result<Self, Error> {
         Ok(ConnStream {
             fd: stream.into_raw_fd(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            reader_guard: Rc::new(ConnStreamGuard {
+                is_acquired: Cell::new(false),
+                drop_cond: Cond::new(),
+            }),
+            writer_guard: Rc::new(ConnStreamGuard {
+                is_acquired: Cell::new(false),
+                drop_cond: Cond::new(),
+            }),
+        })
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn with_tls(stream: TlsStream) -> Result<Self, Error> {
+        Ok(ConnStream {
+            fd: std::os::unix::io::AsRawFd::as_raw_fd(&stream),
+            tls: Some(Rc::new(stream)),
             reader_guard: Rc::new(ConnStreamGuard {
                 is_acquired: Cell::new(false),
                 drop_cond: Cond::new(),
@@ -38,6 +60,8 @@ impl ConnStream {
         self.reader_guard.is_acquired.set(true);
         ConnStreamReader {
             fd: self.fd,
+            #[cfg(feature = "tls")]
+            tls: self.tls.clone(),
             reader_guard: self.reader_guard.clone(),
         }
     }
@@ -47,6 +71,8 @@ impl ConnStream {
         self.writer_guard.is_acquired.set(true);
         ConnStreamWriter {
             fd: self.fd,
+            #[cfg(feature = "tls")]
+            tls: self.tls.clone(),
             writer_guard: self.writer_guard.clone(),
         }
     }
@@ -69,17 +95,28 @@ impl Drop for ConnStream {
     fn drop(&mut self) {
         self.reader_guard.wait();
         self.writer_guard.wait();
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            // The socket is closed by the `TlsStream`
+            return;
+        }
         unsafe { ffi::coio_close(self.fd) };
     }
 }
 
 pub struct ConnStreamReader {
     fd: RawFd,
+    #[cfg(feature = "tls")]
+    tls: Option<Rc<TlsStream>>,
     reader_guard: Rc<ConnStreamGuard>,
 }
 
 impl Read for ConnStreamReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return (&**tls).read(buf);
+        }
         read(self.fd, buf, None)
     }
 }
@@ -93,11 +130,17 @@ impl Drop for ConnStreamReader {
 
 pub struct ConnStreamWriter {
     fd: RawFd,
+    #[cfg(feature = "tls")]
+    tls: Option<Rc<TlsStream>>,
     writer_guard: Rc<ConnStreamGuard>,
 }
 
 impl Write for ConnStreamWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return (&**tls).write(buf);
+        }
         write(self.fd, buf, None)
     }
 
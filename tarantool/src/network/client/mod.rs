@@ -1,28 +1,30 @@
 //! Tarantool based client.
 //! Can be used only from inside tarantool.
 
+mod space;
 mod tcp;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::{Cursor, Error as IoError};
-use std::net::ToSocketAddrs;
-use std::rc::Rc;
-use std::time::Duration;
+use std::ops::{Deref, DerefMut};
+use std::rc::{Rc, Weak};
 
+pub use self::space::{AsyncIndexApi, AsyncSpaceApi, Index, Space};
 use self::tcp::{Error as TcpError, TcpStream};
 
-use super::protocol::api::{Call, Eval, Execute, Ping, Request};
-use super::protocol::options::{ConnOptions, Options};
-use super::protocol::{self, codec, Error as ProtocolError, Protocol, SizeHint, SyncIndex};
-use crate::ffi::tarantool::coio_close;
+use super::protocol::api::{Call, Eval, Execute, Id, Ping, Request, Unwatch, Watch};
+use super::protocol::{
+    self, Error as ProtocolError, Event, Protocol, ProtocolFeature, ProtocolFeatures, SizeHint,
+    SyncIndex, PROTOCOL_VERSION,
+};
 use crate::fiber;
 use crate::fiber::r#async::IntoOnDrop as _;
-use crate::fiber::r#async::{oneshot, timeout, watch};
-use crate::tuple::{Decode, ToTupleBuffer, Tuple};
+use crate::fiber::r#async::{oneshot, watch};
+use crate::tuple::{ToTupleBuffer, Tuple};
+use crate::uri::{Address, Uri};
 
-use futures::io::{ReadHalf, WriteHalf};
-use futures::{AsyncReadExt, AsyncWriteExt};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -32,14 +34,31 @@ pub enum Error {
     Io(#[from] IoError),
     #[error("protocol error: {0}")]
     Protocol(#[from] ProtocolError),
+    #[cfg(feature = "tls")]
+    #[error("tls error: {0}")]
+    Tls(#[from] crate::tls::Error),
     #[error("closed with error: {0}")]
     ClosedWithErr(String),
     #[error("{0}")]
     Other(String),
 }
 
+impl From<Error> for crate::error::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(e) => Self::IO(e),
+            Error::Protocol(e) => e.into(),
+            #[cfg(feature = "tls")]
+            Error::Tls(e) => e.into(),
+            e => Self::IO(IoError::new(std::io::ErrorKind::Other, e)),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 enum State {
+    /// The connection is being established.
+    Connecting,
     Alive,
     ClosedManually,
     ClosedWithError(String),
@@ -59,23 +78,35 @@ type WorkerHandle = fiber::JoinHandle<'static, ()>;
 
 #[derive(Debug)]
 struct ClientInner {
+    /// Address to connect (and reconnect) to.
+    address: Address,
+    config: protocol::Config,
     protocol: Protocol,
     awaiting_response: HashMap<SyncIndex, oneshot::Sender<Result<(), Error>>>,
     state: State,
     close_token: Option<tcp::CloseToken>,
     worker_handles: Vec<WorkerHandle>,
     sender_waker: watch::Sender<()>,
+    features: ProtocolFeatures,
+    /// Remote watchers by watched key. The guard is shared by all the
+    /// [`Watcher`]s of the key.
+    watchers: HashMap<String, (watch::Sender<rmpv::Value>, Weak<UnwatchGuard>)>,
 }
 
 impl ClientInner {
-    pub fn new(config: protocol::Config, sender_waker: watch::Sender<()>) -> Self {
+    pub fn new(address: Address, config: protocol::Config) -> Self {
         Self {
-            protocol: Protocol::with_config(config),
+            address,
+            protocol: Protocol::with_config(config.clone()),
+            config,
             awaiting_response: HashMap::new(),
-            state: State::Alive,
+            state: State::Connecting,
             close_token: None,
             worker_handles: Vec::new(),
-            sender_waker,
+            // Replaced with a new channel for each connection
+            sender_waker: watch::channel(()).0,
+            features: ProtocolFeatures::default(),
+            watchers: HashMap::new(),
         }
     }
 }
@@ -111,34 +142,205 @@ impl Client {
         port: u16,
         config: protocol::Config,
     ) -> Result<Self, Error> {
-        let (sender_waker_tx, sender_waker_rx) = watch::channel(());
-        let mut client = ClientInner::new(config, sender_waker_tx);
-        let stream = TcpStream::connect(url, port).await?;
-        client.close_token = Some(stream.close_token());
+        let address = Address::Inet {
+            host: url.into(),
+            port,
+        };
+        Self::connect_address(address, config).await
+    }
+
+    /// Connects to the instance at `uri`, e.g. `"localhost:3301"` or
+    /// `"admin:secret@unix/:/var/run/tarantool.sock"`. See
+    /// [`uri`](crate::uri) for the supported formats.
+    pub async fn connect_uri(uri: &str) -> Result<Self, Error> {
+        Self::connect_uri_with_config(uri, Default::default()).await
+    }
+
+    /// Same as [`Client::connect_uri`], but the login and password specified
+    /// in the `uri` take precedence over [`protocol::Config::creds`].
+    pub async fn connect_uri_with_config(
+        uri: &str,
+        mut config: protocol::Config,
+    ) -> Result<Self, Error> {
+        let uri: Uri = uri.parse().map_err(IoError::from)?;
+        if let Some(login) = uri.login {
+            config.creds = Some((login, uri.password.unwrap_or_default()));
+        }
+        Self::connect_address(uri.address, config).await
+    }
+
+    /// Connects to the `address`, which is also used to
+    /// [reconnect](Self::reconnect).
+    async fn connect_address(address: Address, config: protocol::Config) -> Result<Self, Error> {
+        let client = Self(Rc::new(RefCell::new(ClientInner::new(address, config))));
+        client.establish().await?;
+        Ok(client)
+    }
+
+    /// Reconnects to the same address if the connection was closed with an
+    /// error, otherwise does nothing. The keys [watched](Self::watch) on the
+    /// old connection are watched again on the new one, so the existing
+    /// [`Watcher`]s keep receiving the updates.
+    ///
+    /// The requests which were in progress when the connection was closed
+    /// aren't retried.
+    pub async fn reconnect(&self) -> Result<(), Error> {
+        match self.0.borrow().state {
+            State::ClosedWithError(_) => {}
+            State::Connecting => return Err(Error::Other("already reconnecting".into())),
+            State::Alive | State::ClosedManually => return Ok(()),
+        }
+        self.stop_workers(State::Connecting);
+        if let Err(e) = self.establish().await {
+            self.0.borrow_mut().state = State::ClosedWithError(e.to_string());
+            return Err(e);
+        }
+        self.rewatch()
+    }
+
+    /// Opens a connection to the client's address, starts the worker fibers
+    /// and negotiates the protocol features.
+    async fn establish(&self) -> Result<(), Error> {
+        let (address, config) = {
+            let client = self.0.borrow();
+            (client.address.clone(), client.config.clone())
+        };
+        let stream = match &address {
+            Address::Inet { host, port } => TcpStream::connect(host, *port).await?,
+            Address::Unix(path) => TcpStream::connect_unix(path)?,
+        };
+        let close_token = stream.close_token();
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &config.tls {
+            let host = match &address {
+                Address::Inet { host, .. } => host.as_str(),
+                Address::Unix(_) => "localhost",
+            };
+            let stream = crate::tls::TlsConnector::new(tls)?
+                .connect_async(host, stream)
+                .await?;
+            let (reader, writer) = stream.split();
+            self.start(config, reader, writer, close_token);
+            return self.negotiate_features().await;
+        }
 
         let (reader, writer) = stream.split();
-        let client = Rc::new(RefCell::new(client));
+        self.start(config, reader, writer, close_token);
+        self.negotiate_features().await
+    }
+
+    /// Starts the worker fibers communicating over `reader` & `writer`.
+    fn start<R, W>(
+        &self,
+        config: protocol::Config,
+        reader: R,
+        writer: W,
+        close_token: tcp::CloseToken,
+    ) where
+        R: AsyncRead + Unpin + 'static,
+        W: AsyncWrite + Unpin + 'static,
+    {
+        let (sender_waker_tx, sender_waker_rx) = watch::channel(());
+        {
+            let mut client = self.0.borrow_mut();
+            client.protocol = Protocol::with_config(config);
+            client.sender_waker = sender_waker_tx;
+            client.close_token = Some(close_token);
+            client.state = State::Alive;
+        }
 
         // start receiver in a separate fiber
-        let mut receiver_handle = fiber::Builder::new()
-            .func_async(receiver(client.clone(), reader))
+        let receiver_handle = fiber::Builder::new()
+            .func_async(receiver(self.0.clone(), reader))
             .name("network-client-receiver")
             .start()
             .unwrap();
 
         // start sender in a separate fiber
         let sender_handle = fiber::Builder::new()
-            .func_async(sender(client.clone(), writer, sender_waker_rx))
+            .func_async(sender(self.0.clone(), writer, sender_waker_rx))
             .name("network-client-sender")
             .start()
             .unwrap();
-        client.borrow_mut().worker_handles = vec![receiver_handle, sender_handle];
-        Ok(Self(client))
+        self.0.borrow_mut().worker_handles = vec![receiver_handle, sender_handle];
+    }
+
+    /// Closes the connection and stops the worker fibers, setting the state
+    /// to `state`. Yields until the fibers are joined.
+    fn stop_workers(&self, state: State) {
+        let mut client = self.0.borrow_mut();
+        client.state = state;
+
+        let close_token = client.close_token.take();
+        let handles: Vec<_> = client.worker_handles.drain(..).collect();
+        let waker = client.sender_waker.clone();
+
+        // Drop ref before executing code that switches fibers.
+        drop(client);
+        if let Some(close_token) = close_token {
+            // Close TCP stream to wake fibers waiting on coio events
+            let _ = close_token.close();
+        }
+        // Wake sender so it can exit loop
+        let _ = waker.send(());
+        // Join fibers
+        for handle in handles {
+            handle.join();
+        }
+    }
+
+    /// Sends `IPROTO_WATCH` for all the watched keys after a reconnect.
+    fn rewatch(&self) -> Result<(), Error> {
+        let mut client = self.0.borrow_mut();
+        if client.watchers.is_empty() {
+            return Ok(());
+        }
+        client
+            .features
+            .require(ProtocolFeature::Watchers)
+            .map_err(ProtocolError::from)?;
+        let ClientInner {
+            protocol, watchers, ..
+        } = &mut *client;
+        for key in watchers.keys() {
+            protocol.send_request(&Watch { key })?;
+        }
+        drop(client);
+        wake_sender(&self.0).unwrap();
+        Ok(())
+    }
+
+    /// Sends `IPROTO_ID` to find out the protocol version and features
+    /// supported by the server. Servers older than 2.10 respond with an
+    /// error, in which case none of the features are considered supported.
+    async fn negotiate_features(&self) -> Result<(), Error> {
+        let request = Id {
+            features: ProtocolFeatures {
+                version: PROTOCOL_VERSION,
+                watchers: true,
+                ..Default::default()
+            },
+        };
+        let features = match self.send(&request).await {
+            Ok(features) => features,
+            Err(Error::Protocol(ProtocolError::Response(_))) => ProtocolFeatures::default(),
+            Err(e) => return Err(e),
+        };
+        self.0.borrow_mut().features = features;
+        Ok(())
+    }
+
+    /// Returns the iproto protocol version and features supported by the
+    /// server.
+    pub fn protocol_features(&self) -> ProtocolFeatures {
+        self.0.borrow().features
     }
 
     fn check_state(&self) -> Result<(), Error> {
         match self.0.borrow().state.clone() {
             State::Alive => Ok(()),
+            State::Connecting => Err(Error::Other("not connected".into())),
             State::ClosedManually => unreachable!("All client handles are dropped at this point"),
             State::ClosedWithError(err) => Err(Error::ClosedWithErr(err)),
         }
@@ -205,6 +407,49 @@ impl Client {
         self.send(&Eval { args, expr }).await
     }
 
+    /// Subscribe to updates of `key` on the remote instance, see
+    /// [`box.watch`](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_events/watch/).
+    ///
+    /// The current value of the key is received shortly after the call and
+    /// every subsequent update is reported via the returned [`Watcher`]. The
+    /// value is `nil` until the first notification is received or if the key
+    /// wasn't broadcast on the remote instance.
+    ///
+    /// Watching the same key more than once shares a single subscription.
+    /// Once all the watchers for a key are dropped, the key is unwatched. The
+    /// subscription survives [reconnects](Self::reconnect): the key is
+    /// watched again on the new connection and the current value is received.
+    /// Once all the clients are dropped, the watchers return errors.
+    ///
+    /// Requires tarantool 2.10 or later on the remote side.
+    pub fn watch(&self, key: &str) -> Result<Watcher, Error> {
+        self.check_state()?;
+        self.protocol_features()
+            .require(ProtocolFeature::Watchers)
+            .map_err(ProtocolError::from)?;
+        let mut client = self.0.borrow_mut();
+        if let Some((sender, guard)) = client.watchers.get(key) {
+            if let Some(guard) = guard.upgrade() {
+                return Ok(Watcher {
+                    rx: sender.subscribe(),
+                    _guard: guard,
+                });
+            }
+        }
+        client.protocol.send_request(&Watch { key })?;
+        let (tx, rx) = watch::channel(rmpv::Value::Nil);
+        let guard = Rc::new(UnwatchGuard {
+            client: Rc::downgrade(&self.0),
+            key: key.into(),
+        });
+        client
+            .watchers
+            .insert(key.into(), (tx, Rc::downgrade(&guard)));
+        drop(client);
+        wake_sender(&self.0).unwrap();
+        Ok(Watcher { rx, _guard: guard })
+    }
+
     /// Remote execute of sql query.
     pub async fn execute<T: ToTupleBuffer>(
         &self,
@@ -225,30 +470,71 @@ impl Drop for Client {
     fn drop(&mut self) {
         // 3 means this client and 2 fibers: receiver and sender
         if Rc::strong_count(&self.0) <= 3 {
-            let mut client = self.0.borrow_mut();
-            // Stop fibers
-            client.state = State::ClosedManually;
-
-            let close_token = client.close_token.take();
-            let handles: Vec<_> = client.worker_handles.drain(..).collect();
-            let waker = client.sender_waker.clone();
-
-            // Drop ref before executing code that switches fibers.
-            drop(client);
-            if let Some(close_token) = close_token {
-                // Close TCP stream to wake fibers waiting on coio events
-                let _ = close_token.close();
-            }
-            // Wake sender so it can exit loop
-            waker.send(());
-            // Join fibers
-            for handle in handles {
-                handle.join();
-            }
+            self.stop_workers(State::ClosedManually);
         }
     }
 }
 
+/// Receives the updates of a key watched on the remote instance, see
+/// [`Client::watch`]. Dereferences to a [`watch::Receiver`].
+///
+/// The key is unwatched once the last `Watcher` for it is dropped.
+#[derive(Clone, Debug)]
+pub struct Watcher {
+    rx: watch::Receiver<rmpv::Value>,
+    _guard: Rc<UnwatchGuard>,
+}
+
+impl Deref for Watcher {
+    type Target = watch::Receiver<rmpv::Value>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.rx
+    }
+}
+
+impl DerefMut for Watcher {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rx
+    }
+}
+
+/// Sends `IPROTO_UNWATCH` for the key when dropped. Shared by all the
+/// [`Watcher`]s of the key.
+#[derive(Debug)]
+struct UnwatchGuard {
+    client: Weak<RefCell<ClientInner>>,
+    key: String,
+}
+
+impl Drop for UnwatchGuard {
+    fn drop(&mut self) {
+        let client = match self.client.upgrade() {
+            Some(client) => client,
+            // All the clients are dropped, there's nothing to unwatch
+            None => return,
+        };
+        // If the client is borrowed, the key is unwatched when the next
+        // notification for it arrives, see `handle_event`.
+        let mut inner = match client.try_borrow_mut() {
+            Ok(inner) => inner,
+            Err(_) => return,
+        };
+        inner.watchers.remove(&self.key);
+        if !inner.state.is_alive() {
+            return;
+        }
+        if let Err(e) = inner.protocol.send_request(&Unwatch { key: &self.key }) {
+            log::error!("Failed to unwatch {:?}: {e}", self.key);
+            return;
+        }
+        drop(inner);
+        let _ = wake_sender(&client);
+    }
+}
+
 macro_rules! handle_result {
     ($client:expr, $e:expr) => {
         match $e {
@@ -263,16 +549,41 @@ macro_rules! handle_result {
                     // We don't care about errors at this point
                     let _ = subscription.send(Err(Error::ClosedWithErr(str_err.clone())));
                 }
+                // The watchers are kept to be watched again on reconnect
                 return;
             }
         }
     };
 }
 
+/// Notifies the watchers of `event.key` and acknowledges the notification, so
+/// that the server sends the next one. Unwatches the key if all the watchers
+/// are dropped, but the [`UnwatchGuard`] couldn't do it.
+fn handle_event(client: &mut ClientInner, event: Event) {
+    let Event { key, value } = event;
+    let is_closed = match client.watchers.get(&key) {
+        // Already unwatched
+        None => return,
+        Some((sender, _)) => sender.is_closed(),
+    };
+    let result = if is_closed {
+        client.watchers.remove(&key);
+        client.protocol.send_request(&Unwatch { key: &key })
+    } else {
+        if client.watchers[&key].0.send(value).is_err() {
+            log::warn!("Failed to notify watchers of {key:?}, the value is borrowed");
+        }
+        client.protocol.send_request(&Watch { key: &key })
+    };
+    if let Err(e) = result {
+        log::error!("Failed to acknowledge event for {key:?}: {e}");
+    }
+}
+
 /// Sender work loop. Yields on each iteration and during awaits.
 async fn sender(
     client: Rc<RefCell<ClientInner>>,
-    mut writer: WriteHalf<TcpStream>,
+    mut writer: impl AsyncWrite + Unpin,
     mut waker: watch::Receiver<()>,
 ) {
     loop {
@@ -287,7 +598,9 @@ async fn sender(
             .collect();
         if data.is_empty() {
             // Wait for explicit wakeup, it should happen when there is new outgoing data
-            waker.changed().await;
+            if waker.changed().await.is_err() {
+                return;
+            }
         } else {
             let result = writer.write_all(&data).await;
             handle_result!(client.borrow_mut(), result);
@@ -296,7 +609,7 @@ async fn sender(
 }
 
 /// Receiver work loop. Yields on each iteration and during awaits.
-async fn receiver(client: Rc<RefCell<ClientInner>>, mut reader: ReadHalf<TcpStream>) {
+async fn receiver(client: Rc<RefCell<ClientInner>>, mut reader: impl AsyncRead + Unpin) {
     let mut hint = client.borrow().protocol.read_size_hint();
     loop {
         if client.borrow().state.is_closed() {
@@ -312,6 +625,10 @@ async fn receiver(client: Rc<RefCell<ClientInner>>, mut reader: ReadHalf<TcpStre
                     .process_incoming(&mut Cursor::new(buf));
                 hint = client.borrow().protocol.read_size_hint();
                 let result = handle_result!(client.borrow_mut(), result);
+                let events = client.borrow_mut().protocol.take_events();
+                for event in events {
+                    handle_event(&mut client.borrow_mut(), event);
+                }
                 if let Some(sync) = result {
                     let subscription = client.borrow_mut().awaiting_response.remove(&sync);
                     if let Some(subscription) = subscription {
@@ -348,15 +665,21 @@ async fn receiver(client: Rc<RefCell<ClientInner>>, mut reader: ReadHalf<TcpStre
 mod tests {
     use super::*;
     use crate::fiber::r#async::timeout::IntoTimeout as _;
+    use crate::index::IteratorType;
     use crate::space::Space;
+    use crate::test::util::unix_socket_proxy;
     use crate::test::TARANTOOL_LISTEN;
+    use std::time::Duration;
 
+    // The update isn't needless if the `tls` feature is enabled
+    #[allow(clippy::needless_update)]
     async fn test_client() -> Client {
         Client::connect_with_config(
             "localhost",
             TARANTOOL_LISTEN,
             protocol::Config {
                 creds: Some(("test_user".to_owned(), "password".to_owned())),
+                ..Default::default()
             },
         )
         .timeout(Duration::from_secs(3))
@@ -368,7 +691,7 @@ mod tests {
     #[crate::test(tarantool = "crate")]
     fn connect() {
         fiber::block_on(async {
-            let client = Client::connect("localhost", TARANTOOL_LISTEN)
+            let _client = Client::connect("localhost", TARANTOOL_LISTEN)
                 .await
                 .unwrap();
         });
@@ -383,6 +706,125 @@ mod tests {
         });
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn connect_uri() {
+        let path = unix_socket_proxy(TARANTOOL_LISTEN);
+        let uris = [
+            format!("test_user:password@localhost:{}", TARANTOOL_LISTEN),
+            format!("test_user:password@unix/:{}", path.display()),
+        ];
+        fiber::block_on(async {
+            for uri in &uris {
+                let client = Client::connect_uri(uri)
+                    .timeout(Duration::from_secs(3))
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let user = client
+                    .eval("return box.session.user()", &())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(user.decode::<(String,)>().unwrap().0, "test_user");
+            }
+
+            let err = Client::connect_uri("localhost").await.unwrap_err();
+            assert!(matches!(err, Error::Io(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn protocol_features() {
+        fiber::block_on(async {
+            let client = test_client().await;
+            let features = client.protocol_features();
+            assert_ne!(features.version, 0);
+            assert!(features.streams);
+            assert!(features.transactions);
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn watch() {
+        crate::event::broadcast("test_watch", "foo").unwrap();
+        fiber::block_on(async {
+            let client = test_client().await;
+            let mut rx = client.watch("test_watch").unwrap();
+            rx.changed()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(rx.get_cloned(), rmpv::Value::from("foo"));
+
+            // Watching the same key again shares the subscription
+            let mut rx_2 = client.watch("test_watch").unwrap();
+            assert_eq!(rx_2.get_cloned(), rmpv::Value::from("foo"));
+
+            crate::event::broadcast("test_watch", "bar").unwrap();
+            rx.changed()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(rx.get_cloned(), rmpv::Value::from("bar"));
+            rx_2.changed()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn watch_reconnect() {
+        crate::event::broadcast("test_watch_reconnect", "foo").unwrap();
+        fiber::block_on(async {
+            let client = test_client().await;
+            let mut watcher = client.watch("test_watch_reconnect").unwrap();
+            watcher
+                .changed()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+
+            // Break the connection on the server side
+            let _ = client
+                .eval(
+                    "local ffi = require('ffi')
+                    pcall(ffi.cdef, 'int shutdown(int sockfd, int how);')
+                    ffi.C.shutdown(box.session.fd(), 2)",
+                    &(),
+                )
+                .timeout(Duration::from_secs(3))
+                .await;
+            let err = client
+                .ping()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap_err();
+            assert!(matches!(err, Error::ClosedWithErr(_)), "{}", err);
+
+            crate::event::broadcast("test_watch_reconnect", "bar").unwrap();
+            client
+                .reconnect()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+            // The key is watched again on the new connection
+            watcher
+                .changed()
+                .timeout(Duration::from_secs(3))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(watcher.get_cloned(), rmpv::Value::from("bar"));
+        });
+    }
+
     #[crate::test(tarantool = "crate")]
     fn ping() {
         fiber::block_on(async {
@@ -453,7 +895,7 @@ mod tests {
 
             assert_eq!(result.len(), 1);
             assert_eq!(
-                result.get(0).unwrap().decode::<(u64, String)>().unwrap(),
+                result.first().unwrap().decode::<(u64, String)>().unwrap(),
                 (6002, "6002".to_string())
             );
         });
@@ -492,6 +934,57 @@ mod tests {
         });
     }
 
+    #[crate::test(tarantool = "crate")]
+    fn space_crud() {
+        Space::find("test_s1").unwrap().truncate().unwrap();
+
+        fiber::block_on(async {
+            let client = test_client().await;
+            assert!(client.space("no_such_space").await.unwrap().is_none());
+            let space = client.space("test_s1").await.unwrap().unwrap();
+
+            let res = space.insert(&(6101, "foo")).await.unwrap();
+            assert_eq!(res.unwrap().decode::<(u64, String)>().unwrap().1, "foo");
+            space.replace(&(6102, "bar")).await.unwrap();
+            let res = space.update(&(6101,), &[("=", 1, "baz")]).await.unwrap();
+            assert_eq!(res.unwrap().decode::<(u64, String)>().unwrap().1, "baz");
+            space
+                .upsert(&(6103, "new"), &[("=", 1, "upd")])
+                .await
+                .unwrap();
+            let res = space.get(&(6103,)).await.unwrap();
+            assert_eq!(res.unwrap().decode::<(u64, String)>().unwrap().1, "new");
+
+            let rows = space.select(IteratorType::GE, &(6102,)).await.unwrap();
+            assert_eq!(rows.len(), 2);
+            let count = space.count(IteratorType::All, &()).await.unwrap();
+            assert_eq!(count, 3);
+
+            let res = space.delete(&(6102,)).await.unwrap();
+            assert_eq!(res.unwrap().decode::<(u64, String)>().unwrap().1, "bar");
+            assert!(space.get(&(6102,)).await.unwrap().is_none());
+
+            let index = space.index("primary").await.unwrap().unwrap();
+            assert!(index.get(&(6101,)).await.unwrap().is_some());
+            assert!(space.index("no_such_index").await.unwrap().is_none());
+        });
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn async_space_api() {
+        async fn replace_and_get<S: AsyncSpaceApi>(space: &S) -> Option<Tuple> {
+            space.replace(&(6201, "generic")).await.unwrap();
+            space.primary_key().get(&(6201,)).await.unwrap()
+        }
+
+        fiber::block_on(async {
+            let client = test_client().await;
+            let space = client.space("test_s1").await.unwrap().unwrap();
+            let res = replace_and_get(&space).await;
+            assert_eq!(res.unwrap().decode::<(u64, String)>().unwrap().1, "generic");
+        });
+    }
+
     #[crate::test(tarantool = "crate")]
     fn eval() {
         fiber::block_on(async {
@@ -0,0 +1,700 @@
+//! Remote spaces and indexes accessed via [`Client`].
+
+use std::io::Write;
+use std::vec::IntoIter;
+
+use futures::future::LocalBoxFuture;
+
+use super::{Client, Error, ProtocolError};
+use crate::fiber;
+use crate::index::{IndexApi, IteratorType};
+use crate::network::protocol::api::{Delete, Eval, Insert, Replace, Select, Update, Upsert};
+use crate::space::{SpaceApi, SystemSpace};
+use crate::tuple::{Encode, ToTupleBuffer, Tuple};
+
+/// Id of the `name` index of the `_vspace` & `_vindex` system spaces.
+const NAME_INDEX_ID: u32 = 2;
+
+impl Client {
+    /// Find the remote space by `name`.
+    ///
+    /// This function performs a SELECT request to the remote `_vspace`
+    /// system space.
+    pub async fn space(&self, name: &str) -> Result<Option<Space>, Error> {
+        let rows = self
+            .send(&Select {
+                space_id: SystemSpace::VSpace as u32,
+                index_id: NAME_INDEX_ID,
+                limit: 1,
+                offset: 0,
+                iterator_type: IteratorType::Eq,
+                key: &(name,),
+            })
+            .await?;
+        let space_id = match rows.first() {
+            Some(row) => row.field::<u32>(0).map_err(ProtocolError::from)?,
+            None => return Ok(None),
+        };
+        Ok(space_id.map(|space_id| Space {
+            client: self.clone(),
+            space_id,
+        }))
+    }
+}
+
+/// Remote space accessed via [`Client`], see [`Client::space`].
+#[derive(Clone, Debug)]
+pub struct Space {
+    client: Client,
+    space_id: u32,
+}
+
+impl Space {
+    /// Returns the id of the space.
+    #[inline(always)]
+    pub fn id(&self) -> u32 {
+        self.space_id
+    }
+
+    /// Find the index by `name`.
+    ///
+    /// This function performs a SELECT request to the remote `_vindex`
+    /// system space.
+    pub async fn index(&self, name: &str) -> Result<Option<Index>, Error> {
+        let rows = self
+            .client
+            .send(&Select {
+                space_id: SystemSpace::VIndex as u32,
+                index_id: NAME_INDEX_ID,
+                limit: 1,
+                offset: 0,
+                iterator_type: IteratorType::Eq,
+                key: &(self.space_id, name),
+            })
+            .await?;
+        let index_id = match rows.first() {
+            Some(row) => row.field::<u32>(1).map_err(ProtocolError::from)?,
+            None => return Ok(None),
+        };
+        Ok(index_id.map(|index_id| Index {
+            client: self.client.clone(),
+            space_id: self.space_id,
+            index_id,
+        }))
+    }
+
+    /// Returns index with id = 0
+    #[inline(always)]
+    pub fn primary_key(&self) -> Index {
+        Index {
+            client: self.client.clone(),
+            space_id: self.space_id,
+            index_id: 0,
+        }
+    }
+
+    /// The remote equivalent of [`space::Space::get`](crate::space::Space::get).
+    pub async fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().get(key).await
+    }
+
+    /// The remote equivalent of [`space::Space::select`](crate::space::Space::select).
+    pub async fn select<K>(&self, iterator_type: IteratorType, key: &K) -> Result<Vec<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().select(iterator_type, key).await
+    }
+
+    /// The remote equivalent of [`space::Space::insert`](crate::space::Space::insert).
+    pub async fn insert<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        self.client
+            .send(&Insert {
+                space_id: self.space_id,
+                value,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`space::Space::replace`](crate::space::Space::replace).
+    pub async fn replace<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        self.client
+            .send(&Replace {
+                space_id: self.space_id,
+                value,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`space::Space::update`](crate::space::Space::update).
+    pub async fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.primary_key().update(key, ops).await
+    }
+
+    /// The remote equivalent of [`space::Space::upsert`](crate::space::Space::upsert).
+    pub async fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.primary_key().upsert(value, ops).await
+    }
+
+    /// The remote equivalent of [`space::Space::delete`](crate::space::Space::delete).
+    pub async fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().delete(key).await
+    }
+
+    /// The remote equivalent of [`space::Space::count`](crate::space::Space::count).
+    pub async fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().count(iterator_type, key).await
+    }
+}
+
+/// Remote index accessed via [`Client`], see [`Space::index`].
+#[derive(Clone, Debug)]
+pub struct Index {
+    client: Client,
+    space_id: u32,
+    index_id: u32,
+}
+
+impl Index {
+    /// The remote equivalent of [`index::Index::get`](crate::index::Index::get).
+    pub async fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        let rows = self
+            .client
+            .send(&Select {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                limit: 1,
+                offset: 0,
+                iterator_type: IteratorType::Eq,
+                key,
+            })
+            .await?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// The remote equivalent of [`index::Index::select`](crate::index::Index::select).
+    pub async fn select<K>(&self, iterator_type: IteratorType, key: &K) -> Result<Vec<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.client
+            .send(&Select {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                limit: u32::MAX,
+                offset: 0,
+                iterator_type,
+                key,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`index::Index::update`](crate::index::Index::update).
+    pub async fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.client
+            .send(&Update {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                key,
+                ops,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`index::Index::upsert`](crate::index::Index::upsert).
+    pub async fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.client
+            .send(&Upsert {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                value,
+                ops,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`index::Index::delete`](crate::index::Index::delete).
+    pub async fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.client
+            .send(&Delete {
+                space_id: self.space_id,
+                index_id: self.index_id,
+                key,
+            })
+            .await
+    }
+
+    /// The remote equivalent of [`index::Index::count`](crate::index::Index::count).
+    ///
+    /// There's no iproto request for counting tuples, so it's done via `eval`.
+    pub async fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        let res = self
+            .client
+            .send(&Eval {
+                expr: "local space_id, index_id, key, iterator = ...
+                return box.space[space_id].index[index_id]:count(key, {iterator = iterator})",
+                args: &CountArgs {
+                    space_id: self.space_id,
+                    index_id: self.index_id,
+                    key,
+                    iterator_type,
+                },
+            })
+            .await?;
+        match res {
+            Some(tuple) => Ok(tuple.decode::<(usize,)>().map_err(ProtocolError::from)?.0),
+            None => Err(Error::Other("count returned nothing".into())),
+        }
+    }
+}
+
+/// Arguments of `index:count` called via `eval`.
+struct CountArgs<'a, K> {
+    space_id: u32,
+    index_id: u32,
+    key: &'a K,
+    iterator_type: IteratorType,
+}
+
+impl<'a, K> ToTupleBuffer for CountArgs<'a, K>
+where
+    K: ToTupleBuffer,
+{
+    fn write_tuple_data(&self, w: &mut impl Write) -> crate::Result<()> {
+        rmp::encode::write_array_len(w, 4)?;
+        rmp::encode::write_uint(w, self.space_id as _)?;
+        rmp::encode::write_uint(w, self.index_id as _)?;
+        self.key.write_tuple_data(w)?;
+        rmp::encode::write_sint(w, self.iterator_type as _)?;
+        Ok(())
+    }
+}
+
+/// Blocks the current fiber until the request is completed, so must not be used
+/// from async code. Use the async methods of [`Space`] or [`AsyncSpaceApi`]
+/// instead.
+impl SpaceApi for Space {
+    type Index = Index;
+
+    #[inline(always)]
+    fn index(&self, name: &str) -> crate::Result<Option<Index>> {
+        Ok(fiber::block_on(Space::index(self, name))?)
+    }
+
+    #[inline(always)]
+    fn primary_key(&self) -> Index {
+        Space::primary_key(self)
+    }
+
+    #[inline(always)]
+    fn insert<T>(&self, value: &T) -> crate::Result<Option<Tuple>>
+    where
+        T: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Space::insert(self, value))?)
+    }
+
+    #[inline(always)]
+    fn replace<T>(&self, value: &T) -> crate::Result<Option<Tuple>>
+    where
+        T: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Space::replace(self, value))?)
+    }
+}
+
+/// Blocks the current fiber until the request is completed, so must not be used
+/// from async code. Use the async methods of [`Index`] or [`AsyncIndexApi`]
+/// instead.
+impl IndexApi for Index {
+    type Iterator = IntoIter<Tuple>;
+
+    #[inline(always)]
+    fn get<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Index::get(self, key))?)
+    }
+
+    #[inline(always)]
+    fn select<K>(&self, iterator_type: IteratorType, key: &K) -> crate::Result<Self::Iterator>
+    where
+        K: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Index::select(self, iterator_type, key))?.into_iter())
+    }
+
+    #[inline(always)]
+    fn update<K, Op>(&self, key: &K, ops: &[Op]) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        Ok(fiber::block_on(Index::update(self, key, ops))?)
+    }
+
+    #[inline(always)]
+    fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> crate::Result<()>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        Ok(fiber::block_on(Index::upsert(self, value, ops))?)
+    }
+
+    #[inline(always)]
+    fn delete<K>(&self, key: &K) -> crate::Result<Option<Tuple>>
+    where
+        K: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Index::delete(self, key))?)
+    }
+
+    #[inline(always)]
+    fn count<K>(&self, iterator_type: IteratorType, key: &K) -> crate::Result<usize>
+    where
+        K: ToTupleBuffer,
+    {
+        Ok(fiber::block_on(Index::count(self, iterator_type, key))?)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// AsyncSpaceApi
+////////////////////////////////////////////////////////////////////////////////
+
+/// The async counterpart of [`SpaceApi`], which allows writing async code
+/// generic over the remote spaces.
+///
+/// The futures are boxed, because async functions aren't supported in traits.
+pub trait AsyncSpaceApi {
+    type Index: AsyncIndexApi;
+
+    /// See [`Space::index`].
+    fn index<'a>(&'a self, name: &'a str)
+        -> LocalBoxFuture<'a, Result<Option<Self::Index>, Error>>;
+
+    /// See [`Space::primary_key`].
+    fn primary_key(&self) -> Self::Index;
+
+    /// See [`Space::get`].
+    fn get<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Space::select`].
+    fn select<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<Vec<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Space::insert`].
+    fn insert<'a, T>(&'a self, value: &'a T) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        T: ToTupleBuffer;
+
+    /// See [`Space::replace`].
+    fn replace<'a, T>(&'a self, value: &'a T) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        T: ToTupleBuffer;
+
+    /// See [`Space::update`].
+    fn update<'a, K, Op>(
+        &'a self,
+        key: &'a K,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+        Op: Encode;
+
+    /// See [`Space::upsert`].
+    fn upsert<'a, T, Op>(
+        &'a self,
+        value: &'a T,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<(), Error>>
+    where
+        T: ToTupleBuffer,
+        Op: Encode;
+
+    /// See [`Space::delete`].
+    fn delete<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Space::count`].
+    fn count<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<usize, Error>>
+    where
+        K: ToTupleBuffer;
+}
+
+impl AsyncSpaceApi for Space {
+    type Index = Index;
+
+    #[inline(always)]
+    fn index<'a>(&'a self, name: &'a str) -> LocalBoxFuture<'a, Result<Option<Index>, Error>> {
+        Box::pin(Space::index(self, name))
+    }
+
+    #[inline(always)]
+    fn primary_key(&self) -> Index {
+        Space::primary_key(self)
+    }
+
+    #[inline(always)]
+    fn get<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Space::get(self, key))
+    }
+
+    #[inline(always)]
+    fn select<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<Vec<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Space::select(self, iterator_type, key))
+    }
+
+    #[inline(always)]
+    fn insert<'a, T>(&'a self, value: &'a T) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        T: ToTupleBuffer,
+    {
+        Box::pin(Space::insert(self, value))
+    }
+
+    #[inline(always)]
+    fn replace<'a, T>(&'a self, value: &'a T) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        T: ToTupleBuffer,
+    {
+        Box::pin(Space::replace(self, value))
+    }
+
+    #[inline(always)]
+    fn update<'a, K, Op>(
+        &'a self,
+        key: &'a K,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        Box::pin(Space::update(self, key, ops))
+    }
+
+    #[inline(always)]
+    fn upsert<'a, T, Op>(
+        &'a self,
+        value: &'a T,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<(), Error>>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        Box::pin(Space::upsert(self, value, ops))
+    }
+
+    #[inline(always)]
+    fn delete<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Space::delete(self, key))
+    }
+
+    #[inline(always)]
+    fn count<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<usize, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Space::count(self, iterator_type, key))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// AsyncIndexApi
+////////////////////////////////////////////////////////////////////////////////
+
+/// The async counterpart of [`IndexApi`], which allows writing async code
+/// generic over the remote indexes.
+///
+/// The futures are boxed, because async functions aren't supported in traits.
+pub trait AsyncIndexApi {
+    /// See [`Index::get`].
+    fn get<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Index::select`].
+    fn select<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<Vec<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Index::update`].
+    fn update<'a, K, Op>(
+        &'a self,
+        key: &'a K,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+        Op: Encode;
+
+    /// See [`Index::upsert`].
+    fn upsert<'a, T, Op>(
+        &'a self,
+        value: &'a T,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<(), Error>>
+    where
+        T: ToTupleBuffer,
+        Op: Encode;
+
+    /// See [`Index::delete`].
+    fn delete<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer;
+
+    /// See [`Index::count`].
+    fn count<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<usize, Error>>
+    where
+        K: ToTupleBuffer;
+}
+
+impl AsyncIndexApi for Index {
+    #[inline(always)]
+    fn get<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Index::get(self, key))
+    }
+
+    #[inline(always)]
+    fn select<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<Vec<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Index::select(self, iterator_type, key))
+    }
+
+    #[inline(always)]
+    fn update<'a, K, Op>(
+        &'a self,
+        key: &'a K,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        Box::pin(Index::update(self, key, ops))
+    }
+
+    #[inline(always)]
+    fn upsert<'a, T, Op>(
+        &'a self,
+        value: &'a T,
+        ops: &'a [Op],
+    ) -> LocalBoxFuture<'a, Result<(), Error>>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        Box::pin(Index::upsert(self, value, ops))
+    }
+
+    #[inline(always)]
+    fn delete<'a, K>(&'a self, key: &'a K) -> LocalBoxFuture<'a, Result<Option<Tuple>, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Index::delete(self, key))
+    }
+
+    #[inline(always)]
+    fn count<'a, K>(
+        &'a self,
+        iterator_type: IteratorType,
+        key: &'a K,
+    ) -> LocalBoxFuture<'a, Result<usize, Error>>
+    where
+        K: ToTupleBuffer,
+    {
+        Box::pin(Index::count(self, iterator_type, key))
+    }
+}
@@ -1,21 +1,24 @@
-use std::cell::{Cell, RefCell};
+use std::cell::Cell;
 use std::ffi::{CString, NulError};
 use std::future::Future;
 use std::mem::{self, MaybeUninit};
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
-use std::os::unix::io::RawFd;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
 use std::os::unix::prelude::IntoRawFd;
+use std::path::Path;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 use std::{io, ptr};
 
 use futures::{AsyncRead, AsyncWrite};
 
 use crate::ffi::tarantool as ffi;
+use crate::fiber::r#async;
 use crate::fiber::r#async::context::ContextExt;
-use crate::fiber::r#async::{self, timeout};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -68,6 +71,17 @@ impl TcpStream {
         })
     }
 
+    /// Creates a [`TcpStream`] connected to a unix domain socket at `path`.
+    ///
+    /// Despite the name, the stream then works over the unix socket.
+    pub fn connect_unix(path: impl AsRef<Path>) -> Result<TcpStream, Error> {
+        let stream = UnixStream::connect(path).map_err(Error::Connect)?;
+        stream.set_nonblocking(true).map_err(Error::SetNonBlock)?;
+        Ok(Self {
+            fd: stream.into_raw_fd(),
+        })
+    }
+
     /// Close token for [`TcpStream`] to be able to close it from other fibers.
     pub fn close_token(&self) -> CloseToken {
         CloseToken(self.fd)
@@ -140,7 +154,7 @@ async unsafe fn get_address_info(url: &str) -> Result<*mut libc::addrinfo, Error
 }
 
 unsafe fn to_rs_sockaddr(addr: *const libc::sockaddr, port: u16) -> Result<SocketAddr, Error> {
-    match (*addr).sa_family as libc::c_int {
+    match (*addr).sa_family as c_int {
         libc::AF_INET => {
             let addr: *mut libc::sockaddr_in = mem::transmute(addr);
             (*addr).sin_port = port;
@@ -199,7 +213,7 @@ impl AsyncWrite for TcpStream {
         }
     }
 
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         // [`TcpStream`] similarily to std does not buffer anything,
         // so there is nothing to flush.
         //
@@ -207,7 +221,7 @@ impl AsyncWrite for TcpStream {
         Poll::Ready(Ok(()))
     }
 
-    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         Poll::Ready(self.close_token().close())
     }
 }
@@ -248,6 +262,13 @@ impl AsyncRead for TcpStream {
     }
 }
 
+impl AsRawFd for TcpStream {
+    #[inline(always)]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
 impl Drop for TcpStream {
     fn drop(&mut self) {
         let _ = self.close_token().close();
@@ -259,11 +280,12 @@ mod tests {
     use super::*;
 
     use crate::fiber;
-    use crate::fiber::r#async::timeout::IntoTimeout;
+    use crate::fiber::r#async::timeout::{self, IntoTimeout};
     use crate::test::TARANTOOL_LISTEN;
 
     use std::net::TcpListener;
     use std::thread;
+    use std::time::Duration;
 
     use futures::{AsyncReadExt, AsyncWriteExt, FutureExt};
 
@@ -312,7 +334,12 @@ mod tests {
                 .unwrap();
             // Read greeting
             let mut buf = vec![0; 128];
-            stream.read_exact(&mut buf).timeout(_10_SEC).await.unwrap();
+            stream
+                .read_exact(&mut buf)
+                .timeout(_10_SEC)
+                .await
+                .unwrap()
+                .unwrap();
         });
     }
 
@@ -346,8 +373,8 @@ mod tests {
             for stream in listener.incoming() {
                 let mut stream = stream.unwrap();
                 let mut buf = vec![];
-                <std::net::TcpStream as std::io::Read>::read_to_end(&mut stream, &mut buf);
-                sender.send(buf);
+                <std::net::TcpStream as std::io::Read>::read_to_end(&mut stream, &mut buf).unwrap();
+                sender.send(buf).unwrap();
             }
         });
         // Send data
@@ -360,10 +387,12 @@ mod tests {
                     .unwrap();
                 timeout::timeout(_10_SEC, stream.write_all(&[1, 2, 3]))
                     .await
+                    .unwrap()
                     .unwrap();
                 timeout::timeout(_10_SEC, stream.write_all(&[4, 5]))
                     .await
                     .unwrap()
+                    .unwrap();
             });
         }
         let buf = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
@@ -379,31 +408,34 @@ mod tests {
             for stream in listener.incoming() {
                 let mut stream = stream.unwrap();
                 let mut buf = vec![0; 5];
-                <std::net::TcpStream as std::io::Read>::read_exact(&mut stream, &mut buf);
-                <std::net::TcpStream as std::io::Write>::write_all(&mut stream, &buf.clone());
-                sender.send(buf);
+                <std::net::TcpStream as std::io::Read>::read_exact(&mut stream, &mut buf).unwrap();
+                <std::net::TcpStream as std::io::Write>::write_all(&mut stream, &buf.clone())
+                    .unwrap();
+                sender.send(buf).unwrap();
             }
         });
         // Send and read data
         {
-            let mut stream =
-                fiber::block_on(TcpStream::connect("localhost", 3303).timeout(_10_SEC))
-                    .unwrap()
-                    .unwrap();
+            let stream = fiber::block_on(TcpStream::connect("localhost", 3303).timeout(_10_SEC))
+                .unwrap()
+                .unwrap();
             let (mut reader, mut writer) = stream.split();
             let reader_handle = fiber::start_async(async move {
                 let mut buf = vec![0; 5];
                 timeout::timeout(_10_SEC, reader.read_exact(&mut buf))
                     .await
+                    .unwrap()
                     .unwrap();
                 assert_eq!(buf, vec![1, 2, 3, 4, 5])
             });
             let writer_handle = fiber::start_async(async move {
                 timeout::timeout(_10_SEC, writer.write_all(&[1, 2, 3]))
                     .await
+                    .unwrap()
                     .unwrap();
                 timeout::timeout(_10_SEC, writer.write_all(&[4, 5]))
                     .await
+                    .unwrap()
                     .unwrap();
             });
             writer_handle.join();
@@ -429,7 +461,7 @@ mod tests {
                     timeout::timeout(_10_SEC, stream.read_exact(&mut buf))
                 );
                 assert_eq!(is_err.unwrap_err(), timeout::Expired);
-                is_ok.unwrap();
+                is_ok.unwrap().unwrap();
             });
         }
         // Testing with different order in join
@@ -447,7 +479,7 @@ mod tests {
                     timeout::timeout(_0_SEC, always_pending())
                 );
                 assert_eq!(is_err.unwrap_err(), timeout::Expired);
-                is_ok.unwrap();
+                is_ok.unwrap().unwrap();
             });
         }
     }
@@ -1,10 +1,11 @@
-use std::io::{Cursor, Read, Write};
+use std::io::{Cursor, Write};
 
 use super::Error;
-use crate::tuple::{ToTupleBuffer, Tuple};
+use crate::index::IteratorType;
+use crate::tuple::{Encode, ToTupleBuffer, Tuple};
 
 use super::codec::IProtoType;
-use super::{codec, SyncIndex};
+use super::{codec, ProtocolFeatures, SyncIndex};
 
 pub trait Request {
     const TYPE: IProtoType;
@@ -25,19 +26,17 @@ pub trait Request {
     fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error>;
 }
 
-// TODO: Implement `Request` for other types in `IProtoType`
-
 pub struct Ping;
 
 impl Request for Ping {
     const TYPE: IProtoType = IProtoType::Ping;
     type Response = ();
 
-    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+    fn encode_body(&self, out: &mut impl Write, _sync: SyncIndex) -> Result<(), Error> {
         codec::encode_ping(out)
     }
 
-    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+    fn decode_body(&self, _in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
         Ok(())
     }
 }
@@ -111,7 +110,198 @@ impl<'u, 'p, 's> Request for Auth<'u, 'p, 's> {
         codec::encode_auth(out, sync, self.user, self.pass, self.salt)
     }
 
+    fn decode_body(&self, _in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        Ok(())
+    }
+}
+
+/// Protocol version and features negotiation request. The response contains
+/// the version and features supported by the server.
+pub struct Id {
+    /// Version and features supported by the client.
+    pub features: ProtocolFeatures,
+}
+
+impl Request for Id {
+    const TYPE: IProtoType = IProtoType::Id;
+    type Response = ProtocolFeatures;
+
+    fn encode_body(&self, out: &mut impl Write, _sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_id(out, &self.features)
+    }
+
     fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_id(r#in)
+    }
+}
+
+/// Subscribes to updates of `key`, or acknowledges the last received
+/// notification of it. There's no response, the server sends `IPROTO_EVENT`
+/// notifications instead.
+pub struct Watch<'a> {
+    pub key: &'a str,
+}
+
+impl<'a> Request for Watch<'a> {
+    const TYPE: IProtoType = IProtoType::Watch;
+    type Response = ();
+
+    fn encode_body(&self, out: &mut impl Write, _sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_watch(out, self.key)
+    }
+
+    fn decode_body(&self, _in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
         Ok(())
     }
 }
+
+/// Unsubscribes from updates of `key`. There's no response.
+pub struct Unwatch<'a> {
+    pub key: &'a str,
+}
+
+impl<'a> Request for Unwatch<'a> {
+    const TYPE: IProtoType = IProtoType::Unwatch;
+    type Response = ();
+
+    fn encode_body(&self, out: &mut impl Write, _sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_watch(out, self.key)
+    }
+
+    fn decode_body(&self, _in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        Ok(())
+    }
+}
+
+pub struct Select<'a, T> {
+    pub space_id: u32,
+    pub index_id: u32,
+    pub limit: u32,
+    pub offset: u32,
+    pub iterator_type: IteratorType,
+    pub key: &'a T,
+}
+
+impl<'a, T: ToTupleBuffer> Request for Select<'a, T> {
+    const TYPE: IProtoType = IProtoType::Select;
+    type Response = Vec<Tuple>;
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_select(
+            out,
+            sync,
+            self.space_id,
+            self.index_id,
+            self.limit,
+            self.offset,
+            self.iterator_type,
+            self.key,
+        )
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_multiple_rows(r#in, None)
+    }
+}
+
+pub struct Insert<'a, T> {
+    pub space_id: u32,
+    pub value: &'a T,
+}
+
+impl<'a, T: ToTupleBuffer> Request for Insert<'a, T> {
+    const TYPE: IProtoType = IProtoType::Insert;
+    type Response = Option<Tuple>;
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_insert(out, sync, self.space_id, self.value)
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_single_row(r#in)
+    }
+}
+
+pub struct Replace<'a, T> {
+    pub space_id: u32,
+    pub value: &'a T,
+}
+
+impl<'a, T: ToTupleBuffer> Request for Replace<'a, T> {
+    const TYPE: IProtoType = IProtoType::Replace;
+    type Response = Option<Tuple>;
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_replace(out, sync, self.space_id, self.value)
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_single_row(r#in)
+    }
+}
+
+pub struct Update<'a, 'b, K, Op> {
+    pub space_id: u32,
+    pub index_id: u32,
+    pub key: &'a K,
+    pub ops: &'b [Op],
+}
+
+impl<'a, 'b, K: ToTupleBuffer, Op: Encode> Request for Update<'a, 'b, K, Op> {
+    const TYPE: IProtoType = IProtoType::Update;
+    type Response = Option<Tuple>;
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_update(out, sync, self.space_id, self.index_id, self.key, self.ops)
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_single_row(r#in)
+    }
+}
+
+pub struct Upsert<'a, 'b, T, Op> {
+    pub space_id: u32,
+    pub index_id: u32,
+    pub value: &'a T,
+    pub ops: &'b [Op],
+}
+
+impl<'a, 'b, T: ToTupleBuffer, Op: Encode> Request for Upsert<'a, 'b, T, Op> {
+    const TYPE: IProtoType = IProtoType::Upsert;
+    type Response = ();
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_upsert(
+            out,
+            sync,
+            self.space_id,
+            self.index_id,
+            self.value,
+            self.ops,
+        )
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_single_row(r#in).map(drop)
+    }
+}
+
+pub struct Delete<'a, K> {
+    pub space_id: u32,
+    pub index_id: u32,
+    pub key: &'a K,
+}
+
+impl<'a, K: ToTupleBuffer> Request for Delete<'a, K> {
+    const TYPE: IProtoType = IProtoType::Delete;
+    type Response = Option<Tuple>;
+
+    fn encode_body(&self, out: &mut impl Write, sync: SyncIndex) -> Result<(), Error> {
+        codec::encode_delete(out, sync, self.space_id, self.index_id, self.key)
+    }
+
+    fn decode_body(&self, r#in: &mut Cursor<Vec<u8>>) -> Result<Self::Response, Error> {
+        codec::decode_single_row(r#in)
+    }
+}
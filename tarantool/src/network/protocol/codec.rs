@@ -12,7 +12,7 @@ use crate::index::IteratorType;
 use crate::msgpack;
 use crate::tuple::{ToTupleBuffer, Tuple};
 
-use super::{ResponseError, SyncIndex};
+use super::{ProtocolFeature, ProtocolFeatures, ResponseError, SyncIndex};
 
 const REQUEST_TYPE: u8 = 0x00;
 const SYNC: u8 = 0x01;
@@ -38,9 +38,15 @@ const ERROR: u8 = 0x31;
 const SQL_TEXT: u8 = 0x40;
 const SQL_BIND: u8 = 0x41;
 
+const VERSION: u8 = 0x54;
+const FEATURES: u8 = 0x55;
+const EVENT_KEY: u8 = 0x57;
+const EVENT_DATA: u8 = 0x58;
+
 #[derive(Debug, Clone, Copy, serde::Deserialize, FromPrimitive)]
 #[serde(try_from = "u8")]
 #[repr(u8)]
+#[allow(dead_code)]
 enum IProtoKey {
     RequestType = REQUEST_TYPE,
     Sync = SYNC,
@@ -61,6 +67,10 @@ enum IProtoKey {
     Error = ERROR,
     SqlText = SQL_TEXT,
     SqlBind = SQL_BIND,
+    Version = VERSION,
+    Features = FEATURES,
+    EventKey = EVENT_KEY,
+    EventData = EVENT_DATA,
 }
 
 impl TryFrom<u8> for IProtoKey {
@@ -83,6 +93,10 @@ pub enum IProtoType {
     Call = 10,
     Execute = 11,
     Ping = 64,
+    Id = 73,
+    Watch = 74,
+    Unwatch = 75,
+    Event = 76,
 }
 
 pub fn encode_header(
@@ -100,7 +114,7 @@ pub fn encode_header(
 
 pub fn encode_auth(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     user: &str,
     password: &str,
     salt: &[u8],
@@ -150,9 +164,28 @@ pub fn encode_ping(stream: &mut impl Write) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn encode_id(stream: &mut impl Write, features: &ProtocolFeatures) -> Result<(), Error> {
+    rmp::encode::write_map_len(stream, 2)?;
+    rmp::encode::write_pfix(stream, VERSION)?;
+    rmp::encode::write_uint(stream, features.version)?;
+    rmp::encode::write_pfix(stream, FEATURES)?;
+    rmp::encode::write_array_len(stream, features.iter().count() as u32)?;
+    for feature in features.iter() {
+        rmp::encode::write_uint(stream, feature.id())?;
+    }
+    Ok(())
+}
+
+pub fn encode_watch(stream: &mut impl Write, key: &str) -> Result<(), Error> {
+    rmp::encode::write_map_len(stream, 1)?;
+    rmp::encode::write_pfix(stream, EVENT_KEY)?;
+    rmp::encode::write_str(stream, key)?;
+    Ok(())
+}
+
 pub fn encode_execute(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     sql: &str,
     bind_params: &impl ToTupleBuffer,
 ) -> Result<(), Error> {
@@ -167,7 +200,7 @@ pub fn encode_execute(
 
 pub fn encode_call<T>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     function_name: &str,
     args: &T,
 ) -> Result<(), Error>
@@ -185,7 +218,7 @@ where
 
 pub fn encode_eval<T>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     expression: &str,
     args: &T,
 ) -> Result<(), Error>
@@ -204,7 +237,7 @@ where
 #[allow(clippy::too_many_arguments)]
 pub fn encode_select<K>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     index_id: u32,
     limit: u32,
@@ -234,7 +267,7 @@ where
 
 pub fn encode_insert<T>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     value: &T,
 ) -> Result<(), Error>
@@ -252,7 +285,7 @@ where
 
 pub fn encode_replace<T>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     value: &T,
 ) -> Result<(), Error>
@@ -270,7 +303,7 @@ where
 
 pub fn encode_update<K, Op>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     index_id: u32,
     key: &K,
@@ -295,7 +328,7 @@ where
 
 pub fn encode_upsert<T, Op>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     index_id: u32,
     value: &T,
@@ -320,7 +353,7 @@ where
 
 pub fn encode_delete<K>(
     stream: &mut impl Write,
-    sync: SyncIndex,
+    _sync: SyncIndex,
     space_id: u32,
     index_id: u32,
     key: &K,
@@ -367,6 +400,16 @@ pub fn decode_header(stream: &mut (impl Read + Seek)) -> Result<Header, Error> {
         }
     }
 
+    // Event notifications are not responses to any request, so they may lack
+    // both the sync and the schema version
+    if status_code == Some(IProtoType::Event as u32) {
+        return Ok(Header {
+            sync: SyncIndex(sync.unwrap_or(0)),
+            status_code: IProtoType::Event as u32,
+            schema_version: schema_version.unwrap_or(0),
+        });
+    }
+
     if sync.is_none() || status_code.is_none() || schema_version.is_none() {
         return Err(io::Error::from(io::ErrorKind::InvalidData).into());
     }
@@ -403,6 +446,59 @@ pub fn decode_greeting(stream: &mut impl Read) -> Result<Vec<u8>, Error> {
     Ok(salt)
 }
 
+pub fn decode_id(buffer: &mut Cursor<Vec<u8>>) -> Result<ProtocolFeatures, Error> {
+    let mut features = ProtocolFeatures::default();
+    let payload_len = rmp::decode::read_map_len(buffer)?;
+    for _ in 0..payload_len {
+        let key = rmp::decode::read_pfix(buffer)?;
+        match key {
+            VERSION => features.version = rmp::decode::read_int(buffer)?,
+            FEATURES => {
+                let count = rmp::decode::read_array_len(buffer)?;
+                for _ in 0..count {
+                    let id: u64 = rmp::decode::read_int(buffer)?;
+                    // Features unknown to this client are ignored
+                    if let Some(feature) = ProtocolFeature::from_id(id) {
+                        features.set(feature);
+                    }
+                }
+            }
+            _ => {
+                msgpack::skip_value(buffer)?;
+            }
+        }
+    }
+    Ok(features)
+}
+
+/// Decodes the body of an `IPROTO_EVENT` notification. Returns the key and the
+/// value associated with it, which is `nil` if the key was never broadcast or
+/// was deleted.
+pub fn decode_event(buffer: &mut Cursor<Vec<u8>>) -> Result<(String, rmpv::Value), Error> {
+    let mut key = None;
+    let mut data = rmpv::Value::Nil;
+    let payload_len = rmp::decode::read_map_len(buffer)?;
+    for _ in 0..payload_len {
+        match rmp::decode::read_pfix(buffer)? {
+            EVENT_KEY => {
+                let str_len = rmp::decode::read_str_len(buffer)? as usize;
+                let mut str_buf = vec![0u8; str_len];
+                buffer.read_exact(&mut str_buf)?;
+                key = Some(from_utf8(&str_buf)?.to_string());
+            }
+            EVENT_DATA => {
+                data = rmpv::decode::read_value(buffer)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+            _ => {
+                msgpack::skip_value(buffer)?;
+            }
+        }
+    }
+    let key = key.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+    Ok((key, data))
+}
+
 pub fn decode_call(buffer: &mut Cursor<Vec<u8>>) -> Result<Option<Tuple>, Error> {
     let payload_len = rmp::decode::read_map_len(buffer)?;
     for _ in 0..payload_len {
@@ -3,17 +3,18 @@
 pub mod api;
 pub mod codec;
 pub mod options;
-pub mod send_queue;
+// Temporarily private as it is in development
+#[allow(unused)]
+mod send_queue;
 
-use std::cmp::{self, min};
+use std::cmp;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::io::{BufWriter, Cursor, Read, Seek, Write};
+use std::io::{Cursor, Read, Seek};
 use std::str::Utf8Error;
 use std::vec::Drain;
 
 use api::Request;
-use codec::Header;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -27,6 +28,8 @@ pub enum Error {
     DecodeNum(#[from] rmp::decode::NumValueReadError),
     #[error("service responded with error: {0}")]
     Response(#[from] ResponseError),
+    #[error("{0}")]
+    Unsupported(#[from] UnsupportedFeature),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
     // TODO: Remove when `Encode` trait will return rmp errors
@@ -40,10 +43,31 @@ impl From<crate::error::Error> for Error {
     }
 }
 
+impl From<Error> for crate::error::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Utf8(e) => Self::Unicode(e),
+            Error::Encode(e) => Self::ValueWrite(e),
+            Error::Decode(e) => Self::ValueRead(e),
+            Error::DecodeNum(e) => Self::NumValueRead(e),
+            #[cfg(feature = "net_box")]
+            Error::Response(e) => {
+                Self::Remote(crate::net_box::ResponseError { message: e.message })
+            }
+            #[cfg(not(feature = "net_box"))]
+            Error::Response(e) => Self::IO(std::io::Error::new(std::io::ErrorKind::Other, e)),
+            Error::Unsupported(e) => Self::Unsupported(e),
+            Error::Io(e) => Self::IO(e),
+            Error::Other(e) => *e,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SyncIndex(u64);
 
 impl SyncIndex {
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Self {
         let sync = self.0;
         self.0 += 1;
@@ -57,7 +81,154 @@ pub struct ResponseError {
     message: String,
 }
 
-type Response = Vec<u8>;
+/// Version of the iproto protocol reported to the server in `IPROTO_ID`
+/// requests.
+pub const PROTOCOL_VERSION: u64 = 3;
+
+/// An optional feature of the iproto protocol, which may or may not be
+/// supported by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProtocolFeature {
+    /// Iproto streams (`IPROTO_STREAM_ID` in request headers).
+    Streams,
+    /// Interactive transactions (`IPROTO_BEGIN`, `IPROTO_COMMIT` and
+    /// `IPROTO_ROLLBACK` requests).
+    Transactions,
+    /// Error objects encoded as `MP_ERROR` msgpack extension.
+    ErrorExtension,
+    /// Remote watchers (`IPROTO_WATCH`, `IPROTO_UNWATCH` and `IPROTO_EVENT`).
+    Watchers,
+    /// Pagination of select requests (`IPROTO_AFTER_POSITION` and
+    /// `IPROTO_FETCH_POSITION`).
+    Pagination,
+}
+
+impl ProtocolFeature {
+    const ALL: [Self; 5] = [
+        Self::Streams,
+        Self::Transactions,
+        Self::ErrorExtension,
+        Self::Watchers,
+        Self::Pagination,
+    ];
+
+    /// Feature id as used in `IPROTO_FEATURES`.
+    #[inline]
+    pub(crate) fn id(self) -> u64 {
+        match self {
+            Self::Streams => 0,
+            Self::Transactions => 1,
+            Self::ErrorExtension => 2,
+            Self::Watchers => 3,
+            Self::Pagination => 4,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_id(id: u64) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.id() == id)
+    }
+
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Streams => "streams",
+            Self::Transactions => "transactions",
+            Self::ErrorExtension => "error_extension",
+            Self::Watchers => "watchers",
+            Self::Pagination => "pagination",
+        }
+    }
+}
+
+impl Display for ProtocolFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Iproto protocol version and features of a server, as reported in response
+/// to an `IPROTO_ID` request.
+///
+/// Servers older than 2.10 don't support `IPROTO_ID`, in which case the
+/// version is `0` and none of the features are supported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ProtocolFeatures {
+    /// Iproto protocol version of the server.
+    pub version: u64,
+    pub streams: bool,
+    pub transactions: bool,
+    pub error_extension: bool,
+    pub watchers: bool,
+    pub pagination: bool,
+}
+
+impl ProtocolFeatures {
+    /// Returns `true` if `feature` is supported.
+    #[inline]
+    pub fn supports(&self, feature: ProtocolFeature) -> bool {
+        match feature {
+            ProtocolFeature::Streams => self.streams,
+            ProtocolFeature::Transactions => self.transactions,
+            ProtocolFeature::ErrorExtension => self.error_extension,
+            ProtocolFeature::Watchers => self.watchers,
+            ProtocolFeature::Pagination => self.pagination,
+        }
+    }
+
+    /// Marks `feature` as supported.
+    #[inline]
+    pub fn set(&mut self, feature: ProtocolFeature) {
+        match feature {
+            ProtocolFeature::Streams => self.streams = true,
+            ProtocolFeature::Transactions => self.transactions = true,
+            ProtocolFeature::ErrorExtension => self.error_extension = true,
+            ProtocolFeature::Watchers => self.watchers = true,
+            ProtocolFeature::Pagination => self.pagination = true,
+        }
+    }
+
+    /// Returns an iterator over the supported features.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = ProtocolFeature> + '_ {
+        ProtocolFeature::ALL
+            .iter()
+            .copied()
+            .filter(move |&f| self.supports(f))
+    }
+
+    /// Returns an error if `feature` isn't supported.
+    #[inline]
+    pub fn require(&self, feature: ProtocolFeature) -> Result<(), UnsupportedFeature> {
+        if self.supports(feature) {
+            Ok(())
+        } else {
+            Err(UnsupportedFeature {
+                feature,
+                protocol_version: self.version,
+            })
+        }
+    }
+}
+
+/// A request requires an iproto protocol feature which the server doesn't
+/// support.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{feature} not supported by the server (protocol version {protocol_version})")]
+pub struct UnsupportedFeature {
+    pub feature: ProtocolFeature,
+    pub protocol_version: u64,
+}
+
+/// A notification about an update of a watched key, see [`api::Watch`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub key: String,
+    /// `nil` if the key was never broadcast or was deleted.
+    pub value: rmpv::Value,
+}
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum State {
@@ -79,6 +250,10 @@ pub enum SizeHint {
 pub struct Config {
     /// (user, password)
     pub creds: Option<(String, String)>,
+    /// If set, the connection is encrypted with TLS according to the given
+    /// parameters, see [`tls`](crate::tls).
+    #[cfg(feature = "tls")]
+    pub tls: Option<crate::tls::TlsConfig>,
     // TODO: add buffer limits here
 }
 
@@ -94,10 +269,18 @@ pub struct Protocol {
     sync: SyncIndex,
     // TODO: limit incoming size
     incoming: HashMap<SyncIndex, Result<Vec<u8>, ResponseError>>,
+    /// `IPROTO_EVENT` notifications received but not yet taken.
+    events: Vec<Event>,
     /// (user, password)
     creds: Option<(String, String)>,
 }
 
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Protocol {
     pub fn new() -> Self {
         Self {
@@ -107,6 +290,7 @@ impl Protocol {
             creds: None,
             outgoing: Vec::new(),
             incoming: HashMap::new(),
+            events: Vec::new(),
         }
     }
 
@@ -120,7 +304,7 @@ impl Protocol {
         matches!(self.state, State::Ready)
     }
 
-    /// Data can be sent independently of whether the protocol [`is_ready`].
+    /// Data can be sent independently of whether the protocol [`is_ready`](Self::is_ready).
     /// If the protocol is not ready data will be queued and eventually processed
     /// after auth is done.
     pub fn send_request(&mut self, request: &impl Request) -> Result<SyncIndex, Error> {
@@ -150,6 +334,11 @@ impl Protocol {
         self.incoming.remove(&sync);
     }
 
+    /// Take the `IPROTO_EVENT` notifications received since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn read_size_hint(&self) -> SizeHint {
         if let State::Init = self.state {
             // Greeting message is exactly 128 bytes
@@ -183,7 +372,7 @@ impl Protocol {
                             pass,
                             salt: &salt,
                         },
-                    );
+                    )?;
                 } else {
                     // No auth
                     self.state = State::Ready;
@@ -200,15 +389,23 @@ impl Protocol {
             }
             State::Ready => {
                 let header = codec::decode_header(chunk)?;
-                let response = if header.status_code != 0 {
-                    Err(codec::decode_error(chunk)?)
-                } else {
+                if header.status_code == codec::IProtoType::Event as u32 {
                     let mut buf = Vec::new();
-                    chunk.read_to_end(&mut buf);
-                    Ok(buf)
-                };
-                self.incoming.insert(header.sync, response);
-                Some(header.sync)
+                    chunk.read_to_end(&mut buf)?;
+                    let (key, value) = codec::decode_event(&mut Cursor::new(buf))?;
+                    self.events.push(Event { key, value });
+                    None
+                } else {
+                    let response = if header.status_code != 0 {
+                        Err(codec::decode_error(chunk)?)
+                    } else {
+                        let mut buf = Vec::new();
+                        chunk.read_to_end(&mut buf)?;
+                        Ok(buf)
+                    };
+                    self.incoming.insert(header.sync, response);
+                    Some(header.sync)
+                }
             }
         };
         self.process_pending_data();
@@ -219,7 +416,7 @@ impl Protocol {
         self.outgoing.len()
     }
 
-    pub fn drain_outgoing_data(&mut self, max: Option<usize>) -> Drain<u8> {
+    pub fn drain_outgoing_data(&mut self, max: Option<usize>) -> Drain<'_, u8> {
         let bound = if let Some(max) = max {
             cmp::min(self.ready_outgoing_len(), max)
         } else {
@@ -287,6 +484,61 @@ mod tests {
         assert!(conn.is_ready())
     }
 
+    #[test]
+    fn id_roundtrip() {
+        let mut features = ProtocolFeatures {
+            version: PROTOCOL_VERSION,
+            ..Default::default()
+        };
+        features.set(ProtocolFeature::Streams);
+        features.set(ProtocolFeature::Watchers);
+
+        let mut buf = Vec::new();
+        codec::encode_id(&mut buf, &features).unwrap();
+        let decoded = codec::decode_id(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(decoded, features);
+        assert_eq!(
+            decoded.iter().collect::<Vec<_>>(),
+            [ProtocolFeature::Streams, ProtocolFeature::Watchers]
+        );
+
+        assert!(decoded.require(ProtocolFeature::Streams).is_ok());
+        let err = decoded.require(ProtocolFeature::Transactions).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "transactions not supported by the server (protocol version 3)"
+        );
+    }
+
+    #[test]
+    fn event_received() {
+        let mut conn = Protocol::new();
+        conn.process_incoming(&mut Cursor::new(fake_greeting()));
+
+        let mut event = Vec::new();
+        rmp::encode::write_map_len(&mut event, 2).unwrap();
+        rmp::encode::write_pfix(&mut event, 0x00).unwrap();
+        rmp::encode::write_uint(&mut event, codec::IProtoType::Event as u64).unwrap();
+        rmp::encode::write_pfix(&mut event, 0x01).unwrap();
+        rmp::encode::write_uint(&mut event, 0).unwrap();
+        rmp::encode::write_map_len(&mut event, 2).unwrap();
+        rmp::encode::write_pfix(&mut event, 0x57).unwrap();
+        rmp::encode::write_str(&mut event, "foo").unwrap();
+        rmp::encode::write_pfix(&mut event, 0x58).unwrap();
+        rmp::encode::write_uint(&mut event, 42).unwrap();
+
+        let sync = conn.process_incoming(&mut Cursor::new(event)).unwrap();
+        assert_eq!(sync, None);
+        assert_eq!(
+            conn.take_events(),
+            [Event {
+                key: "foo".into(),
+                value: 42.into(),
+            }]
+        );
+        assert!(conn.take_events().is_empty());
+    }
+
     #[test]
     fn send_bytes_generated() {
         let mut conn = Protocol::new();
@@ -0,0 +1,142 @@
+use crate::error::{Error, TarantoolError, TarantoolErrorCode};
+use crate::schema::space::SpaceMetadata;
+use crate::set_error;
+use crate::space::{CheckConstraint, ForeignKey, Space, SystemSpace};
+use crate::util::{NumOrStr, Value};
+
+/// Create an SQL check constraint for the space.
+/// (for details see [CREATE TABLE: table constraint definition for check constraints](https://www.tarantool.io/en/doc/latest/reference/reference_sql/sql_statements_and_clauses/#table-constraint-definition-for-check-constraints)).
+///
+/// - `space_id` - ID of existing space.
+/// - `ck` - see CheckConstraint struct.
+pub fn create_check_constraint(space_id: u32, ck: &CheckConstraint) -> Result<(), Error> {
+    let sys_ck_constraint: Space = SystemSpace::CkConstraint.into();
+    // Fields: space_id, name, is_deferred, language, code, is_enabled.
+    sys_ck_constraint.insert(&(space_id, &ck.name, false, "SQL", &ck.expr, true))?;
+    Ok(())
+}
+
+/// Drop an existing check constraint.
+///
+/// - `space_id` - ID of existing space.
+/// - `name` - name of existing check constraint.
+pub fn drop_check_constraint(space_id: u32, name: &str) -> Result<(), Error> {
+    let sys_ck_constraint: Space = SystemSpace::CkConstraint.into();
+    match sys_ck_constraint.delete(&(space_id, name))? {
+        Some(_) => Ok(()),
+        None => no_such_constraint(space_id, name),
+    }
+}
+
+/// Create an SQL foreign key constraint for the space.
+/// (for details see [CREATE TABLE: table constraint definition for foreign keys](https://www.tarantool.io/en/doc/latest/reference/reference_sql/sql_statements_and_clauses/#table-constraint-definition-for-foreign-keys)).
+///
+/// - `space_id` - ID of existing child (referencing) space.
+/// - `fk` - see ForeignKey struct.
+pub fn create_foreign_key(space_id: u32, fk: &ForeignKey) -> Result<(), Error> {
+    let child_meta = space_metadata(space_id)?;
+    let child_cols = resolve_field_nos(&child_meta, &fk.fields)?;
+
+    let parent_id = resolve_space_id(&fk.parent_space)?;
+    let parent_meta = space_metadata(parent_id)?;
+    let parent_cols = resolve_field_nos(&parent_meta, &fk.parent_fields)?;
+
+    let sys_fk_constraint: Space = SystemSpace::FkConstraint.into();
+    // Fields: name, child_id, parent_id, is_deferred, match, on_delete,
+    // on_update, child_cols, parent_cols.
+    sys_fk_constraint.insert(&(
+        &fk.name,
+        space_id,
+        parent_id,
+        false,
+        "simple",
+        fk.on_delete,
+        "no_action",
+        child_cols,
+        parent_cols,
+    ))?;
+    Ok(())
+}
+
+/// Drop an existing foreign key constraint.
+///
+/// - `space_id` - ID of existing child (referencing) space.
+/// - `name` - name of existing foreign key constraint.
+pub fn drop_foreign_key(space_id: u32, name: &str) -> Result<(), Error> {
+    let sys_fk_constraint: Space = SystemSpace::FkConstraint.into();
+    match sys_fk_constraint.delete(&(name, space_id))? {
+        Some(_) => Ok(()),
+        None => no_such_constraint(space_id, name),
+    }
+}
+
+fn no_such_constraint(space_id: u32, name: &str) -> Result<(), Error> {
+    set_error!(
+        TarantoolErrorCode::NoSuchConstraint,
+        "Constraint '{}' does not exist in space '{}'",
+        name,
+        space_id
+    );
+    Err(TarantoolError::last().into())
+}
+
+fn resolve_space_id(space: &NumOrStr) -> Result<u32, Error> {
+    match space {
+        NumOrStr::Num(id) => Ok(*id),
+        NumOrStr::Str(name) => match Space::find(name) {
+            Some(space) => Ok(space.id()),
+            None => {
+                set_error!(
+                    TarantoolErrorCode::NoSuchSpace,
+                    "Space '{}' does not exist",
+                    name
+                );
+                Err(TarantoolError::last().into())
+            }
+        },
+    }
+}
+
+fn space_metadata(space_id: u32) -> Result<SpaceMetadata<'static>, Error> {
+    let sys_space: Space = SystemSpace::Space.into();
+    match sys_space.get(&(space_id,))? {
+        Some(tuple) => tuple.decode(),
+        None => {
+            set_error!(
+                TarantoolErrorCode::NoSuchSpace,
+                "Space '{}' does not exist",
+                space_id
+            );
+            Err(TarantoolError::last().into())
+        }
+    }
+}
+
+fn resolve_field_nos(meta: &SpaceMetadata, fields: &[NumOrStr]) -> Result<Vec<u32>, Error> {
+    let mut res = Vec::with_capacity(fields.len());
+    for field in fields {
+        let field_no = match field {
+            NumOrStr::Num(field_no) => *field_no,
+            NumOrStr::Str(name) => {
+                let pos = meta.format.iter().position(|f| match f.get("name") {
+                    Some(Value::Str(n)) => n == name,
+                    _ => false,
+                });
+                match pos {
+                    Some(pos) => pos as u32,
+                    None => {
+                        set_error!(
+                            TarantoolErrorCode::NoSuchFieldNameInSpace,
+                            "Field '{}' was not found in space '{}' format",
+                            name,
+                            meta.name
+                        );
+                        return Err(TarantoolError::last().into());
+                    }
+                }
+            }
+        };
+        res.push(field_no);
+    }
+    Ok(res)
+}
@@ -1,5 +1,6 @@
 #![cfg(any(feature = "schema", doc))]
 
+pub mod constraint;
 pub mod index;
 pub mod sequence;
 pub mod space;
@@ -7,8 +8,46 @@ pub mod space;
 use crate::error::Error;
 use crate::index::IteratorType;
 use crate::space::{Space, SystemSpace};
+use crate::trigger::TriggerGuard;
 use crate::tuple::Tuple;
 
+/// Returns the current schema version.
+///
+/// The version is incremented by tarantool on every change of the data schema
+/// (creation, alteration or removal of spaces, indexes, users, etc.), so it can
+/// be used to check whether any data derived from the schema is up to date.
+pub fn version() -> Result<u64, Error> {
+    let version = crate::lua_state().eval("return box.internal.schema_version()")?;
+    Ok(version)
+}
+
+/// Set a callback to be called every time a space or an index is created,
+/// altered or dropped.
+///
+/// The callback is called from `on_replace` triggers of `_space` and `_index`
+/// system spaces, i.e. from within the transaction which modifies the schema,
+/// so [`version`] isn't updated yet when it's called. If the system spaces
+/// aren't loaded yet (e.g. `box.cfg` wasn't called), the triggers are set from
+/// `box.ctl.on_schema_init`.
+///
+/// The callback is called until the returned guard is dropped, use
+/// [`TriggerGuard::forget`] to keep it for the rest of the program.
+///
+/// Note that [`Space::find_cached`] & [`Space::index_cached`] caches are
+/// cleared automatically, there's no need to call [`space::clear_cache`]
+/// from the callback.
+///
+/// [`Space::find_cached`]: crate::space::Space::find_cached
+/// [`Space::index_cached`]: crate::space::Space::index_cached
+/// [`space::clear_cache`]: crate::space::clear_cache
+pub fn on_change<F>(cb: F) -> Result<TriggerGuard, Error>
+where
+    F: FnMut() + 'static,
+{
+    let guard = crate::space::on_schema_replace(cb)?;
+    Ok(guard)
+}
+
 fn resolve_user_or_role(user: &str) -> Result<Option<u32>, Error> {
     let space_vuser: Space = SystemSpace::VUser.into();
     let name_idx = space_vuser.index("name").unwrap();
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::error::{Error, TarantoolError, TarantoolErrorCode};
 use crate::index::IteratorType;
 use crate::schema;
+use crate::schema::constraint as schema_constraint;
 use crate::schema::sequence as schema_seq;
 use crate::session;
 use crate::set_error;
@@ -86,9 +87,26 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
         format,
     })?;
 
+    // Create constraints. In case of failure the space is removed, so that a
+    // partially created space doesn't remain in the schema.
+    if let Err(e) = create_constraints(id, opts) {
+        let _ = drop_space(id);
+        return Err(e);
+    }
+
     Ok(Space::find(name).unwrap())
 }
 
+fn create_constraints(space_id: u32, opts: &SpaceCreateOptions) -> Result<(), Error> {
+    for ck in &opts.check_constraints {
+        schema_constraint::create_check_constraint(space_id, ck)?;
+    }
+    for fk in &opts.foreign_keys {
+        schema_constraint::create_foreign_key(space_id, fk)?;
+    }
+    Ok(())
+}
+
 /// SpaceMetadata is tuple, holding space metadata in system `_space` space.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SpaceMetadata<'a> {
@@ -171,7 +189,7 @@ pub fn drop_space(space_id: u32) -> Result<(), Error> {
         .select(IteratorType::Eq, &(space_id,))?
         .collect::<Vec<Tuple>>()
     {
-        let name = t.field::<String>(2)?.unwrap();
+        let name = t.field::<String>(1)?.unwrap();
         sys_ck_constraint.delete(&(space_id, name))?;
     }
 
@@ -5,15 +5,33 @@
 //!
 //! See also:
 //! - [Lua reference: Submodule box.session](https://www.tarantool.io/en/doc/1.10/reference/reference_lua/box_session/)
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use tlua::AsLua as _;
 
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
 use crate::error::{Error, TarantoolError};
+use crate::ffi::helper::has_dyn_symbol;
 use crate::ffi::lua as ffi_lua;
-use crate::ffi::tarantool::luaT_call;
+use crate::ffi::tarantool::{self as ffi, luaT_call};
+use crate::tlua;
+use crate::trigger::TriggerGuard;
 
 /// Get the user ID of the current user.
 pub fn uid() -> Result<isize, Error> {
+    if has_c_api() {
+        let mut uid = 0;
+        if unsafe { ffi::box_session_user_id(&mut uid) } < 0 {
+            return Err(TarantoolError::last().into());
+        }
+        return Ok(uid as _);
+    }
     unsafe {
         // Create new stack (just in case - in order no to mess things
         // in current stack).
@@ -40,6 +58,9 @@ pub fn uid() -> Result<isize, Error> {
 
 /// Get the effective user ID of the current user.
 pub fn euid() -> Result<isize, Error> {
+    if has_c_api() {
+        return Ok(unsafe { ffi::box_effective_user_id() } as _);
+    }
     unsafe {
         // Create new stack (just in case - in order no to mess things
         // in current stack).
@@ -63,3 +84,306 @@ pub fn euid() -> Result<isize, Error> {
         // No need to clean euid_state. It will be gc'ed.
     }
 }
+
+/// Get the ID of the current session.
+pub fn id() -> Result<u64, Error> {
+    if has_c_api() {
+        return Ok(unsafe { ffi::box_session_id() });
+    }
+    let id = crate::lua_state().eval("return box.session.id()")?;
+    Ok(id)
+}
+
+/// Checks if the session C api is supported by the current tarantool
+/// executable (since 2.11), otherwise the lua api is used.
+fn has_c_api() -> bool {
+    static HAS_C_API: Lazy<bool> =
+        Lazy::new(|| unsafe { has_dyn_symbol(crate::c_str!("box_session_id")) });
+    *HAS_C_API
+}
+
+/// Check whether a session with the given `id` exists.
+pub fn exists(id: u64) -> Result<bool, Error> {
+    let res = crate::lua_state()
+        .eval_with("return box.session.exists(...)", id)
+        .map_err(tlua::LuaError::from)?;
+    Ok(res)
+}
+
+/// Get the name of the current user.
+pub fn user() -> Result<String, Error> {
+    let user = crate::lua_state().eval("return box.session.user()")?;
+    Ok(user)
+}
+
+/// Get the host and port of the peer of the current session, e.g.
+/// `"127.0.0.1:57444"`.
+///
+/// Returns `None` if the session isn't associated with a connection (e.g. for
+/// background fibers).
+pub fn peer() -> Result<Option<String>, Error> {
+    let peer = crate::lua_state().eval("return box.session.peer()")?;
+    Ok(peer)
+}
+
+/// Send an out-of-band message to the client of the current session, e.g.
+/// to report the progress of a long running stored procedure. The message is
+/// delivered before the response to the request being processed.
+///
+/// This is the equivalent of `box.session.push(value)`.
+pub fn push<T>(value: &T) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    let data = rmp_serde::to_vec_named(value)?;
+    let data_ptr = data.as_ptr() as *const c_char;
+    if unsafe { ffi::box_session_push(data_ptr, data_ptr.add(data.len())) } < 0 {
+        return Err(TarantoolError::last().into());
+    }
+    Ok(())
+}
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Type of a session, see [`type`](fn@r#type).
+    pub enum SessionType {
+        /// Connected via the binary protocol, e.g. with `net.box`.
+        Binary     = "binary",
+        /// Connected via the admin console.
+        Console    = "console",
+        /// Replication session on the master side.
+        Repl       = "repl",
+        /// Replication session on the replica side.
+        Applier    = "applier",
+        /// Not associated with a connection, e.g. a fiber started by the
+        /// application or the initial session.
+        Background = "background",
+    }
+}
+
+/// Get the type of the current session.
+pub fn r#type() -> Result<SessionType, Error> {
+    let ty = crate::lua_state().eval("return box.session.type()")?;
+    Ok(ty)
+}
+
+/// Call `f` on behalf of the user with the given name, i.e. the effective user
+/// is changed to `user` during the call and is restored afterwards.
+///
+/// Returns the result of `f`. Returns an error if the user doesn't exist or if
+/// the current user isn't allowed to change the effective user.
+pub fn su<F, R>(user: &str, f: F) -> Result<R, Error>
+where
+    F: FnOnce() -> R,
+{
+    let mut f = Some(f);
+    let mut res = None;
+    let mut call = || {
+        if let Some(f) = f.take() {
+            // The panic is resumed after `box.session.su` returns, so that it
+            // doesn't unwind through the lua frames.
+            res = Some(panic::catch_unwind(AssertUnwindSafe(f)));
+        }
+    };
+    // The closure borrows the locals, so it can't be passed to lua directly.
+    // Instead a pointer to it is erased together with its type and only a
+    // monomorphized trampoline knows how to call it. This is sound because:
+    // - the lua function only holds a pointer and a fn pointer, so nothing
+    //   is dropped when it's garbage collected after `su` returns,
+    // - the lua function is only reachable from the chunk below, and
+    //   `box.session.su` calls it synchronously without storing it, so it
+    //   can't be called after `call` goes out of scope.
+    let trampoline = trampoline_for(&call);
+    let data = &mut call as *mut _ as *mut c_void;
+    crate::lua_state()
+        .exec_with(
+            "local user, f = ...
+            box.session.su(user, f)",
+            (user, tlua::function0(move || unsafe { trampoline(data) })),
+        )
+        .map_err(tlua::LuaError::from)?;
+    return match res.expect("function must have been called") {
+        Ok(res) => Ok(res),
+        Err(e) => panic::resume_unwind(e),
+    };
+
+    fn trampoline_for<C: FnMut()>(_: &C) -> unsafe fn(*mut c_void) {
+        unsafe fn trampoline<C: FnMut()>(data: *mut c_void) {
+            (*data.cast::<C>())()
+        }
+        trampoline::<C>
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Storage
+////////////////////////////////////////////////////////////////////////////////
+
+/// Per session storage of rust values. Unlike `box.session.storage` the values
+/// are identified by their types, i.e. there can be only one value of a given
+/// type per session.
+///
+/// Values are dropped when the session is disconnected.
+#[derive(Default)]
+struct Storage {
+    values: RefCell<HashMap<(u64, TypeId), Box<dyn Any>>>,
+    has_trigger: Cell<bool>,
+}
+
+impl Storage {
+    /// Make sure the session's values are dropped on disconnect.
+    fn ensure_trigger(&self) -> Result<(), Error> {
+        if self.has_trigger.get() {
+            return Ok(());
+        }
+        let guard = on_disconnect(|| {
+            if let Ok(id) = id() {
+                // Values are dropped after the borrow is released, so that
+                // their destructors can access the storage.
+                let _removed: Vec<_> = STORAGE.with(|s| {
+                    let mut values = s.values.borrow_mut();
+                    let keys: Vec<_> = values.keys().filter(|k| k.0 == id).copied().collect();
+                    keys.iter().filter_map(|k| values.remove(k)).collect()
+                });
+            }
+        })?;
+        guard.forget();
+        self.has_trigger.set(true);
+        Ok(())
+    }
+}
+
+thread_local! {
+    static STORAGE: Storage = Storage::default();
+}
+
+/// Store a value of type `T` in the current session's storage. Returns the
+/// previously stored value of this type if there was one.
+///
+/// The value is dropped when the session is disconnected.
+pub fn storage_set<T: 'static>(value: T) -> Result<Option<T>, Error> {
+    let id = id()?;
+    STORAGE.with(|s| -> Result<_, Error> {
+        s.ensure_trigger()?;
+        let old = s
+            .values
+            .borrow_mut()
+            .insert((id, TypeId::of::<T>()), Box::new(value));
+        Ok(old.map(|v| *v.downcast().expect("type id must match")))
+    })
+}
+
+/// Call `f` with a mutable reference to the value of type `T` from the
+/// current session's storage or with `None` if there's no such value.
+///
+/// **NOTE** `f` must not access session storage.
+pub fn storage_with<T, F, R>(f: F) -> Result<R, Error>
+where
+    T: 'static,
+    F: FnOnce(Option<&mut T>) -> R,
+{
+    let id = id()?;
+    STORAGE.with(|s| {
+        let mut values = s.values.borrow_mut();
+        let value = values
+            .get_mut(&(id, TypeId::of::<T>()))
+            .map(|v| v.downcast_mut().expect("type id must match"));
+        Ok(f(value))
+    })
+}
+
+/// Get a copy of the value of type `T` from the current session's storage.
+pub fn storage_get<T: Clone + 'static>() -> Result<Option<T>, Error> {
+    storage_with(|v: Option<&mut T>| v.cloned())
+}
+
+/// Remove the value of type `T` from the current session's storage and return
+/// it.
+pub fn storage_take<T: 'static>() -> Result<Option<T>, Error> {
+    let id = id()?;
+    STORAGE.with(|s| {
+        let old = s.values.borrow_mut().remove(&(id, TypeId::of::<T>()));
+        Ok(old.map(|v| *v.downcast().expect("type id must match")))
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Triggers
+////////////////////////////////////////////////////////////////////////////////
+
+/// Set a callback to be called when a new session is created (e.g. a client
+/// connects).
+///
+/// The callback is executed in the context of the new session, so functions
+/// like [`id`], [`user`] or [`peer`] return the values for the new session.
+///
+/// The trigger is removed when the returned guard is dropped. If the callback
+/// panics, the panic is logged and doesn't propagate to tarantool.
+pub fn on_connect<F>(mut cb: F) -> Result<TriggerGuard, Error>
+where
+    F: FnMut() + 'static,
+{
+    set_trigger(
+        "box.session.on_connect",
+        tlua::function0(move || catch_panic("on_connect", &mut cb)),
+    )
+}
+
+/// Set a callback to be called when a session is closed (e.g. a client
+/// disconnects).
+///
+/// The callback is executed in the context of the session being closed.
+///
+/// The trigger is removed when the returned guard is dropped. If the callback
+/// panics, the panic is logged and doesn't propagate to tarantool.
+pub fn on_disconnect<F>(mut cb: F) -> Result<TriggerGuard, Error>
+where
+    F: FnMut() + 'static,
+{
+    set_trigger(
+        "box.session.on_disconnect",
+        tlua::function0(move || catch_panic("on_disconnect", &mut cb)),
+    )
+}
+
+/// Set a callback to be called on authentication attempts. The callback
+/// receives the name of the user and `true` if the authentication was
+/// successful.
+///
+/// The trigger is removed when the returned guard is dropped. If the callback
+/// panics, the panic is logged and doesn't propagate to tarantool.
+pub fn on_auth<F>(mut cb: F) -> Result<TriggerGuard, Error>
+where
+    F: FnMut(&str, bool) + 'static,
+{
+    set_trigger(
+        "box.session.on_auth",
+        tlua::function2(move |user: String, success: bool| {
+            catch_panic("on_auth", || cb(&user, success))
+        }),
+    )
+}
+
+fn set_trigger<F>(setter: &str, cb: F) -> Result<TriggerGuard, Error>
+where
+    F: tlua::PushOneInto<tlua::LuaState>,
+    F::Err: Into<tlua::Void>,
+{
+    let guard = TriggerGuard::from_lua(
+        &format!(
+            "local trigger = ...
+            {setter}(trigger)
+            return function() {setter}(nil, trigger) end"
+        ),
+        cb,
+    )?;
+    Ok(guard)
+}
+
+/// Panics must not unwind into tarantool through the lua frames, so they are
+/// caught and logged.
+fn catch_panic(trigger: &str, f: impl FnOnce()) {
+    if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+        log::error!("Session {trigger} trigger panicked");
+    }
+}
@@ -6,7 +6,7 @@
 //! See also:
 //! - [Lua reference: Submodule box.space](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/)
 //! - [C API reference: Module box](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/box/)
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::os::raw::c_char;
 
@@ -17,11 +17,13 @@ use serde_json::{Map, Value};
 
 use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::index::{Index, IndexIterator, IteratorType};
+use crate::index::{Index, IndexApi, IndexIterator, IteratorType};
 #[cfg(feature = "schema")]
 use crate::schema::space::SpaceMetadata;
+use crate::trigger::TriggerGuard;
 use crate::tuple::{Encode, ToTupleBuffer, Tuple, TupleBuffer};
 use crate::tuple_from_box_api;
+use crate::util::NumOrStr;
 
 /// End of the reserved range of system spaces.
 pub const SYSTEM_ID_MAX: u32 = 511;
@@ -121,6 +123,8 @@ pub struct SpaceCreateOptions {
     pub is_temporary: bool,
     pub is_sync: bool,
     pub format: Option<Vec<Field>>,
+    pub check_constraints: Vec<CheckConstraint>,
+    pub foreign_keys: Vec<ForeignKey>,
 }
 
 impl Default for SpaceCreateOptions {
@@ -135,6 +139,8 @@ impl Default for SpaceCreateOptions {
             is_temporary: false,
             is_sync: false,
             format: None,
+            check_constraints: Vec::new(),
+            foreign_keys: Vec::new(),
         }
     }
 }
@@ -294,6 +300,77 @@ impl IsNullable {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// CheckConstraint
+////////////////////////////////////////////////////////////////////////////////
+
+/// Definition of an SQL check constraint stored in the `_ck_constraint` system
+/// space.
+///
+/// The constraint is checked on every modification of the space, a tuple for
+/// which `expr` evaluates to `false` is rejected.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckConstraint {
+    pub name: String,
+    /// SQL expression, e.g. `"balance" >= 0`. Note that unquoted identifiers
+    /// are converted to upper case by the SQL parser.
+    pub expr: String,
+}
+
+impl CheckConstraint {
+    #[inline(always)]
+    pub fn new(name: impl Into<String>, expr: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            expr: expr.into(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ForeignKey
+////////////////////////////////////////////////////////////////////////////////
+
+/// Definition of an SQL foreign key constraint stored in the `_fk_constraint`
+/// system space.
+///
+/// Fields can be specified either by names or by zero based numbers. The
+/// parent space must have a unique index consisting exactly of
+/// `parent_fields`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ForeignKey {
+    pub name: String,
+    /// Referencing fields of the child space.
+    pub fields: Vec<NumOrStr>,
+    /// Name or id of the referenced space.
+    pub parent_space: NumOrStr,
+    /// Referenced fields of the parent space.
+    pub parent_fields: Vec<NumOrStr>,
+    /// Action performed when a referenced tuple is deleted from the parent
+    /// space.
+    pub on_delete: ForeignKeyAction,
+}
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Action performed on the child space when a referenced tuple in the
+    /// parent space is deleted.
+    pub enum ForeignKeyAction {
+        NoAction   = "no_action",
+        Restrict   = "restrict",
+        Cascade    = "cascade",
+        SetNull    = "set_null",
+        SetDefault = "set_default",
+    }
+}
+
+impl Default for ForeignKeyAction {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::NoAction
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // ...
 ////////////////////////////////////////////////////////////////////////////////
@@ -337,6 +414,7 @@ impl Encode for Privilege {}
 struct SpaceCache {
     spaces: RefCell<HashMap<String, Space>>,
     indexes: RefCell<HashMap<(u32, String), Index>>,
+    has_trigger: Cell<bool>,
 }
 
 impl SpaceCache {
@@ -344,6 +422,20 @@ impl SpaceCache {
         Self {
             spaces: RefCell::new(HashMap::new()),
             indexes: RefCell::new(HashMap::new()),
+            has_trigger: Cell::new(false),
+        }
+    }
+
+    /// Make sure the cache is cleared whenever `_space` or `_index` is
+    /// modified. The trigger is installed once per thread, the first time the
+    /// cache is used after `box.cfg`.
+    fn ensure_trigger(&self) {
+        if self.has_trigger.get() {
+            return;
+        }
+        if let Ok(guard) = on_schema_replace(clear_cache) {
+            guard.forget();
+            self.has_trigger.set(true);
         }
     }
 
@@ -353,6 +445,7 @@ impl SpaceCache {
     }
 
     fn space(&self, name: &str) -> Option<Space> {
+        self.ensure_trigger();
         let mut cache = self.spaces.borrow_mut();
         cache.get(name).cloned().or_else(|| {
             Space::find(name).map(|space| {
@@ -363,6 +456,7 @@ impl SpaceCache {
     }
 
     fn index(&self, space: &Space, name: &str) -> Option<Index> {
+        self.ensure_trigger();
         let mut cache = self.indexes.borrow_mut();
         cache
             .get(&(space.id, name.to_string()))
@@ -387,6 +481,42 @@ pub fn clear_cache() {
     SPACE_CACHE.with(SpaceCache::clear)
 }
 
+/// Register `cb` as an `on_replace` trigger for `_space` and `_index` system
+/// spaces. If the system spaces aren't loaded yet the triggers are set from
+/// within `box.ctl.on_schema_init`. The triggers are removed when the returned
+/// guard is dropped.
+pub(crate) fn on_schema_replace<F>(cb: F) -> Result<TriggerGuard, crate::tlua::LuaError>
+where
+    F: FnMut() + 'static,
+{
+    TriggerGuard::from_lua(
+        "local cb = ...
+        local trigger = function() cb() end
+        local on_replace_set, on_init_set = false, false
+        local function set_triggers()
+            box.space._space:on_replace(trigger)
+            box.space._index:on_replace(trigger)
+            on_replace_set = true
+        end
+        if box.space ~= nil and box.space._space ~= nil then
+            set_triggers()
+        else
+            box.ctl.on_schema_init(set_triggers)
+            on_init_set = true
+        end
+        return function()
+            if on_init_set then
+                box.ctl.on_schema_init(nil, set_triggers)
+            end
+            if on_replace_set then
+                box.space._space:on_replace(nil, trigger)
+                box.space._index:on_replace(nil, trigger)
+            end
+        end",
+        crate::tlua::function0(cb),
+    )
+}
+
 #[derive(Clone, Debug)]
 pub struct Space {
     id: u32,
@@ -443,16 +573,13 @@ impl Space {
     /// it was never called for target space.
     /// - `name` - space name
     ///
-    /// **NOTE** the cache can become invalid for a number of reasons. If an
-    /// operation with a space returned from this function results in a
-    /// [`TarantoolError`] with code [`NoSuchSpace`], try calling [`clear_cache`]
-    /// before trying to find the space again.
+    /// The cache is cleared automatically whenever `_space` or `_index` system
+    /// spaces are modified (e.g. a space is created, dropped or renamed).
+    /// [`clear_cache`] can still be used to reset it manually.
     ///
     /// Returns:
     /// - `None` if not found
     /// - `Some(space)` otherwise
-    ///
-    /// [`NoSuchSpace`]: crate::error::TarantoolErrorCode::NoSuchSpace
     pub fn find_cached(name: &str) -> Option<Self> {
         SPACE_CACHE.with(|cache| cache.space(name))
     }
@@ -508,17 +635,12 @@ impl Space {
     /// This function performs SELECT request to `_vindex` system space.
     /// - `name` - index name
     ///
-    /// **NOTE** the cache can become invalid for a number of reasons. If an
-    /// operation with an index returned from this function results in a
-    /// [`TarantoolError`] with code [`NoSuchSpace`] or [`NoSuchIndexID`], try
-    /// calling [`clear_cache`] before trying to get the index again.
+    /// The cache is cleared automatically whenever `_space` or `_index` system
+    /// spaces are modified. See [`Space::find_cached`] for details.
     ///
     /// Returns:
     /// - `None` if not found
     /// - `Some(index)` otherwise
-    ///
-    /// [`NoSuchSpace`]: crate::error::TarantoolErrorCode::NoSuchSpace
-    /// [`NoSuchIndexID`]: crate::error::TarantoolErrorCode::NoSuchIndexID
     pub fn index_cached(&self, name: &str) -> Option<Index> {
         SPACE_CACHE.with(|cache| cache.index(self, name))
     }
@@ -794,6 +916,145 @@ impl Space {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// SpaceApi
+////////////////////////////////////////////////////////////////////////////////
+
+/// Data manipulation methods common for local and remote spaces.
+///
+/// Allows writing code which is generic over where the data is stored, e.g.
+/// test the code with a local [`Space`] and run it against a
+/// [`net_box::RemoteSpace`](crate::net_box::RemoteSpace):
+///
+/// ```no_run
+/// use tarantool::space::SpaceApi;
+///
+/// fn rename<S: SpaceApi>(space: &S, id: u32, name: &str) -> tarantool::Result<()> {
+///     space.update(&(id,), &[("=", 1, name)])?;
+///     Ok(())
+/// }
+/// ```
+///
+/// The remote implementations use the default request options, use the
+/// inherent methods of the remote types to specify a timeout.
+///
+/// See also [`IndexApi`].
+pub trait SpaceApi {
+    /// The type of the space's indexes.
+    type Index: IndexApi;
+
+    /// Find the index by `name`, see [`Space::index`].
+    fn index(&self, name: &str) -> Result<Option<Self::Index>, Error>;
+
+    /// Returns the index with id = 0.
+    fn primary_key(&self) -> Self::Index;
+
+    /// Search for a tuple by the primary `key`, see [`Space::get`].
+    #[inline(always)]
+    fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().get(key)
+    }
+
+    /// Search for the tuples matching the primary `key`, see [`Space::select`].
+    #[inline(always)]
+    fn select<K>(
+        &self,
+        iterator_type: IteratorType,
+        key: &K,
+    ) -> Result<<Self::Index as IndexApi>::Iterator, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().select(iterator_type, key)
+    }
+
+    /// Insert a tuple into the space, see [`Space::insert`].
+    ///
+    /// Returns the inserted tuple.
+    fn insert<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer;
+
+    /// Insert a tuple into the space or replace the existing one, see
+    /// [`Space::replace`].
+    ///
+    /// Returns the inserted tuple.
+    fn replace<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer;
+
+    /// Update the tuple identified by the primary `key`, see [`Space::update`].
+    #[inline(always)]
+    fn update<K, Op>(&self, key: &K, ops: &[Op]) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.primary_key().update(key, ops)
+    }
+
+    /// Update or insert a tuple, see [`Space::upsert`].
+    #[inline(always)]
+    fn upsert<T, Op>(&self, value: &T, ops: &[Op]) -> Result<(), Error>
+    where
+        T: ToTupleBuffer,
+        Op: Encode,
+    {
+        self.primary_key().upsert(value, ops)
+    }
+
+    /// Delete the tuple identified by the primary `key`, see [`Space::delete`].
+    #[inline(always)]
+    fn delete<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().delete(key)
+    }
+
+    /// Count the tuples matching the primary `key`, see [`Space::count`].
+    #[inline(always)]
+    fn count<K>(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error>
+    where
+        K: ToTupleBuffer,
+    {
+        self.primary_key().count(iterator_type, key)
+    }
+}
+
+impl SpaceApi for Space {
+    type Index = Index;
+
+    #[inline(always)]
+    fn index(&self, name: &str) -> Result<Option<Index>, Error> {
+        Ok(Space::index(self, name))
+    }
+
+    #[inline(always)]
+    fn primary_key(&self) -> Index {
+        Space::primary_key(self)
+    }
+
+    #[inline(always)]
+    fn insert<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        Space::insert(self, value).map(Some)
+    }
+
+    #[inline(always)]
+    fn replace<T>(&self, value: &T) -> Result<Option<Tuple>, Error>
+    where
+        T: ToTupleBuffer,
+    {
+        Space::replace(self, value).map(Some)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Builder
 ////////////////////////////////////////////////////////////////////////////////
@@ -879,6 +1140,72 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Add an SQL check constraint to the space.
+    ///
+    /// The constraint is created right after the space itself.
+    ///
+    /// ```no_run
+    /// use tarantool::space::{Space, Field};
+    ///
+    /// let space = Space::builder("accounts")
+    ///     .field(Field::unsigned("id"))
+    ///     .field(Field::integer("balance"))
+    ///     .check("non_negative_balance", r#""balance" >= 0"#)
+    ///     .create();
+    /// ```
+    #[inline]
+    pub fn check(mut self, name: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.opts
+            .check_constraints
+            .push(CheckConstraint::new(name, expr));
+        self
+    }
+
+    /// Add an SQL foreign key constraint to the space.
+    ///
+    /// - `fields` - names or numbers of the referencing fields of this space
+    /// - `parent_space` - name or id of the referenced space
+    /// - `parent_fields` - names or numbers of the referenced fields, which
+    ///   must be covered by a unique index of the parent space
+    /// - `on_delete` - action to perform when a referenced tuple is deleted
+    ///
+    /// The constraint is created right after the space itself, so the parent
+    /// space must already exist.
+    ///
+    /// ```no_run
+    /// use tarantool::space::{Space, Field, ForeignKeyAction};
+    ///
+    /// let space = Space::builder("orders")
+    ///     .field(Field::unsigned("id"))
+    ///     .field(Field::unsigned("customer_id"))
+    ///     .foreign_key(
+    ///         "fk_order_customer",
+    ///         ["customer_id"],
+    ///         "customers",
+    ///         ["id"],
+    ///         ForeignKeyAction::Cascade,
+    ///     )
+    ///     .create();
+    /// ```
+    #[inline]
+    pub fn foreign_key(
+        mut self,
+        name: impl Into<String>,
+        fields: impl IntoIterator<Item = impl Into<NumOrStr>>,
+        parent_space: impl Into<NumOrStr>,
+        parent_fields: impl IntoIterator<Item = impl Into<NumOrStr>>,
+        on_delete: ForeignKeyAction,
+    ) -> Self {
+        self.opts.foreign_keys.push(ForeignKey {
+            name: name.into(),
+            fields: fields.into_iter().map(Into::into).collect(),
+            parent_space: parent_space.into(),
+            parent_fields: parent_fields.into_iter().map(Into::into).collect(),
+            on_delete,
+        });
+        self
+    }
+
     #[cfg(feature = "schema")]
     pub fn create(self) -> crate::Result<Space> {
         crate::schema::space::create_space(self.name, &self.opts)
@@ -107,3 +107,48 @@ pub fn collect_tester() -> Vec<TestDescAndFn> {
 #[cfg(feature = "internal_test")]
 /// The default port where tarantool listens in tests
 pub const TARANTOOL_LISTEN: u16 = 3301;
+
+#[cfg(feature = "internal_test")]
+pub mod util {
+    use std::io;
+    use std::net::{Shutdown, TcpStream};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Creates a unix domain socket, whose connections are forwarded to the
+    /// tarantool instance listening on `port`, and returns its path. Allows
+    /// testing the unix socket connections without reconfiguring the instance.
+    pub fn unix_socket_proxy(port: u16) -> PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tarantool-test-{}-{}.sock",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed),
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            for client in listener.incoming() {
+                let client = client.unwrap();
+                let server = match TcpStream::connect(("localhost", port)) {
+                    Ok(server) => server,
+                    Err(_) => continue,
+                };
+                let mut client_rx = client.try_clone().unwrap();
+                let mut server_tx = server.try_clone().unwrap();
+                thread::spawn(move || {
+                    let _ = io::copy(&mut client_rx, &mut server_tx);
+                    let _ = server_tx.shutdown(Shutdown::Both);
+                });
+                let (mut server_rx, mut client_tx) = (server, client);
+                thread::spawn(move || {
+                    let _ = io::copy(&mut server_rx, &mut client_tx);
+                    let _ = client_tx.shutdown(Shutdown::Both);
+                });
+            }
+        });
+        path
+    }
+}
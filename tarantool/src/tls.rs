@@ -0,0 +1,818 @@
+//! TLS encrypted connections.
+//!
+//! Requires the `tls` feature, which links the crate with the system OpenSSL
+//! library.
+//!
+//! A [`TlsStream`] wraps a non-blocking socket based stream, e.g.
+//! [`CoIOStream`] or the async `TcpStream` of the network client, and
+//! provides both the [`Read`]/[`Write`] interface, which makes the fiber
+//! yield while the socket is not ready, and the
+//! [`AsyncRead`]/[`AsyncWrite`] interface for the
+//! [`fiber::block_on`](crate::fiber::block_on) async runtime.
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use tarantool::coio::CoIOStream;
+//! use tarantool::tls::{TlsConfig, TlsConnector};
+//!
+//! let connector = TlsConnector::new(&TlsConfig {
+//!     ca_file: Some("/etc/ssl/ca.pem".into()),
+//!     ..Default::default()
+//! })
+//! .unwrap();
+//! let stream = CoIOStream::connect("example.org:3301").unwrap();
+//! let mut stream = connector.connect("example.org", stream).unwrap();
+//! stream.write_all(b"hello").unwrap();
+//! ```
+#![cfg(feature = "tls")]
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::{AsyncRead, AsyncWrite};
+use openssl::error::ErrorStack;
+use openssl::ssl::{
+    self, ErrorCode, HandshakeError, MidHandshakeSslStream, SslConnector, SslFiletype, SslMethod,
+    SslStream, SslVerifyMode,
+};
+
+use crate::clock::INFINITY;
+use crate::coio::{coio_wait, CoIOStream};
+use crate::ffi::tarantool::CoIOFlags;
+use crate::fiber::r#async::context::ContextExt;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to configure tls: {0}")]
+    Setup(#[from] ErrorStack),
+    #[error("tls handshake failed: {0}")]
+    Handshake(ssl::Error),
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl<S> From<HandshakeError<S>> for Error {
+    fn from(error: HandshakeError<S>) -> Self {
+        match error {
+            HandshakeError::SetupFailure(e) => Self::Setup(e),
+            HandshakeError::Failure(mid) | HandshakeError::WouldBlock(mid) => {
+                Self::Handshake(mid.into_error())
+            }
+        }
+    }
+}
+
+/// TLS parameters of a client connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// Name of the server sent in the SNI extension and checked against the
+    /// server's certificate. If `None`, the host the connection is made to is
+    /// used, which may also be an ip address.
+    pub server_name: Option<String>,
+
+    /// Path to a PEM file with the CA certificates to verify the server's
+    /// certificate with. If `None`, the system default CA certificates are
+    /// used.
+    pub ca_file: Option<PathBuf>,
+
+    /// Path to a PEM file with the client certificate chain, used if the
+    /// server requires the clients to authenticate. Requires
+    /// [`key_file`](Self::key_file).
+    pub cert_file: Option<PathBuf>,
+
+    /// Path to a PEM file with the private key of the client certificate.
+    pub key_file: Option<PathBuf>,
+
+    /// Disables the verification of the server's certificate and name.
+    ///
+    /// **Dangerous**, the connection is then open to man-in-the-middle
+    /// attacks. Should only be used for testing.
+    pub accept_invalid_certs: bool,
+}
+
+/// Performs the client side of TLS handshakes according to a [`TlsConfig`].
+///
+/// Loads the certificates once, so it's cheaper to reuse the connector for
+/// multiple connections.
+#[derive(Clone)]
+pub struct TlsConnector {
+    inner: SslConnector,
+    server_name: Option<String>,
+    verify: bool,
+}
+
+impl TlsConnector {
+    /// Creates a connector, loading the certificates and the key specified
+    /// in the `config`.
+    pub fn new(config: &TlsConfig) -> Result<Self, Error> {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+        if let Some(ca_file) = &config.ca_file {
+            builder.set_ca_file(ca_file)?;
+        }
+        if let Some(cert_file) = &config.cert_file {
+            builder.set_certificate_chain_file(cert_file)?;
+        }
+        if let Some(key_file) = &config.key_file {
+            builder.set_private_key_file(key_file, SslFiletype::PEM)?;
+            builder.check_private_key()?;
+        }
+        if config.accept_invalid_certs {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        Ok(Self {
+            inner: builder.build(),
+            server_name: config.server_name.clone(),
+            verify: !config.accept_invalid_certs,
+        })
+    }
+
+    /// Performs the TLS handshake over the `stream` connected to `host`.
+    ///
+    /// This function yields.
+    #[inline(always)]
+    pub fn connect<S>(&self, host: &str, stream: S) -> Result<TlsStream<S>, Error>
+    where
+        S: AsRawFd,
+    {
+        self.connect_timeout(host, stream, INFINITY)
+    }
+
+    /// Performs the TLS handshake over the `stream` connected to `host`
+    /// failing with [`io::ErrorKind::TimedOut`] if it takes longer than
+    /// `timeout`.
+    ///
+    /// This function yields.
+    pub fn connect_timeout<S>(
+        &self,
+        host: &str,
+        stream: S,
+        timeout: Duration,
+    ) -> Result<TlsStream<S>, Error>
+    where
+        S: AsRawFd,
+    {
+        let deadline = Instant::now().checked_add(timeout);
+        let fd = stream.as_raw_fd();
+        let mut res = self.start_handshake(host, stream);
+        loop {
+            match res {
+                Ok(ssl) => return Ok(TlsStream::new(ssl)),
+                Err(HandshakeError::WouldBlock(mid)) => {
+                    let timeout = match deadline {
+                        Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                        None => INFINITY,
+                    };
+                    coio_wait(fd, wanted_io(mid.error()), timeout.as_secs_f64())?;
+                    res = mid.handshake();
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Performs the TLS handshake over the `stream` connected to `host`.
+    ///
+    /// Use [`timeout`](crate::fiber::async::timeout::timeout) to limit the
+    /// time of the handshake.
+    pub async fn connect_async<S>(&self, host: &str, stream: S) -> Result<TlsStream<S>, Error>
+    where
+        S: AsRawFd,
+    {
+        let fd = stream.as_raw_fd();
+        let mid = match self.start_handshake(host, stream) {
+            Ok(ssl) => return Ok(TlsStream::new(ssl)),
+            Err(HandshakeError::WouldBlock(mid)) => mid,
+            Err(e) => return Err(e.into()),
+        };
+        Handshake {
+            fd,
+            mid: Some(mid),
+            is_waiting: false,
+        }
+        .await
+    }
+
+    fn start_handshake<S>(
+        &self,
+        host: &str,
+        stream: S,
+    ) -> Result<SslStream<Socket<S>>, HandshakeError<Socket<S>>>
+    where
+        S: AsRawFd,
+    {
+        let domain = self.server_name.as_deref().unwrap_or(host);
+        let config = self
+            .inner
+            .configure()
+            .map_err(HandshakeError::SetupFailure)?
+            .verify_hostname(self.verify);
+        config.connect(domain, Socket(stream))
+    }
+}
+
+/// Returns the readiness of the socket the TLS session is waiting for.
+fn wanted_io(error: &ssl::Error) -> CoIOFlags {
+    if error.code() == ErrorCode::WANT_WRITE {
+        CoIOFlags::WRITE
+    } else {
+        CoIOFlags::READ
+    }
+}
+
+/// A future completing an interrupted TLS handshake.
+struct Handshake<S> {
+    fd: RawFd,
+    mid: Option<MidHandshakeSslStream<Socket<S>>>,
+    /// The handshake is resumed only after the socket readiness requested by
+    /// the previous attempt was waited for.
+    is_waiting: bool,
+}
+
+impl<S: AsRawFd> Future for Handshake<S> {
+    type Output = Result<TlsStream<S>, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mid = self.mid.take().expect("polled after completion");
+        let res = if self.is_waiting {
+            mid.handshake()
+        } else {
+            Err(HandshakeError::WouldBlock(mid))
+        };
+        match res {
+            Ok(ssl) => Poll::Ready(Ok(TlsStream::new(ssl))),
+            Err(HandshakeError::WouldBlock(mid)) => {
+                // SAFETY: This is safe as long as the `Context` really
+                // is the `ContextExt`. It's always true within provided
+                // `block_on` async runtime.
+                unsafe { ContextExt::set_coio_wait(cx, self.fd, wanted_io(mid.error())) };
+                self.mid = Some(mid);
+                self.is_waiting = true;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e.into())),
+        }
+    }
+}
+
+impl<S> Unpin for Handshake<S> {}
+
+/// Non-blocking I/O over the socket of the wrapped stream.
+///
+/// Never makes the fiber yield, so that the TLS session is never accessed
+/// from several fibers at once. Instead the callers wait for the socket
+/// readiness requested by the session and retry.
+#[derive(Debug)]
+struct Socket<S>(S);
+
+impl<S: AsRawFd> Read for Socket<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let fd = self.0.as_raw_fd();
+        let res = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+        if res < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res as usize)
+        }
+    }
+}
+
+impl<S: AsRawFd> Write for Socket<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let fd = self.0.as_raw_fd();
+        let res = unsafe { libc::write(fd, buf.as_ptr().cast(), buf.len()) };
+        if res < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res as usize)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Outcome of a single non-blocking operation on a TLS session.
+enum Progress {
+    Done(usize),
+    /// The session waits for the socket to become ready.
+    Wait(CoIOFlags),
+    /// The operation was interrupted by a signal and can be retried.
+    Retry,
+}
+
+/// A TLS encrypted stream, see [`TlsConnector`] and the
+/// [module level documentation](self).
+///
+/// The stream can be read and written by different fibers simultaneously
+/// via shared references.
+pub struct TlsStream<S = CoIOStream> {
+    fd: RawFd,
+    ssl: RefCell<SslStream<Socket<S>>>,
+}
+
+impl<S: AsRawFd> TlsStream<S> {
+    fn new(ssl: SslStream<Socket<S>>) -> Self {
+        Self {
+            fd: ssl.get_ref().0.as_raw_fd(),
+            ssl: RefCell::new(ssl),
+        }
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> io::Result<Progress> {
+        match self.ssl.borrow_mut().ssl_read(buf) {
+            Ok(n) => Ok(Progress::Done(n)),
+            // The peer closed the session
+            Err(e) if e.code() == ErrorCode::ZERO_RETURN => Ok(Progress::Done(0)),
+            // The peer closed the connection without closing the session
+            Err(e) if e.code() == ErrorCode::SYSCALL && e.io_error().is_none() => {
+                Ok(Progress::Done(0))
+            }
+            Err(e) => progress_from(e),
+        }
+    }
+
+    fn try_write(&self, buf: &[u8]) -> io::Result<Progress> {
+        match self.ssl.borrow_mut().ssl_write(buf) {
+            Ok(n) => Ok(Progress::Done(n)),
+            Err(e) => progress_from(e),
+        }
+    }
+
+    /// Blocks the fiber until the operation `f` is completed.
+    fn blocking(&self, mut f: impl FnMut() -> io::Result<Progress>) -> io::Result<usize> {
+        loop {
+            match f()? {
+                Progress::Done(n) => return Ok(n),
+                Progress::Wait(flags) => coio_wait(self.fd, flags, INFINITY.as_secs_f64())?,
+                Progress::Retry => continue,
+            }
+        }
+    }
+
+    /// Returns `Poll::Pending` registering the wait for the socket
+    /// readiness in the `cx` if the operation didn't complete.
+    fn poll(
+        &self,
+        cx: &mut Context<'_>,
+        progress: io::Result<Progress>,
+    ) -> Poll<io::Result<usize>> {
+        match progress {
+            Ok(Progress::Done(n)) => Poll::Ready(Ok(n)),
+            Ok(Progress::Wait(flags)) => {
+                // SAFETY: This is safe as long as the `Context` really
+                // is the `ContextExt`. It's always true within provided
+                // `block_on` async runtime.
+                unsafe { ContextExt::set_coio_wait(cx, self.fd, flags) };
+                Poll::Pending
+            }
+            Ok(Progress::Retry) => {
+                // SAFETY: Same as above.
+                unsafe { ContextExt::set_deadline(cx, Instant::now()) };
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn progress_from(error: ssl::Error) -> io::Result<Progress> {
+    match error.code() {
+        ErrorCode::WANT_READ => Ok(Progress::Wait(CoIOFlags::READ)),
+        ErrorCode::WANT_WRITE => Ok(Progress::Wait(CoIOFlags::WRITE)),
+        _ => match error.into_io_error() {
+            Ok(e) if e.kind() == io::ErrorKind::Interrupted => Ok(Progress::Retry),
+            Ok(e) => Err(e),
+            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+        },
+    }
+}
+
+impl<S> AsRawFd for TlsStream<S> {
+    #[inline(always)]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl<S: AsRawFd> Read for &TlsStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.blocking(|| self.try_read(buf))
+    }
+}
+
+impl<S: AsRawFd> Write for &TlsStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.blocking(|| self.try_write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: AsRawFd> Read for TlsStream<S> {
+    #[inline(always)]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+}
+
+impl<S: AsRawFd> Write for TlsStream<S> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: AsRawFd> AsyncRead for TlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let progress = self.try_read(buf);
+        self.poll(cx, progress)
+    }
+}
+
+impl<S> AsyncWrite for TlsStream<S>
+where
+    S: AsRawFd + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let progress = self.try_write(buf);
+        self.poll(cx, progress)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut ssl = self.ssl.borrow_mut();
+        // Notifying the peer is best effort, the connection is closed anyway
+        let _ = ssl.shutdown();
+        Pin::new(&mut ssl.get_mut().0).poll_close(cx)
+    }
+}
+
+impl<S> std::fmt::Debug for TlsStream<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("fd", &self.fd)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::path::Path;
+    use std::thread;
+
+    use once_cell::sync::Lazy;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::{BigNum, MsbOption};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::SslAcceptor;
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    use openssl::x509::{X509NameBuilder, X509};
+
+    use crate::fiber;
+    use crate::fiber::r#async::timeout::IntoTimeout as _;
+    use crate::net_box::{Conn, ConnOptions, Options};
+    use crate::network::client::{Client, Error as ClientError};
+    use crate::network::protocol;
+    use crate::test::TARANTOOL_LISTEN;
+
+    const _3_SEC: Duration = Duration::from_secs(3);
+
+    /// Certificates issued by a throwaway CA for `localhost` & `127.0.0.1`.
+    struct Certs {
+        ca: PathBuf,
+        server_cert: PathBuf,
+        server_key: PathBuf,
+        client_cert: PathBuf,
+        client_key: PathBuf,
+    }
+
+    static CERTS: Lazy<Certs> = Lazy::new(|| {
+        let dir = std::env::temp_dir().join(format!("tarantool-tls-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (ca, ca_key) = issue_cert("test ca", None);
+        let (server, server_key) = issue_cert("localhost", Some((&ca, &ca_key)));
+        let (client, client_key) = issue_cert("test client", Some((&ca, &ca_key)));
+        let write = |name: &str, pem: Vec<u8>| {
+            let path = dir.join(name);
+            std::fs::write(&path, pem).unwrap();
+            path
+        };
+        Certs {
+            ca: write("ca.pem", ca.to_pem().unwrap()),
+            server_cert: write("server.pem", server.to_pem().unwrap()),
+            server_key: write("server.key", server_key.private_key_to_pem_pkcs8().unwrap()),
+            client_cert: write("client.pem", client.to_pem().unwrap()),
+            client_key: write("client.key", client_key.private_key_to_pem_pkcs8().unwrap()),
+        }
+    });
+
+    /// Issues a certificate signed by the `issuer` or a self-signed CA
+    /// certificate if there's no `issuer`.
+    fn issue_cert(name: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+        let mut serial = BigNum::new().unwrap();
+        serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_serial_number(&serial.to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        match issuer {
+            None => {
+                builder.set_issuer_name(&subject).unwrap();
+                let ca = BasicConstraints::new().critical().ca().build().unwrap();
+                builder.append_extension(ca).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+            Some((issuer, issuer_key)) => {
+                builder.set_issuer_name(issuer.subject_name()).unwrap();
+                let san = SubjectAlternativeName::new()
+                    .dns("localhost")
+                    .ip("127.0.0.1")
+                    .build(&builder.x509v3_context(Some(issuer), None))
+                    .unwrap();
+                builder.append_extension(san).unwrap();
+                builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+            }
+        }
+        (builder.build(), key)
+    }
+
+    /// Starts a TLS terminating proxy in front of the test instance and
+    /// returns its port. If `verify_clients` is set, the clients are required
+    /// to present a certificate issued by the test CA.
+    fn start_proxy(verify_clients: bool) -> u16 {
+        let certs = &*CERTS;
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).unwrap();
+        acceptor
+            .set_certificate_chain_file(&certs.server_cert)
+            .unwrap();
+        acceptor
+            .set_private_key_file(&certs.server_key, SslFiletype::PEM)
+            .unwrap();
+        if verify_clients {
+            acceptor.set_ca_file(&certs.ca).unwrap();
+            acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+        let acceptor = acceptor.build();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for client in listener.incoming() {
+                let acceptor = acceptor.clone();
+                thread::spawn(move || {
+                    let client = match acceptor.accept(client.unwrap()) {
+                        Ok(client) => client,
+                        Err(_) => return,
+                    };
+                    let server = TcpStream::connect(("127.0.0.1", TARANTOOL_LISTEN)).unwrap();
+                    relay(client, server);
+                });
+            }
+        });
+        port
+    }
+
+    /// Forwards the data between the TLS `client` and the plain `server`
+    /// until either of them closes the connection.
+    fn relay(mut client: SslStream<TcpStream>, mut server: TcpStream) {
+        client.get_ref().set_nonblocking(true).unwrap();
+        server.set_nonblocking(true).unwrap();
+        let mut buf = [0; 4096];
+        loop {
+            let mut is_idle = true;
+            match client.ssl_read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    is_idle = false;
+                    if write_all(|data| server.write(data), &buf[..n]).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.code() == ErrorCode::WANT_READ => {}
+                Err(_) => break,
+            }
+            match server.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    is_idle = false;
+                    let res = write_all(
+                        |data| {
+                            client.ssl_write(data).map_err(|e| match e.code() {
+                                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => {
+                                    io::ErrorKind::WouldBlock.into()
+                                }
+                                _ => io::Error::new(io::ErrorKind::Other, e),
+                            })
+                        },
+                        &buf[..n],
+                    );
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+            if is_idle {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        let _ = server.shutdown(Shutdown::Both);
+        let _ = client.get_ref().shutdown(Shutdown::Both);
+    }
+
+    fn write_all(
+        mut write: impl FnMut(&[u8]) -> io::Result<usize>,
+        mut data: &[u8],
+    ) -> io::Result<()> {
+        while !data.is_empty() {
+            match write(data) {
+                Ok(n) => data = &data[n..],
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn config(ca_file: Option<&Path>) -> TlsConfig {
+        TlsConfig {
+            ca_file: ca_file.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    fn net_box_eval(port: u16, tls: TlsConfig) -> crate::Result<i32> {
+        let conn = Conn::new(
+            ("127.0.0.1", port),
+            ConnOptions {
+                user: "test_user".into(),
+                password: "password".into(),
+                tls: Some(tls),
+                ..Default::default()
+            },
+            None,
+        )?;
+        let options = Options {
+            timeout: Some(_3_SEC),
+            ..Default::default()
+        };
+        let res = conn.eval("return ...", &(42,), &options)?.unwrap();
+        Ok(res.decode::<(i32,)>()?.0)
+    }
+
+    async fn client_eval(port: u16, tls: TlsConfig) -> Result<i32, ClientError> {
+        let client = Client::connect_with_config(
+            "localhost",
+            port,
+            protocol::Config {
+                creds: Some(("test_user".into(), "password".into())),
+                tls: Some(tls),
+            },
+        )
+        .await?;
+        let res = client.eval("return ...", &(42,)).await?.unwrap();
+        Ok(res.decode::<(i32,)>().map_err(protocol::Error::from)?.0)
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn net_box() {
+        let port = start_proxy(false);
+        let res = net_box_eval(port, config(Some(&CERTS.ca))).unwrap();
+        assert_eq!(res, 42);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn net_box_uri() {
+        let port = start_proxy(false);
+        // The certificate is verified against the host from the uri
+        let conn = Conn::from_uri(
+            &format!("test_user:password@localhost:{}", port),
+            ConnOptions {
+                tls: Some(config(Some(&CERTS.ca))),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+        let options = Options {
+            timeout: Some(_3_SEC),
+            ..Default::default()
+        };
+        let res = conn.eval("return ...", &(42,), &options).unwrap().unwrap();
+        assert_eq!(res.decode::<(i32,)>().unwrap().0, 42);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn network_client() {
+        let port = start_proxy(false);
+        let res = fiber::block_on(client_eval(port, config(Some(&CERTS.ca))).timeout(_3_SEC))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res, 42);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn unknown_ca() {
+        let port = start_proxy(false);
+        let err = net_box_eval(port, config(None)).unwrap_err();
+        assert!(matches!(err, crate::error::Error::Tls(Error::Handshake(_))));
+
+        let err = fiber::block_on(client_eval(port, config(None)).timeout(_3_SEC))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, ClientError::Tls(Error::Handshake(_))));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn server_name_mismatch() {
+        let port = start_proxy(false);
+        let tls = TlsConfig {
+            server_name: Some("example.org".into()),
+            ..config(Some(&CERTS.ca))
+        };
+        let err = net_box_eval(port, tls.clone()).unwrap_err();
+        assert!(matches!(err, crate::error::Error::Tls(Error::Handshake(_))));
+
+        let err = fiber::block_on(client_eval(port, tls).timeout(_3_SEC))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, ClientError::Tls(Error::Handshake(_))));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn accept_invalid_certs() {
+        let port = start_proxy(false);
+        let tls = TlsConfig {
+            accept_invalid_certs: true,
+            ..config(None)
+        };
+        assert_eq!(net_box_eval(port, tls).unwrap(), 42);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn client_cert() {
+        let port = start_proxy(true);
+        let tls = TlsConfig {
+            cert_file: Some(CERTS.client_cert.clone()),
+            key_file: Some(CERTS.client_key.clone()),
+            ..config(Some(&CERTS.ca))
+        };
+        assert_eq!(net_box_eval(port, tls.clone()).unwrap(), 42);
+        let res = fiber::block_on(client_eval(port, tls).timeout(_3_SEC))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res, 42);
+
+        // The server rejects the clients without a certificate
+        net_box_eval(port, config(Some(&CERTS.ca))).unwrap_err();
+    }
+}
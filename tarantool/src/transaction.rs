@@ -27,8 +27,14 @@
 //! - [Lua reference: Functions for transaction management](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_txn_management/)
 //! - [C API reference: Module txn](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/txn/)
 
-use crate::error::TransactionError;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use crate::error::{Error, TarantoolError, TarantoolErrorCode, TransactionError};
 use crate::ffi::tarantool as ffi;
+use crate::fiber;
+use crate::tlua;
 
 /// Begin a transaction in the current fiber.
 ///
@@ -64,3 +70,352 @@ where
     }
     result
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Transaction
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns `true` if there's an active transaction in the current fiber.
+#[inline(always)]
+pub fn is_in_txn() -> bool {
+    unsafe { ffi::box_txn() }
+}
+
+crate::define_str_enum! {
+    #![coerce_from_str]
+    /// Transaction isolation level.
+    /// (for details see [Transaction isolation levels](https://www.tarantool.io/en/doc/latest/concepts/atomic/txn_mode_mvcc/#transaction-isolation-levels)).
+    ///
+    /// Requires tarantool 2.10 or later, `Linearizable` requires 2.11 or later.
+    pub enum TxnIsolation {
+        /// Either `ReadCommitted` or `ReadConfirmed` is chosen depending on the
+        /// transaction contents.
+        BestEffort    = "best-effort",
+        /// Read changes that are committed but not necessarily confirmed by
+        /// the replicas.
+        ReadCommitted = "read-committed",
+        /// Read only changes that are confirmed by the replicas.
+        ReadConfirmed = "read-confirmed",
+        /// Read only the latest data confirmed by the quorum of replicas. Only
+        /// allowed for transactions on synchronous spaces.
+        Linearizable  = "linearizable",
+    }
+}
+
+/// Options for [`Transaction::begin_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionOptions {
+    /// Transaction isolation level. If `None`, the level specified in
+    /// `box.cfg.default_txn_isolation` is used.
+    pub isolation: Option<TxnIsolation>,
+    /// The transaction is rolled back automatically if it isn't committed
+    /// before the timeout expires. If `None`, `box.cfg.txn_timeout` is used.
+    pub timeout: Option<Duration>,
+}
+
+/// An active transaction in the current fiber.
+///
+/// The transaction is **rolled back** when the value is dropped, unless
+/// [`Transaction::commit`] or [`Transaction::rollback`] was called explicitly.
+///
+/// ```no_run
+/// use tarantool::space::Space;
+/// use tarantool::transaction::Transaction;
+///
+/// let space = Space::find("accounts").unwrap();
+/// let txn = Transaction::begin().unwrap();
+/// space.insert(&(1, "Alice")).unwrap();
+/// let savepoint = txn.savepoint().unwrap();
+/// space.insert(&(2, "Bob")).unwrap();
+/// // Only Alice will be inserted
+/// txn.rollback_to(&savepoint).unwrap();
+/// txn.commit().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Transaction {
+    /// Id of the transaction, used to make sure the guard doesn't roll back
+    /// some other transaction, if this one was finished behind its back
+    /// (e.g. via `box.commit()` from lua).
+    id: i64,
+    is_finished: bool,
+    /// A transaction is bound to the fiber in which it was started.
+    marker: PhantomData<*const ()>,
+}
+
+impl Transaction {
+    /// Begin a transaction in the current fiber.
+    ///
+    /// Returns an error if there's already an active transaction in the
+    /// current fiber.
+    pub fn begin() -> Result<Self, Error> {
+        if unsafe { ffi::box_txn_begin() } < 0 {
+            return Err(TransactionError::AlreadyStarted.into());
+        }
+        Ok(Self::new())
+    }
+
+    /// Begin a transaction in the current fiber with the given isolation
+    /// level and timeout.
+    ///
+    /// Returns an error if there's already an active transaction in the
+    /// current fiber or if the options aren't supported by the current
+    /// tarantool version.
+    pub fn begin_with(opts: &TransactionOptions) -> Result<Self, Error> {
+        if is_in_txn() {
+            return Err(TransactionError::AlreadyStarted.into());
+        }
+        crate::lua_state()
+            .exec_with(
+                "local txn_isolation, timeout = ...
+                box.begin({ txn_isolation = txn_isolation, timeout = timeout })",
+                (opts.isolation, opts.timeout.map(|t| t.as_secs_f64())),
+            )
+            .map_err(tlua::LuaError::from)?;
+        Ok(Self::new())
+    }
+
+    #[inline(always)]
+    fn new() -> Self {
+        Self {
+            id: unsafe { ffi::box_txn_id() },
+            is_finished: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create a savepoint in the transaction. Use [`Transaction::rollback_to`]
+    /// to roll back all the changes made after the savepoint was created.
+    pub fn savepoint(&self) -> Result<Savepoint<'_>, Error> {
+        let ptr = unsafe { ffi::box_txn_savepoint() };
+        match NonNull::new(ptr) {
+            Some(ptr) => Ok(Savepoint {
+                ptr,
+                marker: PhantomData,
+            }),
+            None => Err(TarantoolError::last().into()),
+        }
+    }
+
+    /// Roll back all the changes made after the `savepoint` was created.
+    /// The transaction remains active.
+    ///
+    /// The savepoint remains valid and can be rolled back to again, but all
+    /// the savepoints created after it become invalid.
+    pub fn rollback_to(&self, savepoint: &Savepoint) -> Result<(), Error> {
+        if unsafe { ffi::box_txn_rollback_to_savepoint(savepoint.ptr.as_ptr()) } < 0 {
+            return Err(TarantoolError::last().into());
+        }
+        Ok(())
+    }
+
+    /// Commit the transaction.
+    ///
+    /// If the commit fails the transaction is rolled back and the
+    /// corresponding [`TarantoolError`] is returned (e.g. with code
+    /// [`TransactionConflict`] in case of a conflict with a concurrent
+    /// transaction).
+    ///
+    /// [`TransactionConflict`]: crate::error::TarantoolErrorCode::TransactionConflict
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.is_finished = true;
+        if unsafe { ffi::box_txn_commit() } < 0 {
+            return Err(TarantoolError::last().into());
+        }
+        Ok(())
+    }
+
+    /// Roll back the transaction.
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.is_finished = true;
+        if unsafe { ffi::box_txn_rollback() } < 0 {
+            return Err(TransactionError::FailedToRollback.into());
+        }
+        Ok(())
+    }
+
+    /// Set a callback to be called after the transaction is successfully
+    /// committed.
+    pub fn on_commit<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: FnOnce() + 'static,
+    {
+        set_trigger("box.on_commit", cb)
+    }
+
+    /// Set a callback to be called after the transaction is rolled back,
+    /// either explicitly or because of a failed commit.
+    pub fn on_rollback<F>(&self, cb: F) -> Result<(), Error>
+    where
+        F: FnOnce() + 'static,
+    {
+        set_trigger("box.on_rollback", cb)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.is_finished && unsafe { ffi::box_txn_id() } == self.id {
+            unsafe { ffi::box_txn_rollback() };
+        }
+    }
+}
+
+fn set_trigger<F>(setter: &str, cb: F) -> Result<(), Error>
+where
+    F: FnOnce() + 'static,
+{
+    let mut cb = Some(cb);
+    crate::lua_state()
+        .exec_with(
+            &format!("local cb = ... {}(function() cb() end)", setter),
+            tlua::function0(move || {
+                if let Some(cb) = cb.take() {
+                    cb()
+                }
+            }),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// A savepoint in a [`Transaction`]. See [`Transaction::savepoint`].
+#[derive(Debug)]
+pub struct Savepoint<'txn> {
+    ptr: NonNull<ffi::BoxTxnSavepoint>,
+    marker: PhantomData<&'txn Transaction>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// retrying
+////////////////////////////////////////////////////////////////////////////////
+
+/// Policy used by [`retrying`] to decide how many times and how often a
+/// conflicting transaction is retried.
+///
+/// After each conflict the current fiber sleeps for the backoff duration,
+/// which starts at `initial_backoff` and is doubled after every attempt, but
+/// never exceeds `max_backoff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of times the transaction is executed, including the
+    /// first attempt.
+    pub max_attempts: u32,
+    /// Time to sleep after the first conflict.
+    pub initial_backoff: Duration,
+    /// Upper bound for the time to sleep between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Errors which can signal a transaction conflict, see [`retrying`].
+pub trait ConflictError {
+    /// Returns `true` if the error is a [`TransactionConflict`] error.
+    ///
+    /// [`TransactionConflict`]: TarantoolErrorCode::TransactionConflict
+    fn is_conflict(&self) -> bool;
+}
+
+impl ConflictError for TarantoolError {
+    #[inline(always)]
+    fn is_conflict(&self) -> bool {
+        self.error_code() == TarantoolErrorCode::TransactionConflict as u32
+    }
+}
+
+impl ConflictError for Error {
+    #[inline(always)]
+    fn is_conflict(&self) -> bool {
+        matches!(self, Error::Tarantool(e) if e.is_conflict())
+    }
+}
+
+/// Error returned by [`retrying`].
+#[derive(Debug, thiserror::Error)]
+pub enum RetryError<E> {
+    /// The transaction failed with a conflict on every attempt. `error` is the
+    /// error of the last attempt.
+    #[error("transaction gave up after {attempts} conflicts: {error}")]
+    TooManyConflicts { attempts: u32, error: E },
+
+    /// The transaction failed with an error other than a conflict, e.g. the
+    /// error returned by the closure.
+    #[error("{0}")]
+    Other(E),
+}
+
+impl<E> RetryError<E> {
+    /// Returns the underlying error.
+    pub fn into_inner(self) -> E {
+        match self {
+            Self::TooManyConflicts { error, .. } | Self::Other(error) => error,
+        }
+    }
+}
+
+/// Execute `f` in a transaction and commit it, retrying the whole transaction
+/// if it's aborted because of a conflict with a concurrent transaction (which
+/// is possible when `box.cfg.memtx_use_mvcc_engine` is enabled).
+///
+/// - `policy` - how many times and how often to retry, see [`RetryPolicy`].
+/// - `f` - function will be invoked within transaction. Can be called several
+///   times, so it shouldn't have any side effects other than the database
+///   modifications.
+///
+/// Returns result of function `f` execution. Depending on the function result:
+/// - will **commit** - if function completes successfully
+/// - will **retry** - if either the function or the commit fails with a
+///   [`TransactionConflict`] error and `policy.max_attempts` isn't reached
+/// - will **rollback** - if function completes with any other error
+///
+/// ```no_run
+/// use tarantool::error::Error;
+/// use tarantool::space::Space;
+/// use tarantool::transaction::{retrying, RetryPolicy};
+///
+/// let space = Space::find("accounts").unwrap();
+/// let res = retrying(&RetryPolicy::default(), || -> Result<(), Error> {
+///     space.upsert(&(1, 100), [("+", 1, 100)])?;
+///     Ok(())
+/// });
+/// ```
+///
+/// [`TransactionConflict`]: TarantoolErrorCode::TransactionConflict
+pub fn retrying<T, E, F>(policy: &RetryPolicy, mut f: F) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Result<T, E>,
+    E: From<Error> + ConflictError,
+{
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = Transaction::begin().map_err(E::from).and_then(|txn| {
+            let value = f()?;
+            txn.commit()?;
+            Ok(value)
+        });
+        match result {
+            Ok(value) => return Ok(value),
+            Err(e) if e.is_conflict() => {
+                if attempt >= policy.max_attempts {
+                    return Err(RetryError::TooManyConflicts {
+                        attempts: attempt,
+                        error: e,
+                    });
+                }
+                fiber::sleep(backoff);
+                backoff = std::cmp::min(backoff * 2, policy.max_backoff);
+            }
+            Err(e) => return Err(RetryError::Other(e)),
+        }
+    }
+}
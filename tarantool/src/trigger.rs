@@ -1,6 +1,9 @@
+use std::cell::Cell;
+
 use crate::error::{TarantoolError, TarantoolErrorCode};
 use crate::ffi::tarantool as ffi;
 use crate::set_error;
+use crate::tlua::{self, LuaState, PushOneInto, Void};
 
 use nix::errno;
 
@@ -26,3 +29,73 @@ pub fn on_shutdown<F: FnOnce() + 'static>(cb: F) -> Result<(), TarantoolError> {
         0
     }
 }
+
+/// Key in the lua registry of the table with the functions removing the
+/// triggers owned by [`TriggerGuard`]s.
+const REMOVERS_KEY: &str = "tarantool.rs.trigger_removers";
+
+/// A trigger set via the lua api.
+///
+/// The trigger is removed when the guard is dropped, use
+/// [`TriggerGuard::forget`] to keep it for the rest of the program.
+#[must_use = "the trigger is removed as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct TriggerGuard {
+    id: u64,
+}
+
+impl TriggerGuard {
+    /// Execute lua `code` with `trigger` as the only argument. The code must
+    /// set the trigger and return a function which removes it.
+    pub(crate) fn from_lua<T>(code: &str, trigger: T) -> Result<Self, tlua::LuaError>
+    where
+        T: PushOneInto<LuaState>,
+        T::Err: Into<Void>,
+    {
+        thread_local! {
+            static NEXT_ID: Cell<u64> = const { Cell::new(1) };
+        }
+        let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+        crate::lua_state().exec_with(
+            &format!(
+                "local id, trigger = ...
+                local remove = (function(...) {code} end)(trigger)
+                local registry = debug.getregistry()
+                registry[{REMOVERS_KEY:?}] = registry[{REMOVERS_KEY:?}] or {{}}
+                registry[{REMOVERS_KEY:?}][id] = remove"
+            ),
+            (id, trigger),
+        )?;
+        Ok(Self { id })
+    }
+
+    /// Keep the trigger set after the guard is gone.
+    pub fn forget(self) {
+        self.take_remover("");
+        std::mem::forget(self);
+    }
+
+    /// Take the remover out of the registry and pass it to `action`, which is
+    /// a lua statement.
+    fn take_remover(&self, action: &str) {
+        let res = crate::lua_state().exec_with(
+            &format!(
+                "local id = ...
+                local removers = debug.getregistry()[{REMOVERS_KEY:?}]
+                local remove = removers[id]
+                removers[id] = nil
+                {action}"
+            ),
+            self.id,
+        );
+        if let Err(e) = res {
+            log::error!("Failed to remove trigger: {e}");
+        }
+    }
+}
+
+impl Drop for TriggerGuard {
+    fn drop(&mut self) {
+        self.take_remover("remove()");
+    }
+}
@@ -0,0 +1,495 @@
+//! Communication between the TX thread and other OS threads.
+//!
+//! Most of the tarantool API (fibers, box, lua) can only be used from the TX
+//! thread, i.e. the thread in which the module is loaded. This module allows
+//! the threads spawned with [`std::thread`] to pass data back to the fibers:
+//! - [`channel`] creates a channel with a [`Sender`] which can be used from
+//!   any thread and a [`Receiver`] which is used by the fibers, both blocking
+//!   and in async code.
+//! - [`execute`] runs a closure in the TX thread and returns its result to the
+//!   calling thread. [`init`] must be called in the TX thread first.
+//!
+//! The receiver is notified via a socket pair, so no fiber is blocked while
+//! the channel is empty.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::{fiber, tx_thread};
+//!
+//! let (tx, rx) = tx_thread::channel().unwrap();
+//! std::thread::spawn(move || {
+//!     let hash = heavy_computation();
+//!     tx.send(hash).unwrap();
+//! });
+//! // yields until the value is sent
+//! let hash = rx.recv().unwrap();
+//! # fn heavy_computation() -> u64 { 0 }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+
+use crate::ffi::tarantool as ffi;
+use crate::fiber::r#async::context::ContextExt;
+use crate::fiber::{self, Cancelled, RecvError, TryRecvError};
+
+////////////////////////////////////////////////////////////////////////////////
+// channel
+////////////////////////////////////////////////////////////////////////////////
+
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    senders: AtomicUsize,
+    rx_closed: AtomicBool,
+    /// Written to by the senders to wake up the receiver.
+    notify: UnixStream,
+}
+
+impl<T> Shared<T> {
+    fn notify(&self) {
+        // If the socket buffer is full, the receiver will be woken up anyway
+        let _ = (&self.notify).write(&[1]);
+    }
+}
+
+/// Creates an unbounded channel for sending values from any thread to the
+/// fibers of the TX thread. See the [module level documentation](self) for
+/// details.
+///
+/// Must be called from the TX thread. Returns an error if the notification
+/// socket couldn't be created.
+pub fn channel<T>() -> io::Result<(Sender<T>, Receiver<T>)> {
+    let (notify_rx, notify_tx) = UnixStream::pair()?;
+    notify_rx.set_nonblocking(true)?;
+    notify_tx.set_nonblocking(true)?;
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        senders: AtomicUsize::new(1),
+        rx_closed: AtomicBool::new(false),
+        notify: notify_tx,
+    });
+    let tx = Sender {
+        shared: shared.clone(),
+    };
+    let rx = Receiver {
+        shared,
+        notify: notify_rx,
+        marker: PhantomData,
+    };
+    Ok((tx, rx))
+}
+
+/// The sending half of a [`channel`]. Can be cloned and sent to other
+/// threads.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to the receiver. Never blocks.
+    ///
+    /// Returns the value back if the receiver was dropped.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.shared.rx_closed.load(Ordering::Acquire) {
+            return Err(SendError(value));
+        }
+        self.shared
+            .queue
+            .lock()
+            .expect("never poisoned")
+            .push_back(value);
+        self.shared.notify();
+        Ok(())
+    }
+
+    /// Returns `true` if the receiver was dropped.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.shared.rx_closed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Wake up the receiver to report the disconnection
+            self.shared.notify();
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a [`channel`]. Can only be used in the TX thread.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    notify: UnixStream,
+    /// The receiver uses the fiber API, so it must not leave the TX thread.
+    marker: PhantomData<*const ()>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives a value, **yielding** until one is available.
+    ///
+    /// Returns `None` if all the senders were dropped and the channel is
+    /// empty or if the fiber was cancelled.
+    #[inline]
+    pub fn recv(&self) -> Option<T> {
+        self.recv_maybe_timeout(None).ok()
+    }
+
+    /// Receives a value, **yielding** until one is available or the `timeout`
+    /// expires.
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvError> {
+        self.recv_maybe_timeout(Some(timeout))
+    }
+
+    fn recv_maybe_timeout(&self, timeout: Option<Duration>) -> Result<T, RecvError> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            match self.try_recv() {
+                Ok(v) => return Ok(v),
+                Err(TryRecvError::Empty) => {}
+                Err(_) => return Err(RecvError::Disconnected),
+            }
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return Err(RecvError::Timeout);
+                    }
+                    timeout.as_secs_f64()
+                }
+                None => ffi::TIMEOUT_INFINITY,
+            };
+            match crate::coio::coio_wait(self.notify.as_raw_fd(), ffi::CoIOFlags::READ, timeout) {
+                Err(e) if Cancelled::is_cause_of(&e) => return Err(RecvError::Cancelled),
+                // Timeout is checked above
+                _ => {}
+            }
+        }
+    }
+
+    /// Receives a value if one is available. Never yields.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.drain_notifications();
+        if let Some(v) = self.pop() {
+            return Ok(v);
+        }
+        if self.shared.senders.load(Ordering::Acquire) == 0 {
+            // The last values could've been sent right before disconnection
+            return self.pop().ok_or(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    /// Returns a future which resolves to the next value or `None` if all the
+    /// senders were dropped and the channel is empty.
+    ///
+    /// The future must be executed by [`fiber::block_on`].
+    #[inline]
+    pub fn recv_async(&self) -> Recv<'_, T> {
+        Recv { rx: self }
+    }
+
+    #[inline]
+    fn pop(&self) -> Option<T> {
+        self.shared
+            .queue
+            .lock()
+            .expect("never poisoned")
+            .pop_front()
+    }
+
+    /// Reads the pending notifications, so that the next wait blocks until
+    /// a new value is sent.
+    fn drain_notifications(&self) {
+        let mut buf = [0; 64];
+        while let Ok(n) = (&self.notify).read(&mut buf) {
+            if n < buf.len() {
+                break;
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.rx_closed.store(true, Ordering::Release);
+    }
+}
+
+impl<T> std::fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// Future returned by [`Receiver::recv_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.rx.try_recv() {
+            Ok(v) => Poll::Ready(Some(v)),
+            Err(TryRecvError::Empty) => {
+                // SAFETY: Safe as long as this future is executed by
+                // `fiber::block_on` async executor.
+                unsafe {
+                    ContextExt::set_coio_wait(cx, self.rx.notify.as_raw_fd(), ffi::CoIOFlags::READ)
+                }
+                Poll::Pending
+            }
+            Err(_) => Poll::Ready(None),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Recv<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recv").finish_non_exhaustive()
+    }
+}
+
+/// Error returned by [`Sender::send`] if the receiver was dropped. Contains
+/// the value which couldn't be sent.
+#[derive(thiserror::Error, Clone, Copy, PartialEq, Eq)]
+#[error("channel closed")]
+pub struct SendError<T>(pub T);
+
+impl<T> std::fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// execute
+////////////////////////////////////////////////////////////////////////////////
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Executor {
+    thread: ThreadId,
+    jobs: Sender<Job>,
+}
+
+static EXECUTOR: OnceCell<Executor> = OnceCell::new();
+
+/// Starts a fiber in the current thread (which must be the TX thread) which
+/// runs the closures passed to [`execute`]. Calling this again does nothing.
+pub fn init() -> crate::Result<()> {
+    if EXECUTOR.get().is_some() {
+        return Ok(());
+    }
+    let (tx, rx) = channel::<Job>()?;
+    let jh = fiber::Builder::new()
+        .name("tx_thread::execute")
+        .proc(move || {
+            while let Some(job) = rx.recv() {
+                run_job(job);
+            }
+        })
+        .start()?;
+    // The fiber is waiting for the jobs and never finishes
+    jh.detach();
+    let executor = Executor {
+        thread: thread::current().id(),
+        jobs: tx,
+    };
+    if EXECUTOR.set(executor).is_err() {
+        unreachable!("init is only called in the TX thread");
+    }
+    Ok(())
+}
+
+/// Runs the job in a separate fiber, so that the jobs which yield don't block
+/// each other.
+fn run_job(job: Job) {
+    let done = std::rc::Rc::new(std::cell::Cell::new(false));
+    let res = fiber::Builder::new()
+        .name("tx_thread::job")
+        .proc({
+            let done = done.clone();
+            move || {
+                job();
+                done.set(true);
+            }
+        })
+        .start();
+    match res {
+        // The job has finished without yielding, so this doesn't yield
+        Ok(jh) if done.get() => jh.join(),
+        Ok(jh) => jh.detach(),
+        // The job is dropped and `execute` returns an error
+        Err(_) => {}
+    }
+}
+
+/// Runs `f` in the TX thread blocking the current thread until it returns.
+/// Panics in `f` are propagated to the caller. [`init`] must be called first.
+///
+/// `f` runs in a separate fiber, so it can use any tarantool API, including
+/// the yielding functions.
+///
+/// If called from the TX thread `f` is called directly.
+///
+/// ```no_run
+/// use tarantool::{space::Space, tx_thread};
+///
+/// # tx_thread::init().unwrap();
+/// std::thread::spawn(|| {
+///     let len = tx_thread::execute(|| Space::find("users").unwrap().len().unwrap())
+///         .unwrap();
+///     println!("there are {} users", len);
+/// });
+/// ```
+pub fn execute<F, T>(f: F) -> Result<T, ExecuteError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let executor = EXECUTOR.get().ok_or(ExecuteError::NotInitialized)?;
+    if executor.thread == thread::current().id() {
+        return Ok(f());
+    }
+    let (tx, rx) = mpsc::sync_channel(1);
+    let job: Job = Box::new(move || {
+        let res = panic::catch_unwind(AssertUnwindSafe(f));
+        let _ = tx.send(res);
+    });
+    executor
+        .jobs
+        .send(job)
+        .map_err(|_| ExecuteError::Disconnected)?;
+    match rx.recv() {
+        Ok(Ok(v)) => Ok(v),
+        Ok(Err(e)) => panic::resume_unwind(e),
+        Err(_) => Err(ExecuteError::Disconnected),
+    }
+}
+
+/// Error returned by [`execute`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecuteError {
+    /// [`init`] wasn't called.
+    #[error("tx_thread::init was not called")]
+    NotInitialized,
+    /// The closure was dropped without being called, e.g. because the fiber
+    /// couldn't be created.
+    #[error("closure was not executed in the TX thread")]
+    Disconnected,
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    #[crate::test(tarantool = "crate")]
+    fn send_from_threads() {
+        let (tx, rx) = channel().unwrap();
+        let threads: Vec<_> = (0..3)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..10 {
+                        tx.send(i * 10 + j).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut values = vec![];
+        while let Some(v) = rx.recv() {
+            values.push(v);
+        }
+        values.sort_unstable();
+        assert_eq!(values, (0..30).collect::<Vec<_>>());
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn timeout_and_disconnect() {
+        let (tx, rx) = channel::<i32>().unwrap();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvError::Timeout)
+        );
+        tx.send(1).unwrap();
+        drop(tx);
+        assert_eq!(rx.recv_timeout(Duration::from_millis(1)), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = channel::<i32>().unwrap();
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1).unwrap_err().0, 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn recv_async() {
+        let (tx, rx) = channel().unwrap();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.send("hello").unwrap();
+        });
+        assert_eq!(fiber::block_on(rx.recv_async()), Some("hello"));
+        t.join().unwrap();
+        assert_eq!(fiber::block_on(rx.recv_async()), None);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn execute_in_tx_thread() {
+        init().unwrap();
+        let tx_thread = thread::current().id();
+        let t = thread::spawn(move || {
+            let res = execute(move || {
+                fiber::sleep(Duration::from_millis(1));
+                thread::current().id() == tx_thread
+            });
+            let panicked = panic::catch_unwind(|| execute(|| -> i32 { panic!("oops") }));
+            (res, panicked.is_err())
+        });
+        // Yield until the thread finishes, so the executor can run
+        while !t.is_finished() {
+            fiber::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(t.join().unwrap(), (Ok(true), true));
+        assert_eq!(execute(|| 1), Ok(1));
+    }
+}
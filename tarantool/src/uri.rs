@@ -0,0 +1,307 @@
+//! Parsing of the addresses of tarantool instances.
+//!
+//! The format is compatible with the one accepted by tarantool's
+//! `net.box.connect` & `box.cfg.listen`:
+//!
+//! - `3301` - port on the `localhost`
+//! - `localhost:3301`, `192.168.0.1:3301`, `[::1]:3301` - host and port
+//! - `unix/:/path/to.sock` or `/path/to.sock` - unix domain socket
+//! - `user@localhost:3301`, `user:password@unix/:/path/to.sock` - any of the
+//!   above with credentials
+//!
+//! ```
+//! use tarantool::uri::{Address, Uri};
+//!
+//! let uri: Uri = "admin:secret@unix/:/var/run/tarantool.sock".parse().unwrap();
+//! assert_eq!(uri.login.as_deref(), Some("admin"));
+//! assert_eq!(uri.password.as_deref(), Some("secret"));
+//! assert_eq!(uri.address, Address::Unix("/var/run/tarantool.sock".into()));
+//! ```
+//!
+//! See also:
+//! - [Lua reference: Module uri](https://www.tarantool.io/en/doc/latest/reference/reference_lua/uri/)
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::Ipv6Addr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Prefix of the unix domain socket addresses.
+const UNIX_PREFIX: &str = "unix/:";
+
+/// Parsed address of a tarantool instance, see the
+/// [module level documentation](self) for the supported formats.
+///
+/// Neither [`Display`] nor [`Debug`](fmt::Debug) show the password.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Uri {
+    pub login: Option<String>,
+    pub password: Option<String>,
+    pub address: Address,
+}
+
+/// Address part of a [`Uri`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    /// TCP address, `host` is a domain name or an ip address (ipv6 addresses
+    /// are stored without the square brackets).
+    Inet { host: String, port: u16 },
+    /// Path to a unix domain socket.
+    Unix(PathBuf),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid uri {uri:?}: {reason}")]
+pub struct ParseError {
+    uri: String,
+    reason: &'static str,
+}
+
+impl From<ParseError> for io::Error {
+    fn from(error: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+impl FromStr for Uri {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseError {
+            uri: s.into(),
+            reason,
+        };
+
+        // A unix socket path may contain '@', so the credentials are only
+        // looked for before the "unix/:" prefix
+        let (userinfo, address) = if let Some(i) = s.find(UNIX_PREFIX) {
+            match &s[..i] {
+                "" => (None, &s[i..]),
+                prefix => match prefix.strip_suffix('@') {
+                    Some(userinfo) => (Some(userinfo), &s[i..]),
+                    None => return Err(error("unexpected characters before \"unix/:\"")),
+                },
+            }
+        } else if is_path(s) {
+            (None, s)
+        } else {
+            match s.rfind('@') {
+                Some(i) => (Some(&s[..i]), &s[i + 1..]),
+                None => (None, s),
+            }
+        };
+
+        let (login, password) = match userinfo {
+            None => (None, None),
+            Some(userinfo) => {
+                let (login, password) = match userinfo.split_once(':') {
+                    Some((login, password)) => (login, Some(password.into())),
+                    None => (userinfo, None),
+                };
+                if login.is_empty() {
+                    return Err(error("empty login"));
+                }
+                (Some(login.into()), password)
+            }
+        };
+
+        let address = if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+            if path.is_empty() {
+                return Err(error("empty unix socket path"));
+            }
+            Address::Unix(path.into())
+        } else if is_path(address) {
+            Address::Unix(address.into())
+        } else {
+            let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+                let (host, port) = rest
+                    .split_once("]:")
+                    .ok_or_else(|| error("expected \"[host]:port\""))?;
+                if host.parse::<Ipv6Addr>().is_err() {
+                    return Err(error("invalid ipv6 address"));
+                }
+                (host, port)
+            } else {
+                let (host, port) = address.rsplit_once(':').unwrap_or(("localhost", address));
+                if host.is_empty() {
+                    return Err(error("empty host"));
+                }
+                if host.contains([':', '/', '[', ']']) {
+                    return Err(error("invalid host"));
+                }
+                (host, port)
+            };
+            let port = port.parse().map_err(|_| error("invalid port"))?;
+            Address::Inet {
+                host: host.into(),
+                port,
+            }
+        };
+
+        Ok(Uri {
+            login,
+            password,
+            address,
+        })
+    }
+}
+
+/// Checks if `s` is a unix socket path without the "unix/:" prefix.
+fn is_path(s: &str) -> bool {
+    s.starts_with('/') || s.starts_with("./") || s.starts_with("../")
+}
+
+/// Formats the uri omitting the password, so that it can be logged safely.
+impl Display for Uri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(login) = &self.login {
+            write!(f, "{}@", login)?;
+        }
+        self.address.fmt(f)
+    }
+}
+
+impl fmt::Debug for Uri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Uri")
+            .field("login", &self.login)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inet { host, port } if host.contains(':') => write!(f, "[{}]:{}", host, port),
+            Self::Inet { host, port } => write!(f, "{}:{}", host, port),
+            Self::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inet(host: &str, port: u16) -> Address {
+        Address::Inet {
+            host: host.into(),
+            port,
+        }
+    }
+
+    fn parse(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn inet_address() {
+        assert_eq!(parse("3301").address, inet("localhost", 3301));
+        assert_eq!(parse("localhost:3301").address, inet("localhost", 3301));
+        assert_eq!(parse("192.168.0.1:3302").address, inet("192.168.0.1", 3302));
+        assert_eq!(parse("[::1]:3303").address, inet("::1", 3303));
+        assert_eq!(parse("example.org:3304").address, inet("example.org", 3304));
+    }
+
+    #[test]
+    fn unix_address() {
+        let path = |p: &str| Address::Unix(p.into());
+        assert_eq!(parse("unix/:/tmp/t.sock").address, path("/tmp/t.sock"));
+        assert_eq!(parse("unix/:./t.sock").address, path("./t.sock"));
+        assert_eq!(parse("unix/:t@1.sock").address, path("t@1.sock"));
+        assert_eq!(parse("/tmp/t.sock").address, path("/tmp/t.sock"));
+        assert_eq!(parse("../t.sock").address, path("../t.sock"));
+    }
+
+    #[test]
+    fn credentials() {
+        let uri = parse("admin:secret@localhost:3301");
+        assert_eq!(uri.login.as_deref(), Some("admin"));
+        assert_eq!(uri.password.as_deref(), Some("secret"));
+        assert_eq!(uri.address, inet("localhost", 3301));
+
+        let uri = parse("guest@[::1]:3301");
+        assert_eq!(uri.login.as_deref(), Some("guest"));
+        assert_eq!(uri.password, None);
+        assert_eq!(uri.address, inet("::1", 3301));
+
+        let uri = parse("admin:p@ss:@unix/:/tmp/t@1.sock");
+        assert_eq!(uri.login.as_deref(), Some("admin"));
+        assert_eq!(uri.password.as_deref(), Some("p@ss:"));
+        assert_eq!(uri.address, Address::Unix("/tmp/t@1.sock".into()));
+
+        let uri = parse("admin:@3301");
+        assert_eq!(uri.password.as_deref(), Some(""));
+        assert_eq!(uri.address, inet("localhost", 3301));
+
+        let uri = parse("localhost:3301");
+        assert_eq!((uri.login, uri.password), (None, None));
+    }
+
+    #[test]
+    fn invalid() {
+        let err = |s: &str| s.parse::<Uri>().unwrap_err().to_string();
+        assert_eq!(err(""), r#"invalid uri "": invalid port"#);
+        assert_eq!(err("localhost"), r#"invalid uri "localhost": invalid port"#);
+        assert_eq!(
+            err("localhost:0x1"),
+            r#"invalid uri "localhost:0x1": invalid port"#
+        );
+        assert_eq!(
+            err("localhost:65536"),
+            r#"invalid uri "localhost:65536": invalid port"#
+        );
+        assert_eq!(err(":3301"), r#"invalid uri ":3301": empty host"#);
+        assert_eq!(err("::1:3301"), r#"invalid uri "::1:3301": invalid host"#);
+        assert_eq!(
+            err("[::1]"),
+            r#"invalid uri "[::1]": expected "[host]:port""#
+        );
+        assert_eq!(
+            err("[localhost]:3301"),
+            r#"invalid uri "[localhost]:3301": invalid ipv6 address"#
+        );
+        assert_eq!(
+            err("@localhost:3301"),
+            r#"invalid uri "@localhost:3301": empty login"#
+        );
+        assert_eq!(
+            err("unix/:"),
+            r#"invalid uri "unix/:": empty unix socket path"#
+        );
+        assert_eq!(
+            err("admin unix/:/tmp/t.sock"),
+            r#"invalid uri "admin unix/:/tmp/t.sock": unexpected characters before "unix/:""#
+        );
+    }
+
+    #[test]
+    fn display() {
+        for s in [
+            "localhost:3301",
+            "[::1]:3301",
+            "admin@example.org:3301",
+            "unix/:/tmp/t.sock",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("3301").to_string(), "localhost:3301");
+        assert_eq!(parse("/tmp/t.sock").to_string(), "unix/:/tmp/t.sock");
+        // The password is never shown
+        assert_eq!(
+            parse("admin:secret@localhost:3301").to_string(),
+            "admin@localhost:3301"
+        );
+    }
+
+    #[test]
+    fn debug() {
+        let debug = format!("{:?}", parse("admin:secret@localhost:3301"));
+        assert!(!debug.contains("secret"), "{}", debug);
+        assert!(debug.contains("<redacted>"), "{}", debug);
+        let debug = format!("{:?}", parse("admin@localhost:3301"));
+        assert!(debug.contains("password: None"), "{}", debug);
+    }
+}
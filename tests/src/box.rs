@@ -5,7 +5,9 @@ use std::collections::BTreeMap;
 use tarantool::index::{self, IndexOptions, IteratorType};
 use tarantool::sequence::Sequence;
 use tarantool::space::UpdateOps;
-use tarantool::space::{self, Field, Space, SpaceCreateOptions, SpaceEngineType, SystemSpace};
+use tarantool::space::{
+    self, Field, ForeignKeyAction, Space, SpaceCreateOptions, SpaceEngineType, SystemSpace,
+};
 use tarantool::tuple::Tuple;
 use tarantool::util::Value;
 use tarantool::{update, upsert};
@@ -29,17 +31,13 @@ pub fn space_cache_invalidated() {
     let space = Space::find_cached(SPACE_NAME).unwrap();
     space.drop().unwrap();
 
-    // `space` is invalid due to stale cache
-    let space = Space::find_cached(SPACE_NAME).unwrap();
-    let msg = space.get(&[1]).unwrap_err().to_string();
-    const HEAD: &str = "Tarantool error: NoSuchSpace: Space '";
-    const TAIL: &str = "' does not exist";
-    assert_eq!(&msg[..HEAD.len()], HEAD);
-    assert_eq!(&msg[msg.len() - TAIL.len()..], TAIL);
-
-    // refresh the cache
-    tarantool::space::clear_cache();
+    // cache is invalidated by the `_space` trigger
     assert!(Space::find_cached(SPACE_NAME).is_none());
+
+    // space with the same name is found again after it's recreated
+    let space = Space::builder(SPACE_NAME).create().unwrap();
+    assert_eq!(Space::find_cached(SPACE_NAME).unwrap().id(), space.id());
+    space.drop().unwrap();
 }
 
 pub fn space_get_system() {
@@ -68,16 +66,54 @@ pub fn index_cache_invalidated() {
     let index = space.index_cached(INDEX_NAME).unwrap();
     index.drop().unwrap();
 
-    // `index` is invalid due to stale cache
-    let index = space.index_cached(INDEX_NAME).unwrap();
-    assert_eq!(
-        index.get(&[1]).unwrap_err().to_string(),
-        format!("Tarantool error: NoSuchIndexID: No index #0 is defined in space '{SPACE_NAME}'")
-    );
-
-    // refresh the cache
-    tarantool::space::clear_cache();
+    // cache is invalidated by the `_index` trigger
     assert!(space.index_cached(INDEX_NAME).is_none());
+    space.drop().unwrap();
+}
+
+pub fn schema_version() {
+    let before = tarantool::schema::version().unwrap();
+    let space = Space::builder("test_schema_version_space")
+        .create()
+        .unwrap();
+    let after = tarantool::schema::version().unwrap();
+    assert!(after > before);
+    space.drop().unwrap();
+    assert!(tarantool::schema::version().unwrap() > after);
+}
+
+pub fn schema_on_change() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let count = Rc::new(Cell::new(0));
+    let guard = tarantool::schema::on_change({
+        let count = count.clone();
+        move || count.set(count.get() + 1)
+    })
+    .unwrap();
+
+    let space = Space::builder("test_schema_on_change_space")
+        .create()
+        .unwrap();
+    let after_create = count.get();
+    assert!(after_create > 0);
+
+    space.index_builder("pk").create().unwrap();
+    let after_index = count.get();
+    assert!(after_index > after_create);
+
+    space.drop().unwrap();
+    let after_drop = count.get();
+    assert!(after_drop > after_index);
+
+    // the callback isn't called after the trigger is removed
+    drop(guard);
+    let space = Space::builder("test_schema_on_change_space")
+        .create()
+        .unwrap();
+    space.drop().unwrap();
+    assert_eq!(count.get(), after_drop);
 }
 
 pub fn get() {
@@ -991,3 +1027,116 @@ pub fn index_parts() {
     );
     assert!(iter.next().is_none());
 }
+
+pub fn space_create_check_constraint() {
+    let space = Space::builder("ck_constraint_test")
+        .field(Field::unsigned("id"))
+        .field(Field::integer("balance"))
+        .check("non_negative_balance", r#""balance" >= 0"#)
+        .create()
+        .unwrap();
+    space.index_builder("pk").part("id").create().unwrap();
+
+    let sys_ck_constraint = Space::from(SystemSpace::CkConstraint);
+    let ck = sys_ck_constraint
+        .get(&(space.id(), "non_negative_balance"))
+        .unwrap();
+    assert!(ck.is_some());
+
+    space.insert(&(1, 10)).unwrap();
+    space.insert(&(2, -10)).unwrap_err();
+
+    tarantool::schema::constraint::drop_check_constraint(space.id(), "non_negative_balance")
+        .unwrap();
+    space.insert(&(2, -10)).unwrap();
+    tarantool::schema::constraint::drop_check_constraint(space.id(), "non_negative_balance")
+        .unwrap_err();
+
+    tarantool::schema::constraint::create_check_constraint(
+        space.id(),
+        &space::CheckConstraint::new("small_balance", r#""balance" < 100"#),
+    )
+    .unwrap();
+    space.insert(&(3, 1000)).unwrap_err();
+
+    drop_space("ck_constraint_test");
+    assert!(sys_ck_constraint
+        .get(&(space.id(), "small_balance"))
+        .unwrap()
+        .is_none());
+}
+
+pub fn space_create_foreign_key() {
+    let parent = Space::builder("fk_parent_test")
+        .field(Field::unsigned("id"))
+        .create()
+        .unwrap();
+    parent.index_builder("pk").part("id").create().unwrap();
+
+    let child = Space::builder("fk_child_test")
+        .field(Field::unsigned("id"))
+        .field(Field::unsigned("parent_id"))
+        .foreign_key(
+            "fk_child_parent",
+            ["parent_id"],
+            "fk_parent_test",
+            ["id"],
+            ForeignKeyAction::Cascade,
+        )
+        .check("positive_id", r#""id" > 0"#)
+        .create()
+        .unwrap();
+    child.index_builder("pk").part("id").create().unwrap();
+
+    let sys_fk_constraint = Space::from(SystemSpace::FkConstraint);
+    let fk = sys_fk_constraint
+        .get(&("fk_child_parent", child.id()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(fk.field::<u32>(2).unwrap(), Some(parent.id()));
+    assert_eq!(fk.field::<String>(5).unwrap().unwrap(), "cascade");
+    assert_eq!(fk.field::<Vec<u32>>(7).unwrap(), Some(vec![1]));
+    assert_eq!(fk.field::<Vec<u32>>(8).unwrap(), Some(vec![0]));
+
+    // Referenced field must exist in the parent space.
+    let res = Space::builder("fk_child_test_2")
+        .field(Field::unsigned("id"))
+        .foreign_key(
+            "fk_child_parent_2",
+            ["id"],
+            "fk_parent_test",
+            ["no_such_field"],
+            ForeignKeyAction::NoAction,
+        )
+        .create();
+    assert!(res.is_err());
+    // Space is not created if the constraint is invalid.
+    assert!(Space::find("fk_child_test_2").is_none());
+
+    // Check constraints are enforced for every insert.
+    assert!(child.insert(&(0, 1)).is_err());
+
+    // Foreign keys are enforced by sql statements.
+    let insert_child = |id: u32, parent_id: u32| -> Option<String> {
+        tarantool::lua_state()
+            .eval_with(
+                r#"local _, err = box.execute([[INSERT INTO "fk_child_test" VALUES (?, ?)]], {...})
+                return err and tostring(err)"#,
+                (id, parent_id),
+            )
+            .unwrap()
+    };
+    parent.insert(&(1,)).unwrap();
+    assert_eq!(insert_child(1, 1), None);
+    assert!(insert_child(2, 42).is_some());
+    assert!(child.get(&(2,)).unwrap().is_none());
+
+    tarantool::schema::constraint::drop_foreign_key(child.id(), "fk_child_parent").unwrap();
+    assert!(sys_fk_constraint
+        .get(&("fk_child_parent", child.id()))
+        .unwrap()
+        .is_none());
+
+    drop_space("fk_child_test");
+    drop_space("fk_parent_test");
+}
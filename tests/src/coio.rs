@@ -6,7 +6,7 @@ use std::os::unix::net::UnixStream;
 use std::time::Duration;
 
 use tarantool::coio::{self, channel, CoIOListener, CoIOStream, Receiver, Sender};
-use tarantool::fiber::{sleep, Fiber};
+use tarantool::fiber::{self, sleep, Fiber};
 
 pub fn coio_accept() {
     let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -106,3 +106,41 @@ pub fn channel_tx_closed() {
     fiber.start(rx);
     fiber.join();
 }
+
+pub fn run_blocking() {
+    let data = vec![1u64, 2, 3];
+    let thread = std::thread::current().id();
+    let (sum, other_thread) =
+        coio::run_blocking(|| (data.iter().sum::<u64>(), std::thread::current().id())).unwrap();
+    assert_eq!(sum, 6);
+    assert_ne!(thread, other_thread);
+}
+
+pub fn run_blocking_panic() {
+    let res = std::panic::catch_unwind(|| coio::run_blocking(|| panic!("oops")));
+    let e = res.unwrap_err();
+    assert_eq!(e.downcast_ref::<&str>(), Some(&"oops"));
+}
+
+pub fn spawn_blocking() {
+    let handle = coio::spawn_blocking(|| {
+        std::thread::sleep(Duration::from_millis(10));
+        42
+    });
+    // Other fibers keep running while the task is executed
+    let jh = fiber::start(|| 1);
+    assert_eq!(jh.join(), 1);
+    assert_eq!(fiber::block_on(handle).unwrap(), 42);
+
+    // Dropping the handle doesn't stop the task
+    let (tx, rx) = std::sync::mpsc::channel();
+    drop(coio::spawn_blocking(move || tx.send(13).unwrap()));
+    sleep(Duration::from_millis(100));
+    assert_eq!(rx.try_recv().unwrap(), 13);
+}
+
+pub fn spawn_blocking_panic() {
+    let handle = coio::spawn_blocking(|| panic!("oops"));
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| fiber::block_on(handle)));
+    assert!(res.is_err());
+}
@@ -0,0 +1,24 @@
+use tarantool::event;
+
+pub fn broadcast() {
+    if !tarantool::ffi::has_broadcast() {
+        return;
+    }
+
+    event::broadcast("test_event", &(13, "foo")).unwrap();
+
+    let (n, s): (i32, String) = tarantool::lua_state()
+        .eval(
+            "local fiber = require('fiber')
+            local value
+            local w = box.watch('test_event', function(_, v) value = v end)
+            local deadline = fiber.clock() + 3
+            while value == nil and fiber.clock() < deadline do
+                fiber.sleep(0.001)
+            end
+            w:unregister()
+            return value[1], value[2]",
+        )
+        .unwrap();
+    assert_eq!((n, s.as_str()), (13, "foo"));
+}
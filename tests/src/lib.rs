@@ -28,6 +28,7 @@ mod decimal;
 mod define_str_enum;
 mod enums;
 mod error;
+mod event;
 mod fiber;
 mod latch;
 mod log;
@@ -449,6 +450,8 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 r#box::index_get_by_name,
                 r#box::index_get_by_name_cached,
                 r#box::index_cache_invalidated,
+                r#box::schema_version,
+                r#box::schema_on_change,
                 r#box::insert,
                 r#box::replace,
                 r#box::delete,
@@ -480,6 +483,8 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 r#box::space_drop,
                 r#box::index_create_drop,
                 r#box::index_parts,
+                r#box::space_create_check_constraint,
+                r#box::space_create_foreign_key,
                 tuple::tuple_new_from_struct,
                 tuple::new_tuple_from_flatten_struct,
                 tuple::tuple_field_count,
@@ -510,8 +515,20 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 coio::coio_channel,
                 coio::channel_rx_closed,
                 coio::channel_tx_closed,
+                coio::run_blocking,
+                coio::run_blocking_panic,
+                coio::spawn_blocking,
+                coio::spawn_blocking_panic,
                 transaction::transaction_commit,
                 transaction::transaction_rollback,
+                transaction::guard_commit,
+                transaction::guard_rollback_on_drop,
+                transaction::guard_drop_other_txn,
+                transaction::savepoint,
+                transaction::on_commit_on_rollback,
+                transaction::retrying_on_conflict,
+                transaction::retrying_gives_up,
+                transaction::retrying_business_error,
                 log::log_with_user_defined_mapping,
                 #[should_panic]
                 log::zlog,
@@ -528,12 +545,22 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 net_box::call_timeout,
                 net_box::call_async_timeout,
                 net_box::call_async_wait_disconnected,
+                net_box::call_async_options_timeout,
+                net_box::call_async_cancel,
+                net_box::call_async_await,
+                net_box::batch_wait_all,
+                net_box::batch_completion_order,
+                net_box::batch_request_timeout,
+                net_box::batch_encode_error,
                 net_box::eval,
                 net_box::eval_async,
                 net_box::async_common_cond,
                 net_box::connection_error,
                 net_box::is_connected,
                 net_box::schema_sync,
+                net_box::space_format,
+                net_box::space_count_len_min_max,
+                net_box::space_api,
                 net_box::select,
                 net_box::get,
                 net_box::insert,
@@ -546,8 +573,22 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 net_box::triggers_reject,
                 net_box::triggers_schema_sync,
                 net_box::execute,
+                net_box::protocol_features,
+                net_box::stream_ids,
+                net_box::stream_transaction,
+                net_box::pool_modes,
+                net_box::pool_failover,
+                net_box::connect_uri,
+                net_box::connect_unix,
+                event::broadcast,
                 session::uid,
                 session::euid,
+                session::id,
+                session::user_peer_type,
+                session::push,
+                session::su,
+                session::storage,
+                session::triggers,
                 proc::simple,
                 proc::return_tuple,
                 proc::return_raw_bytes,
@@ -1,14 +1,21 @@
 #![allow(clippy::redundant_allocation)]
 use std::io;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tarantool::error::Error;
-use tarantool::fiber::{reschedule, sleep, start_proc, Cond, Fiber};
-use tarantool::index::IteratorType;
-use tarantool::net_box::{promise::State, Conn, ConnOptions, ConnTriggers, Options};
-use tarantool::space::Space;
-use tarantool::tuple::Tuple;
+use tarantool::fiber::{self, reschedule, sleep, start_proc, Cond, Fiber};
+use tarantool::index::{IndexApi, IteratorType};
+use tarantool::net_box::{
+    pool::{Mode, Pool, PoolOptions},
+    promise::State,
+    Conn, ConnOptions, ConnTriggers, Options, ProtocolFeature,
+};
+use tarantool::space::{FieldType, Space, SpaceApi};
+use tarantool::test::util::unix_socket_proxy;
+use tarantool::transaction::{TransactionOptions, TxnIsolation};
+use tarantool::tuple::{ToTupleBuffer, Tuple};
+use tarantool::util::NumOrStr;
 
 use crate::{
     common::{QueryOperation, S1Record, S2Record},
@@ -216,6 +223,167 @@ pub fn call_async_wait_disconnected() {
     jh.join();
 }
 
+pub fn call_async_options_timeout() {
+    let conn = test_user_conn();
+    let options = Options {
+        timeout: Some(Duration::from_millis(100)),
+        ..Options::default()
+    };
+    let p = conn
+        .call_async_with_options::<_, ()>("test_timeout", (), &options)
+        .unwrap();
+    assert_eq!(p.state(), State::Pending);
+    let p = p.wait_timeout(Duration::from_millis(10)).pending().unwrap();
+    let err = p.wait().unwrap_err();
+    assert!(matches!(err, Error::IO(ref e) if e.kind() == io::ErrorKind::TimedOut));
+
+    let p = conn
+        .call_async_with_options::<_, ()>("test_timeout", (), &options)
+        .unwrap();
+    sleep(Duration::from_millis(100));
+    assert_eq!(p.state(), State::TimedOut);
+    assert!(p.try_get().err().is_some());
+}
+
+pub fn call_async_cancel() {
+    let conn = test_user_conn();
+    let p = conn
+        .call_async::<_, (i32,)>("test_stored_proc", (1, 1))
+        .unwrap();
+    p.cancel();
+    // The response to the cancelled request is discarded and doesn't affect
+    // the subsequent requests
+    let p = conn
+        .call_async::<_, (i32,)>("test_stored_proc", (2, 2))
+        .unwrap();
+    assert_eq!(p.wait().unwrap(), (4,));
+}
+
+pub fn call_async_await() {
+    let conn = test_user_conn();
+    let p1 = conn
+        .call_async::<_, (i32,)>("test_stored_proc", (1, 2))
+        .unwrap();
+    let p2 = conn
+        .call_async::<_, (i32,)>("test_stored_proc", (3, 4))
+        .unwrap();
+    let (r1, r2) = fiber::block_on(async { fiber::r#async::join!(p1, p2) });
+    assert_eq!(r1.unwrap(), (3,));
+    assert_eq!(r2.unwrap(), (7,));
+
+    let p = conn
+        .call_async_with_options::<_, ()>(
+            "test_timeout",
+            (),
+            &Options {
+                timeout: Some(Duration::from_millis(10)),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+    let err = fiber::block_on(p).unwrap_err();
+    assert!(matches!(err, Error::IO(ref e) if e.kind() == io::ErrorKind::TimedOut));
+}
+
+pub fn batch_wait_all() {
+    let conn = test_user_conn();
+    let mut batch = conn.batch::<(i32,)>();
+    batch
+        .call("test_stored_proc", (1, 2))
+        .call("Procedure is not defined", ())
+        .eval("return ...", (3,));
+    assert_eq!(batch.len(), 3);
+    let results = batch.send(&Options::default()).unwrap();
+    assert_eq!(results.len(), 3);
+    let results = results.wait_all(Duration::from_secs(1));
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &(3,));
+    assert_eq!(
+        results[1].as_ref().unwrap_err().to_string(),
+        "Server responded with error: Procedure 'Procedure is not defined' is not defined"
+    );
+    assert_eq!(results[2].as_ref().unwrap(), &(3,));
+
+    let mut batch = conn.batch::<()>();
+    batch.call("test_timeout", ());
+    let results = batch
+        .send(&Options::default())
+        .unwrap()
+        .wait_all(Duration::from_millis(10));
+    assert!(matches!(results[0], Err(Error::IO(ref e)) if e.kind() == io::ErrorKind::TimedOut));
+}
+
+pub fn batch_completion_order() {
+    let conn = test_user_conn();
+    let expr = "require'fiber'.sleep(...) return ...";
+    let mut batch = conn.batch::<(f64,)>();
+    batch
+        .eval(expr, (0.3,))
+        .eval(expr, (0.1,))
+        .eval(expr, (0.2,));
+    let mut results = batch.send(&Options::default()).unwrap();
+    assert_eq!(results.pending(), 3);
+    assert!(results.next_timeout(Duration::from_millis(10)).is_none());
+    assert_eq!(results.pending(), 3);
+    let order: Vec<_> = results.map(|(i, res)| (i, res.unwrap().0)).collect();
+    assert_eq!(order, vec![(1, 0.1), (2, 0.2), (0, 0.3)]);
+}
+
+pub fn batch_request_timeout() {
+    let conn = test_user_conn();
+    let mut batch = conn.batch::<()>();
+    batch.call("test_timeout", ()).call("test_timeout", ());
+    let options = Options {
+        timeout: Some(Duration::from_millis(10)),
+        ..Options::default()
+    };
+    let results = batch.send(&options).unwrap();
+    let start = Instant::now();
+    let results: Vec<_> = results.collect();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(results.len(), 2);
+    for (_, res) in &results {
+        assert!(matches!(res, Err(Error::IO(ref e)) if e.kind() == io::ErrorKind::TimedOut));
+    }
+
+    let mut batch = conn.batch::<()>();
+    batch.call("test_timeout", ());
+    let results = batch
+        .send(&options)
+        .unwrap()
+        .wait_all(Duration::from_secs(10));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(matches!(results[0], Err(Error::IO(ref e)) if e.kind() == io::ErrorKind::TimedOut));
+}
+
+pub fn batch_encode_error() {
+    struct FailingArgs;
+
+    impl ToTupleBuffer for FailingArgs {
+        fn write_tuple_data(&self, w: &mut impl io::Write) -> tarantool::Result<()> {
+            // Some data is written before the failure
+            w.write_all(b"\x92\x01")?;
+            Err(io::Error::new(io::ErrorKind::Other, "oops").into())
+        }
+    }
+
+    let conn = test_user_conn();
+    conn.ping(&Options::default()).unwrap();
+    let mut batch = conn.batch::<(i32,)>();
+    batch
+        .call("test_stored_proc", (1, 2))
+        .call("test_stored_proc", FailingArgs)
+        .call("test_stored_proc", (3, 4));
+    let err = batch.send(&Options::default()).err().unwrap();
+    assert!(matches!(err, Error::IO(ref e) if e.to_string() == "oops"));
+
+    // None of the batch's data is sent, so the connection is still usable
+    let res = conn
+        .call("test_stored_proc", &(5, 6), &Options::default())
+        .unwrap();
+    assert_eq!(res.unwrap().decode::<(i32,)>().unwrap(), (11,));
+}
+
 pub fn eval() {
     let conn = test_user_conn();
     let result = conn
@@ -346,6 +514,129 @@ pub fn get() {
     );
 }
 
+pub fn space_format() {
+    let conn = test_user_conn();
+    let space = conn.space("test_s2").unwrap().unwrap();
+
+    let format = space.format().unwrap();
+    let names: Vec<_> = format.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["id", "key", "value", "a", "b"]);
+    assert_eq!(format[0].field_type, FieldType::Unsigned);
+    assert_eq!(format[3].field_type, FieldType::Integer);
+    assert_eq!(space.field_no("value").unwrap(), Some(2));
+    assert_eq!(space.field_no("no such field").unwrap(), None);
+
+    let idx = space.index("idx_2").unwrap().unwrap();
+    let fields: Vec<_> = idx
+        .parts()
+        .unwrap()
+        .into_iter()
+        .map(|part| match part.field {
+            NumOrStr::Num(field_no) => field_no,
+            NumOrStr::Str(name) => panic!("unexpected field name {}", name),
+        })
+        .collect();
+    assert_eq!(fields, [1, 4, 5]);
+
+    let tuple = space.get(&(16,), &Options::default()).unwrap().unwrap();
+    let key: Option<String> = space.get_field(&tuple, "key").unwrap();
+    assert_eq!(key.as_deref(), Some("key_16"));
+    let b: Option<i32> = space.get_field(&tuple, "b").unwrap();
+    assert_eq!(b, Some(3));
+    let none: Option<i32> = space.get_field(&tuple, "no such field").unwrap();
+    assert_eq!(none, None);
+}
+
+pub fn space_count_len_min_max() {
+    let conn = test_user_conn();
+    let space = conn.space("test_s2").unwrap().unwrap();
+    let options = Options::default();
+
+    assert_eq!(space.len(&options).unwrap(), 20);
+    assert_eq!(space.count(IteratorType::All, &(), &options).unwrap(), 20);
+    assert_eq!(space.count(IteratorType::GE, &(10,), &options).unwrap(), 11);
+
+    let idx = space.index("idx_3").unwrap().unwrap();
+    assert_eq!(idx.count(IteratorType::Eq, &(0,), &options).unwrap(), 4);
+
+    let min = space.min(&(), &options).unwrap().unwrap();
+    assert_eq!(min.decode::<S2Record>().unwrap().id, 1);
+    let max = space.max(&(), &options).unwrap().unwrap();
+    assert_eq!(max.decode::<S2Record>().unwrap().id, 20);
+    let max = idx.max(&(0,), &options).unwrap().unwrap();
+    assert_eq!(max.decode::<S2Record>().unwrap().a, 0);
+}
+
+/// Exercises the CRUD methods common for local and remote spaces.
+fn check_space_api(space: &impl SpaceApi) {
+    let input = S1Record {
+        id: 1,
+        text: "Original".to_string(),
+    };
+    let res = space.insert(&input).unwrap().unwrap();
+    assert_eq!(res.decode::<S1Record>().unwrap(), input);
+    space
+        .replace(&S1Record {
+            id: 2,
+            text: "Replaced".to_string(),
+        })
+        .unwrap();
+
+    let res = space
+        .update(
+            &(1,),
+            &[QueryOperation {
+                op: "=".to_string(),
+                field_id: 1,
+                value: "Updated".into(),
+            }],
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(res.decode::<S1Record>().unwrap().text, "Updated");
+
+    space
+        .upsert(
+            &(3, "Inserted"),
+            &[QueryOperation {
+                op: "=".to_string(),
+                field_id: 1,
+                value: "Upserted".into(),
+            }],
+        )
+        .unwrap();
+    let res = space.get(&(3,)).unwrap().unwrap();
+    assert_eq!(res.decode::<S1Record>().unwrap().text, "Inserted");
+
+    let ids: Vec<u32> = space
+        .select(IteratorType::GE, &(2,))
+        .unwrap()
+        .map(|t| t.decode::<S1Record>().unwrap().id)
+        .collect();
+    assert_eq!(ids, [2, 3]);
+    assert_eq!(space.count(IteratorType::All, &()).unwrap(), 3);
+
+    let res = space.delete(&(2,)).unwrap().unwrap();
+    assert_eq!(res.decode::<S1Record>().unwrap().text, "Replaced");
+    assert!(space.get(&(2,)).unwrap().is_none());
+
+    let index = space.index("primary").unwrap().unwrap();
+    assert!(index.get(&(1,)).unwrap().is_some());
+    assert_eq!(index.count(IteratorType::All, &()).unwrap(), 2);
+    assert!(space.index("no_such_index").unwrap().is_none());
+}
+
+pub fn space_api() {
+    let local_space = Space::find("test_s1").unwrap();
+    local_space.truncate().unwrap();
+    check_space_api(&local_space);
+
+    local_space.truncate().unwrap();
+    let conn = test_user_conn();
+    let remote_space = conn.space("test_s1").unwrap().unwrap();
+    check_space_api(&remote_space);
+}
+
 pub fn select() {
     let conn = test_user_conn();
     let space = conn.space("test_s2").unwrap().unwrap();
@@ -659,3 +950,210 @@ pub fn triggers_schema_sync() {
 
     assert_eq!(is_trigger_called.get(), true);
 }
+
+pub fn protocol_features() {
+    let conn = default_conn();
+    let features = conn.protocol_features().unwrap();
+    assert_ne!(features.version, 0);
+    assert!(features.supports(ProtocolFeature::Streams));
+    assert!(features.supports(ProtocolFeature::Transactions));
+}
+
+pub fn stream_ids() {
+    let conn = test_user_conn();
+    let s1 = conn.new_stream();
+    let s2 = conn.new_stream();
+    assert_ne!(s1.id(), 0);
+    assert_ne!(s1.id(), s2.id());
+}
+
+pub fn stream_transaction() {
+    let local_space = Space::find("test_s1").unwrap();
+    local_space.truncate().unwrap();
+    local_space.insert(&(1, "One")).unwrap();
+
+    let conn = test_user_conn();
+    let stream = conn.new_stream();
+    let is_in_txn = |stream: &tarantool::net_box::RemoteStream| {
+        stream
+            .eval("return box.is_in_txn()", &(), &Options::default())
+            .unwrap()
+            .unwrap()
+            .decode::<(bool,)>()
+            .unwrap()
+            .0
+    };
+    assert!(!stream.is_in_txn());
+    assert!(!is_in_txn(&stream));
+
+    let txn_options = TransactionOptions {
+        isolation: Some(TxnIsolation::ReadCommitted),
+        timeout: Some(Duration::from_secs(10)),
+    };
+    stream.begin(&txn_options, &Options::default()).unwrap();
+    assert!(stream.is_in_txn());
+    assert!(is_in_txn(&stream));
+
+    // Requests outside of the stream aren't part of the transaction
+    let result = conn
+        .eval("return box.is_in_txn()", &(), &Options::default())
+        .unwrap();
+    assert_eq!(result.unwrap().decode::<(bool,)>().unwrap(), (false,));
+
+    let remote_space = stream.space("test_s1").unwrap().unwrap();
+    let output = remote_space.get(&(1,), &Options::default()).unwrap();
+    assert_eq!(output.unwrap().decode::<(i32, String)>().unwrap().1, "One");
+    remote_space
+        .insert(&(2, "Two"), &Options::default())
+        .unwrap();
+
+    stream.rollback(&Options::default()).unwrap();
+    assert!(!stream.is_in_txn());
+    assert!(!is_in_txn(&stream));
+
+    // The write made in the transaction is rolled back
+    let output = remote_space.get(&(2,), &Options::default()).unwrap();
+    assert!(output.is_none());
+    let other_conn = test_user_conn();
+    let other_space = other_conn.space("test_s1").unwrap().unwrap();
+    let output = other_space.get(&(2,), &Options::default()).unwrap();
+    assert!(output.is_none());
+    assert!(local_space.get(&(2,)).unwrap().is_none());
+
+    stream
+        .begin(&TransactionOptions::default(), &Options::default())
+        .unwrap();
+    assert!(is_in_txn(&stream));
+    stream.commit(&Options::default()).unwrap();
+    assert!(!is_in_txn(&stream));
+}
+
+fn test_user_pool(addrs: &[String]) -> Pool {
+    Pool::new(
+        addrs,
+        PoolOptions {
+            conn_options: ConnOptions {
+                user: "test_user".into(),
+                password: "password".into(),
+                connect_timeout: Duration::from_millis(100),
+                ..ConnOptions::default()
+            },
+            health_check_timeout: Duration::from_millis(100),
+            ..PoolOptions::default()
+        },
+    )
+    .unwrap()
+}
+
+pub fn pool_modes() {
+    let addr = format!("localhost:{}", unsafe { LISTEN });
+    let pool = test_user_pool(&[addr.clone()]);
+
+    let status = pool.status();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].addr, addr);
+    assert!(status[0].is_healthy);
+    assert_eq!(status[0].is_ro, Some(false));
+
+    let conn = pool.conn(Mode::Rw).unwrap();
+    assert_eq!(conn.addr(), addr);
+    assert_eq!(pool.status()[0].pending, 1);
+    drop(conn);
+    assert_eq!(pool.status()[0].pending, 0);
+
+    // The local instance is writable
+    assert!(pool.conn(Mode::Any).is_ok());
+    assert!(pool.conn(Mode::PreferRo).is_ok());
+    match pool.conn(Mode::Ro) {
+        Err(Error::IO(e)) => assert_eq!(e.kind(), io::ErrorKind::NotConnected),
+        _ => panic!("no read-only nodes expected"),
+    }
+
+    let result = pool
+        .eval(Mode::Rw, "return ...", &(1, 2), &Options::default())
+        .unwrap();
+    assert_eq!(result.unwrap().decode::<(i32, i32)>().unwrap(), (1, 2));
+    // The password isn't shown in the node's address
+    let pool = test_user_pool(&[format!("test_user:password@{}", addr)]);
+    let expected = format!("test_user@{}", addr);
+    assert_eq!(pool.status()[0].addr, expected);
+    assert_eq!(pool.conn(Mode::Rw).unwrap().addr(), expected);
+}
+
+pub fn pool_failover() {
+    let alive = format!("localhost:{}", unsafe { LISTEN });
+    // Nothing listens on the port once the listener is dropped
+    let dead = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("127.0.0.1:{}", listener.local_addr().unwrap().port())
+    };
+    let pool = test_user_pool(&[dead.clone(), alive.clone()]);
+
+    let status = pool.status();
+    assert!(!status[0].is_healthy);
+    assert_eq!(status[0].failures, 1);
+    assert!(status[1].is_healthy);
+
+    // Only the healthy node is ever chosen
+    for _ in 0..3 {
+        let result = pool
+            .with_failover(Mode::Any, |conn| {
+                conn.eval("return 42", &(), &Options::default())
+            })
+            .unwrap();
+        assert_eq!(result.unwrap().decode::<(i32,)>().unwrap(), (42,));
+        assert_eq!(pool.conn(Mode::Any).unwrap().addr(), alive);
+    }
+
+    // Network errors make the pool try the next node
+    let mut tried = vec![];
+    let result = pool.with_failover(Mode::Any, |conn| {
+        tried.push(conn.addr().to_string());
+        Err::<(), _>(io::Error::from(io::ErrorKind::ConnectionReset).into())
+    });
+    assert!(matches!(result, Err(Error::IO(_))));
+    assert_eq!(tried, vec![alive]);
+}
+
+fn session_user(conn: &Conn) -> String {
+    let user = conn
+        .eval("return box.session.user()", &(), &Options::default())
+        .unwrap()
+        .unwrap();
+    user.decode::<(String,)>().unwrap().0
+}
+
+pub fn connect_uri() {
+    let uri = format!("test_user:password@localhost:{}", unsafe { LISTEN });
+    let conn = Conn::from_uri(&uri, ConnOptions::default(), None).unwrap();
+    assert_eq!(session_user(&conn), "test_user");
+
+    // The credentials from the uri take precedence over the options
+    let options = ConnOptions {
+        user: "no_such_user".into(),
+        password: "wrong".into(),
+        ..ConnOptions::default()
+    };
+    let conn = Conn::from_uri(&uri, options, None).unwrap();
+    assert_eq!(session_user(&conn), "test_user");
+
+    match Conn::from_uri("localhost", ConnOptions::default(), None) {
+        Err(Error::IO(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+        _ => panic!("invalid uri expected"),
+    }
+}
+
+pub fn connect_unix() {
+    let path = unix_socket_proxy(unsafe { LISTEN });
+    let uri = format!("test_user:password@unix/:{}", path.display());
+    let conn = Conn::from_uri(&uri, ConnOptions::default(), None).unwrap();
+    assert_eq!(session_user(&conn), "test_user");
+    assert!(conn.space("test_s1").unwrap().is_some());
+
+    let pool = test_user_pool(&[format!("unix/:{}", path.display())]);
+    assert!(pool.status()[0].is_healthy);
+    let result = pool
+        .eval(Mode::Any, "return 42", &(), &Options::default())
+        .unwrap();
+    assert_eq!(result.unwrap().decode::<(i32,)>().unwrap(), (42,));
+}
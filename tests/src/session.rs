@@ -1,4 +1,13 @@
-use tarantool::session;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use tarantool::fiber;
+use tarantool::net_box::{Conn, ConnOptions, Options};
+use tarantool::session::{self, SessionType};
+use tarantool::tlua;
+
+use crate::LISTEN;
 
 pub fn uid() {
     let uid = session::uid().unwrap();
@@ -9,3 +18,147 @@ pub fn euid() {
     let euid = session::euid().unwrap();
     assert_eq!(euid, 1);
 }
+
+pub fn id() {
+    let id = session::id().unwrap();
+    assert!(session::exists(id).unwrap());
+    assert!(!session::exists(u32::MAX as _).unwrap());
+}
+
+pub fn user_peer_type() {
+    assert_eq!(session::user().unwrap(), "admin");
+    assert_eq!(session::peer().unwrap(), None);
+    assert_eq!(session::r#type().unwrap(), SessionType::Background);
+}
+
+pub fn push() {
+    // background sessions don't support pushes
+    assert!(session::push(&1).is_err());
+
+    let lua = tarantool::lua_state();
+    lua.set(
+        "test_session_push",
+        tlua::function1(|v: i32| session::push(&v).is_ok()),
+    );
+    let pushed: Vec<i32> = lua
+        .eval_with(
+            "local conn = require('net.box').connect(...)
+            local pushed = {}
+            local on_push = function(_, v) table.insert(pushed, v) end
+            assert(conn:call('test_session_push', {1}, {on_push = on_push}))
+            assert(conn:call('test_session_push', {2}, {on_push = on_push}))
+            conn:close()
+            return pushed",
+            format!("test_user:password@localhost:{}", unsafe { LISTEN }),
+        )
+        .unwrap();
+    lua.set("test_session_push", tlua::Nil);
+    assert_eq!(pushed, [1, 2]);
+}
+
+pub fn su() {
+    let (euid, user) = session::su("guest", || {
+        (session::euid().unwrap(), session::user().unwrap())
+    })
+    .unwrap();
+    assert_eq!(euid, 0);
+    assert_eq!(user, "guest");
+    assert_eq!(session::euid().unwrap(), 1);
+
+    assert!(session::su("no_such_user", || unreachable!()).is_err());
+}
+
+pub fn storage() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counter(u32);
+
+    assert_eq!(session::storage_get::<Counter>().unwrap(), None);
+    assert_eq!(session::storage_set(Counter(1)).unwrap(), None);
+    session::storage_with(|c: Option<&mut Counter>| c.unwrap().0 += 1).unwrap();
+    assert_eq!(session::storage_get().unwrap(), Some(Counter(2)));
+    // Values of different types don't interfere
+    session::storage_set(String::from("hello")).unwrap();
+    assert_eq!(session::storage_set(Counter(3)).unwrap(), Some(Counter(2)));
+    assert_eq!(session::storage_take().unwrap(), Some(Counter(3)));
+    assert_eq!(session::storage_get::<Counter>().unwrap(), None);
+    assert_eq!(
+        session::storage_take().unwrap(),
+        Some(String::from("hello"))
+    );
+}
+
+pub fn triggers() {
+    struct DropFlag(Rc<Cell<bool>>);
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.set(true)
+        }
+    }
+
+    let connected = Rc::new(Cell::new(0));
+    let disconnected = Rc::new(Cell::new(0));
+    let auths = Rc::new(RefCell::new(vec![]));
+    let dropped = Rc::new(Cell::new(false));
+
+    let on_connect = session::on_connect({
+        let connected = connected.clone();
+        let dropped = dropped.clone();
+        move || {
+            if matches!(session::r#type(), Ok(SessionType::Binary)) {
+                connected.set(connected.get() + 1);
+            }
+            session::storage_set(DropFlag(dropped.clone())).unwrap();
+        }
+    })
+    .unwrap();
+    let on_disconnect = session::on_disconnect({
+        let disconnected = disconnected.clone();
+        move || disconnected.set(disconnected.get() + 1)
+    })
+    .unwrap();
+    let on_auth = session::on_auth({
+        let auths = auths.clone();
+        move |user, success| auths.borrow_mut().push((user.to_string(), success))
+    })
+    .unwrap();
+
+    let conn = Conn::new(
+        ("localhost", unsafe { LISTEN }),
+        ConnOptions {
+            user: "test_user".into(),
+            password: "password".into(),
+            ..ConnOptions::default()
+        },
+        None,
+    )
+    .unwrap();
+    conn.ping(&Options::default()).unwrap();
+    assert_eq!(connected.get(), 1);
+    assert_eq!(&*auths.borrow(), &[("test_user".to_string(), true)]);
+    assert!(!dropped.get());
+
+    conn.close();
+    for _ in 0..100 {
+        if disconnected.get() > 0 {
+            break;
+        }
+        fiber::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(disconnected.get(), 1);
+    assert!(dropped.get());
+
+    // the callbacks aren't called after the triggers are removed
+    drop((on_connect, on_disconnect, on_auth));
+    let conn = Conn::new(
+        ("localhost", unsafe { LISTEN }),
+        ConnOptions::default(),
+        None,
+    )
+    .unwrap();
+    conn.ping(&Options::default()).unwrap();
+    conn.close();
+    fiber::sleep(Duration::from_millis(100));
+    assert_eq!(connected.get(), 1);
+    assert_eq!(disconnected.get(), 1);
+    assert_eq!(auths.borrow().len(), 1);
+}
@@ -1,8 +1,14 @@
+use std::cell::Cell;
 use std::io;
+use std::rc::Rc;
 
-use tarantool::error::Error;
+use tarantool::error::{Error, TarantoolError, TarantoolErrorCode, TransactionError};
+use tarantool::set_error;
 use tarantool::space::Space;
-use tarantool::transaction::start_transaction;
+use tarantool::transaction::{
+    is_in_txn, retrying, start_transaction, RetryError, RetryPolicy, Transaction,
+    TransactionOptions,
+};
 
 use crate::common::S1Record;
 
@@ -42,3 +48,194 @@ pub fn transaction_rollback() {
     let output = space.get(&(1,)).unwrap();
     assert!(output.is_none());
 }
+
+pub fn guard_commit() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    assert!(!is_in_txn());
+    let txn = Transaction::begin().unwrap();
+    assert!(is_in_txn());
+    assert!(matches!(
+        Transaction::begin(),
+        Err(Error::Transaction(TransactionError::AlreadyStarted))
+    ));
+    space.insert(&(1, "test")).unwrap();
+    txn.commit().unwrap();
+    assert!(!is_in_txn());
+
+    assert!(space.get(&(1,)).unwrap().is_some());
+}
+
+pub fn guard_rollback_on_drop() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    {
+        let _txn = Transaction::begin().unwrap();
+        space.insert(&(1, "test")).unwrap();
+    }
+    assert!(!is_in_txn());
+    assert!(space.get(&(1,)).unwrap().is_none());
+
+    let txn = Transaction::begin_with(&TransactionOptions::default()).unwrap();
+    space.insert(&(1, "test")).unwrap();
+    txn.rollback().unwrap();
+    assert!(space.get(&(1,)).unwrap().is_none());
+}
+
+pub fn guard_drop_other_txn() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let txn = Transaction::begin().unwrap();
+    space.insert(&(1, "test")).unwrap();
+    // the transaction is finished behind the guard's back
+    tarantool::lua_state().exec("box.commit()").unwrap();
+    // a new one is started
+    let other = Transaction::begin().unwrap();
+    space.insert(&(2, "test")).unwrap();
+    // the new transaction isn't affected when the guard is dropped
+    drop(txn);
+    assert!(is_in_txn());
+    other.commit().unwrap();
+
+    assert!(space.get(&(1,)).unwrap().is_some());
+    assert!(space.get(&(2,)).unwrap().is_some());
+}
+
+pub fn savepoint() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let txn = Transaction::begin().unwrap();
+    space.insert(&(1, "one")).unwrap();
+    let sp1 = txn.savepoint().unwrap();
+    space.insert(&(2, "two")).unwrap();
+    let sp2 = txn.savepoint().unwrap();
+    space.insert(&(3, "three")).unwrap();
+
+    txn.rollback_to(&sp2).unwrap();
+    assert!(space.get(&(3,)).unwrap().is_none());
+    assert!(space.get(&(2,)).unwrap().is_some());
+
+    txn.rollback_to(&sp1).unwrap();
+    assert!(space.get(&(2,)).unwrap().is_none());
+    txn.commit().unwrap();
+
+    assert!(space.get(&(1,)).unwrap().is_some());
+    assert_eq!(space.len().unwrap(), 1);
+}
+
+pub fn on_commit_on_rollback() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let committed = Rc::new(Cell::new(false));
+    let rolled_back = Rc::new(Cell::new(false));
+
+    let txn = Transaction::begin().unwrap();
+    space.insert(&(1, "test")).unwrap();
+    txn.on_commit({
+        let committed = committed.clone();
+        move || committed.set(true)
+    })
+    .unwrap();
+    txn.on_rollback({
+        let rolled_back = rolled_back.clone();
+        move || rolled_back.set(true)
+    })
+    .unwrap();
+    txn.commit().unwrap();
+    assert!(committed.get());
+    assert!(!rolled_back.get());
+
+    committed.set(false);
+    let txn = Transaction::begin().unwrap();
+    space.insert(&(2, "test")).unwrap();
+    txn.on_commit({
+        let committed = committed.clone();
+        move || committed.set(true)
+    })
+    .unwrap();
+    txn.on_rollback({
+        let rolled_back = rolled_back.clone();
+        move || rolled_back.set(true)
+    })
+    .unwrap();
+    drop(txn);
+    assert!(!committed.get());
+    assert!(rolled_back.get());
+}
+
+fn conflict() -> Error {
+    set_error!(TarantoolErrorCode::TransactionConflict, "conflict");
+    TarantoolError::last().into()
+}
+
+pub fn retrying_on_conflict() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        ..Default::default()
+    };
+    let mut attempts = 0;
+    let res = retrying(&policy, || -> Result<u32, Error> {
+        attempts += 1;
+        space.insert(&(attempts, "test"))?;
+        if attempts < 3 {
+            return Err(conflict());
+        }
+        Ok(attempts)
+    });
+    assert_eq!(res.unwrap(), 3);
+    // Only the last attempt is committed
+    assert_eq!(space.len().unwrap(), 1);
+    assert!(space.get(&(3,)).unwrap().is_some());
+}
+
+pub fn retrying_gives_up() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let policy = RetryPolicy {
+        max_attempts: 2,
+        ..Default::default()
+    };
+    let mut attempts = 0;
+    let res = retrying(&policy, || -> Result<(), Error> {
+        attempts += 1;
+        space.insert(&(attempts, "test"))?;
+        Err(conflict())
+    });
+    match res {
+        Err(RetryError::TooManyConflicts { attempts, error }) => {
+            assert_eq!(attempts, 2);
+            assert_eq!(
+                error.to_string(),
+                "Tarantool error: TransactionConflict: conflict"
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(attempts, 2);
+    assert!(!is_in_txn());
+    assert_eq!(space.len().unwrap(), 0);
+}
+
+pub fn retrying_business_error() {
+    let space = Space::find("test_s1").unwrap();
+    space.truncate().unwrap();
+
+    let mut attempts = 0;
+    let res = retrying(&RetryPolicy::default(), || -> Result<(), Error> {
+        attempts += 1;
+        space.insert(&(1, "test"))?;
+        Err(Error::IO(io::ErrorKind::Interrupted.into()))
+    });
+    assert!(matches!(res, Err(RetryError::Other(Error::IO(_)))));
+    assert_eq!(attempts, 1);
+    assert_eq!(space.len().unwrap(), 0);
+}